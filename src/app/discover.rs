@@ -0,0 +1,52 @@
+use crate::app::sources::{self, Source};
+use crate::app::{AppError, RunConfig, parser};
+
+/// What one input history contains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct HistorySummary {
+    pub(super) path: String,
+    pub(super) format: &'static str,
+    pub(super) entries: usize,
+    /// Oldest and newest entry timestamps, when any entry has one
+    pub(super) first: Option<i64>,
+    pub(super) last: Option<i64>,
+}
+
+/// Detect the format of every input history and count its entries.
+///
+/// Time filters do not apply: the summary describes the files as they
+/// are.
+pub(super) fn collect_summaries(
+    config: &RunConfig
+) -> Result<Vec<HistorySummary>, AppError> {
+    let sources = sources::detect_sources(config)?;
+    let mut summaries: Vec<HistorySummary> =
+        sources.iter().map(empty_summary).collect();
+
+    parser::for_each_source_entry(
+        &sources,
+        config.no_hist,
+        |source, entry| {
+            let summary = &mut summaries[source];
+            summary.entries += 1;
+            if let Some(timestamp) = entry.timestamp {
+                summary.first =
+                    Some(summary.first.map_or(timestamp, |t| t.min(timestamp)));
+                summary.last =
+                    Some(summary.last.map_or(timestamp, |t| t.max(timestamp)));
+            }
+        },
+    )?;
+
+    Ok(summaries)
+}
+
+fn empty_summary(source: &Source) -> HistorySummary {
+    HistorySummary {
+        path: source.path.clone(),
+        format: source.format.name(),
+        entries: 0,
+        first: None,
+        last: None,
+    }
+}
@@ -0,0 +1,155 @@
+use ahash::AHashMap;
+
+use crate::app::parser;
+use crate::app::{
+    AppError, EntryFilter, RunConfig, Verdict, ensure_timestamps, output_limit,
+};
+use crate::history::simple_history::{
+    filtered_commands, for_each_entry_command_name,
+};
+
+/// Wall time statistics of one command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct CommandDurations {
+    pub(super) name: String,
+    pub(super) count: usize,
+    pub(super) total: u64,
+    pub(super) median: u64,
+}
+
+/// Rank commands by the total wall time recorded for their entries.
+///
+/// An entry's duration is charged to its first command name, so the
+/// `tee` in `cargo build | tee log` does not count the build twice.
+/// Entries without a recorded duration are ignored.
+pub(super) fn collect_durations(
+    config: &RunConfig
+) -> Result<Vec<CommandDurations>, AppError> {
+    let filter = EntryFilter::new(config);
+    let filtered_commands = filtered_commands(&config.ignore, config.no_hist);
+    let mut durations: AHashMap<String, Vec<u64>> = AHashMap::default();
+    let mut missing_timestamps = 0;
+    let mut timed_entries = 0;
+
+    parser::for_each_entry(config, |entry| {
+        match filter.check(entry) {
+            Verdict::Keep => {}
+            Verdict::Skip => return,
+            Verdict::MissingTimestamp => {
+                missing_timestamps += 1;
+                return;
+            }
+        }
+        let Some(duration) = entry.duration else {
+            return;
+        };
+        timed_entries += 1;
+
+        let mut first = None;
+        for_each_entry_command_name(
+            entry,
+            &filtered_commands,
+            config.no_hist,
+            |name| {
+                first.get_or_insert(name);
+            },
+        );
+        if let Some(name) = first {
+            match durations.get_mut(name) {
+                Some(samples) => samples.push(duration),
+                None => {
+                    durations.insert(name.to_string(), vec![duration]);
+                }
+            }
+        }
+    })?;
+
+    ensure_timestamps(&config.input_label(), missing_timestamps)?;
+    if timed_entries == 0 {
+        return Err(AppError::MissingDurations { path: config.input_label() });
+    }
+
+    let mut commands = rank_durations(durations, config.more_than);
+    commands.truncate(output_limit(commands.len(), config.all, config.count));
+    Ok(commands)
+}
+
+fn rank_durations(
+    durations: AHashMap<String, Vec<u64>>,
+    more_than: usize,
+) -> Vec<CommandDurations> {
+    let mut commands: Vec<CommandDurations> = durations
+        .into_iter()
+        .filter(|(_, samples)| samples.len() > more_than)
+        .map(|(name, mut samples)| CommandDurations {
+            name,
+            count: samples.len(),
+            // A corrupt elapsed field can hold any value
+            total: samples
+                .iter()
+                .fold(0_u64, |total, sample| total.saturating_add(*sample)),
+            median: median(&mut samples),
+        })
+        .collect();
+
+    commands.sort_unstable_by(|a, b| {
+        b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name))
+    });
+    commands
+}
+
+/// Median of `samples`, rounded down between the two middle values
+fn median(samples: &mut [u64]) -> u64 {
+    if samples.is_empty() {
+        return 0;
+    }
+    samples.sort_unstable();
+    let mid = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        let (a, b) = (samples[mid - 1], samples[mid]);
+        a / 2 + b / 2 + (a % 2 + b % 2) / 2
+    } else {
+        samples[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), 0);
+        assert_eq!(median(&mut [9, 1, 5]), 5);
+        assert_eq!(median(&mut [4, 1, 10, 3]), 3);
+        assert_eq!(median(&mut [u64::MAX, u64::MAX]), u64::MAX);
+        assert_eq!(median(&mut [u64::MAX, u64::MAX - 2]), u64::MAX - 1);
+    }
+
+    #[test]
+    fn test_rank_durations_by_total_time() {
+        let mut durations = AHashMap::default();
+        durations.insert("ls".to_string(), vec![0, 0, 0, 1]);
+        durations.insert("cargo".to_string(), vec![40, 30, 600]);
+        durations.insert("make".to_string(), vec![700]);
+
+        let commands = rank_durations(durations, 0);
+        assert_eq!(commands[0].name, "make");
+        assert_eq!(commands[1].name, "cargo");
+        assert_eq!(commands[1].count, 3);
+        assert_eq!(commands[1].total, 670);
+        assert_eq!(commands[1].median, 40);
+        assert_eq!(commands[2].name, "ls");
+    }
+
+    #[test]
+    fn test_rank_durations_more_than() {
+        let mut durations = AHashMap::default();
+        durations.insert("ls".to_string(), vec![1, 1]);
+        durations.insert("make".to_string(), vec![700]);
+
+        let commands = rank_durations(durations, 1);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].name, "ls");
+    }
+}
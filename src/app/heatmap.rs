@@ -0,0 +1,56 @@
+use chrono::{Datelike, Timelike};
+
+use crate::app::parser;
+use crate::app::{
+    AppError, EntryFilter, RunConfig, Verdict, ensure_timestamps,
+};
+use crate::history::simple_history::{
+    filtered_commands, for_each_entry_command_name,
+};
+use crate::output::heatmap::Heatmap;
+
+/// Build a weekday by hour heatmap of command activity.
+///
+/// Every counted command name adds one to the cell of its entry's
+/// timestamp; with `command` set only that command is counted.
+pub(super) fn collect_heatmap(
+    config: &RunConfig,
+    command: Option<&str>,
+) -> Result<Heatmap, AppError> {
+    let filter = EntryFilter::new(config);
+    let filtered_commands = filtered_commands(&config.ignore, config.no_hist);
+    let mut heatmap = Heatmap::default();
+    let mut missing_timestamps = 0;
+
+    parser::for_each_entry(config, |entry| {
+        match filter.check_timed(entry) {
+            Verdict::Keep => {}
+            Verdict::Skip => return,
+            Verdict::MissingTimestamp => {
+                missing_timestamps += 1;
+                return;
+            }
+        }
+        let Some(datetime) =
+            entry.timestamp.and_then(|ts| config.zone.datetime(ts))
+        else {
+            return;
+        };
+        let weekday = datetime.weekday().num_days_from_monday() as usize;
+        let hour = datetime.hour() as usize;
+
+        for_each_entry_command_name(
+            entry,
+            &filtered_commands,
+            config.no_hist,
+            |name| {
+                if command.is_none_or(|command| command == name) {
+                    heatmap.add(weekday, hour);
+                }
+            },
+        );
+    })?;
+
+    ensure_timestamps(&config.input_label(), missing_timestamps)?;
+    Ok(heatmap)
+}
@@ -1,18 +1,30 @@
 //! Application orchestration layer.
 
 use ahash::AHashMap;
-use std::{cmp, fmt, io};
+use std::{cmp, env, fmt, io};
 
+use crate::app::paths::PathPrefix;
+use crate::history::{HistoryEntry, HistoryFormat};
 use crate::output::OutputFormat;
 use crate::output::color::ColorMode;
+use crate::shared::time::{Period, TimeRange, UntimedPolicy, Zone};
 
+mod discover;
+mod durations;
+mod heatmap;
 mod parser;
+mod paths;
 mod render;
+mod sources;
+mod trend;
 
 #[derive(Debug)]
 pub enum AppError {
     Config(String),
     HistoryRead { parser: &'static str, path: String, source: io::Error },
+    MissingTimestamps { path: String, count: usize },
+    MissingDurations { path: String },
+    MissingPaths { path: String },
     Output(io::Error),
     BrokenPipe,
 }
@@ -31,6 +43,27 @@ impl fmt::Display for AppError {
                     parser, path, source
                 )
             }
+            Self::MissingTimestamps { path, count } => {
+                write!(
+                    f,
+                    "{} entries in {} have no timestamp; use --untimed include or --untimed exclude",
+                    count, path
+                )
+            }
+            Self::MissingDurations { path } => {
+                write!(
+                    f,
+                    "No command durations recorded in {}; durations need zsh EXTENDED_HISTORY, nushell SQLite, xonsh, atuin or zsh-histdb history",
+                    path
+                )
+            }
+            Self::MissingPaths { path } => {
+                write!(
+                    f,
+                    "No paths recorded in {}; path reports need fish history",
+                    path
+                )
+            }
             Self::Output(source) => {
                 write!(f, "Error writing output: {}", source)
             }
@@ -39,9 +72,32 @@ impl fmt::Display for AppError {
     }
 }
 
+/// Report produced by [`run`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Report {
+    /// Commands ranked by number of invocations
+    #[default]
+    Ranking,
+    /// Weekday by hour activity, optionally for a single command
+    Heatmap { command: Option<String> },
+    /// Usage per period for one command or the top commands
+    Trend { command: Option<String> },
+    /// Commands ranked by total wall time spent
+    Durations,
+    /// Most referenced paths and directories
+    Paths,
+    /// Format, entry count and date range of every input history
+    Discover,
+    /// How the format of every input history was detected
+    Detection,
+}
+
 #[derive(Debug, Clone)]
 pub struct RunConfig {
-    pub file: String,
+    /// History files to analyse, merged into one report
+    pub files: Vec<String>,
+    /// Formats to read input files in instead of detecting them
+    pub formats: Vec<(String, HistoryFormat)>,
     pub count: usize,
     pub all: bool,
     pub more_than: usize,
@@ -53,6 +109,29 @@ pub struct RunConfig {
     pub no_perc: bool,
     pub output_format: OutputFormat,
     pub color_mode: ColorMode,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub untimed: UntimedPolicy,
+    pub report: Report,
+    pub zone: Zone,
+    pub period: Period,
+    /// Number of periods shown in the ranking's sparkline column
+    pub sparkline: Option<usize>,
+    /// Only consider entries that referenced a path in this tree
+    pub path_prefix: Option<String>,
+    /// Break the ranking down by input file
+    pub by_source: bool,
+}
+
+impl RunConfig {
+    fn time_range(&self) -> TimeRange {
+        TimeRange { since: self.since, until: self.until }
+    }
+
+    /// The input files, for error messages
+    fn input_label(&self) -> String {
+        self.files.join(", ")
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -62,14 +141,163 @@ pub(crate) struct RankedCommand {
 }
 
 pub fn run(config: &RunConfig) -> Result<(), AppError> {
-    let command_counts = parser::load_command_counts(
-        &config.file,
-        &config.ignore,
-        config.no_hist,
-    )?;
-    let commands = filter_and_sort_commands(command_counts, config.more_than);
-    let n = output_limit(commands.len(), config.all, config.count);
-    render::write_output(&commands, n, config)
+    match &config.report {
+        Report::Ranking => {
+            let sources = sources::detect_sources(config)?;
+            let mut period_counts =
+                config.sparkline.map(|_| trend::PeriodCounts::default());
+            let mut source_counts = config
+                .by_source
+                .then(|| sources::SourceCounts::new(sources.len()));
+            let command_counts = parser::load_command_counts(
+                config,
+                &sources,
+                &EntryFilter::new(config),
+                period_counts.as_mut(),
+                source_counts.as_mut(),
+            )?;
+            let commands =
+                filter_and_sort_commands(command_counts, config.more_than);
+            let n = output_limit(commands.len(), config.all, config.count);
+            let sparklines = period_counts
+                .as_ref()
+                .zip(config.sparkline)
+                .and_then(|(period_counts, periods)| {
+                    trend::build_sparklines(
+                        period_counts,
+                        &commands[..n],
+                        config.period,
+                        periods,
+                    )
+                });
+            let breakdown =
+                source_counts.map(|source_counts| sources::SourceBreakdown {
+                    labels: sources::source_labels(&sources),
+                    counts: commands[..n]
+                        .iter()
+                        .map(|command| source_counts.get(&command.name))
+                        .collect(),
+                });
+            render::write_output(
+                &commands,
+                n,
+                sparklines.as_deref(),
+                breakdown.as_ref(),
+                config,
+            )
+        }
+        Report::Heatmap { command } => {
+            let heatmap = heatmap::collect_heatmap(config, command.as_deref())?;
+            render::write_heatmap_output(&heatmap, command.as_deref(), config)
+        }
+        Report::Trend { command } => {
+            let trend = trend::collect_trend(
+                config,
+                command.as_deref(),
+                config.period,
+            )?;
+            render::write_trend_output(&trend, config)
+        }
+        Report::Durations => {
+            let commands = durations::collect_durations(config)?;
+            render::write_durations_output(&commands, config)
+        }
+        Report::Paths => {
+            let report = paths::collect_paths(config)?;
+            render::write_paths_output(&report, config)
+        }
+        Report::Discover => {
+            let summaries = discover::collect_summaries(config)?;
+            render::write_discover_output(&summaries, config)
+        }
+        Report::Detection => {
+            let reports = sources::explain_sources(config)?;
+            render::write_detection_output(&reports, config)
+        }
+    }
+}
+
+/// Fail when entries were rejected for lacking a timestamp.
+fn ensure_timestamps(
+    path: &str,
+    missing: usize,
+) -> Result<(), AppError> {
+    if missing > 0 {
+        return Err(AppError::MissingTimestamps {
+            path: path.to_string(),
+            count: missing,
+        });
+    }
+    Ok(())
+}
+
+/// Outcome of checking a history entry against an [`EntryFilter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Keep,
+    Skip,
+    /// The entry has no timestamp and the policy forbids guessing
+    MissingTimestamp,
+}
+
+/// Decides which history entries take part in a report
+pub(crate) struct EntryFilter {
+    time_range: TimeRange,
+    untimed: UntimedPolicy,
+    path_prefix: Option<PathPrefix>,
+}
+
+impl EntryFilter {
+    pub(crate) fn new(config: &RunConfig) -> Self {
+        let home = env::var("HOME").ok();
+        Self {
+            time_range: config.time_range(),
+            untimed: config.untimed,
+            path_prefix: config
+                .path_prefix
+                .as_deref()
+                .map(|prefix| PathPrefix::new(prefix, home.as_deref())),
+        }
+    }
+
+    pub(crate) fn check(
+        &self,
+        entry: &HistoryEntry<'_>,
+    ) -> Verdict {
+        if let Some(ref path_prefix) = self.path_prefix
+            && !path_prefix.matches_any(entry.paths)
+            && !entry.cwd.is_some_and(|cwd| path_prefix.matches(cwd))
+        {
+            return Verdict::Skip;
+        }
+        if self.time_range.is_unbounded() {
+            return Verdict::Keep;
+        }
+        match (entry.timestamp, self.untimed) {
+            (Some(timestamp), _) if self.time_range.contains(timestamp) => {
+                Verdict::Keep
+            }
+            (Some(_), _) => Verdict::Skip,
+            (None, UntimedPolicy::Include) => Verdict::Keep,
+            (None, UntimedPolicy::Exclude) => Verdict::Skip,
+            (None, UntimedPolicy::Error) => Verdict::MissingTimestamp,
+        }
+    }
+
+    /// Like [`check`](Self::check), for reports that can only place
+    /// entries that have a timestamp.
+    ///
+    /// Untimed entries are skipped unless the policy is to fail on them.
+    pub(crate) fn check_timed(
+        &self,
+        entry: &HistoryEntry<'_>,
+    ) -> Verdict {
+        match (entry.timestamp, self.untimed) {
+            (None, UntimedPolicy::Error) => Verdict::MissingTimestamp,
+            (None, _) => Verdict::Skip,
+            (Some(_), _) => self.check(entry),
+        }
+    }
 }
 
 fn filter_and_sort_commands(
@@ -132,4 +360,72 @@ mod tests {
         assert_eq!(output_limit(2, false, 3), 2);
         assert_eq!(output_limit(2, true, 1), 2);
     }
+
+    fn entry_filter(
+        since: Option<i64>,
+        until: Option<i64>,
+        untimed: UntimedPolicy,
+    ) -> EntryFilter {
+        EntryFilter {
+            time_range: TimeRange { since, until },
+            untimed,
+            path_prefix: None,
+        }
+    }
+
+    #[test]
+    fn test_entry_filter_time_range() {
+        let filter = entry_filter(Some(100), Some(200), UntimedPolicy::Error);
+        let entry = |timestamp| HistoryEntry {
+            timestamp: Some(timestamp),
+            ..HistoryEntry::new("ls")
+        };
+        assert_eq!(filter.check(&entry(99)), Verdict::Skip);
+        assert_eq!(filter.check(&entry(100)), Verdict::Keep);
+        assert_eq!(filter.check(&entry(200)), Verdict::Skip);
+    }
+
+    #[test]
+    fn test_entry_filter_untimed_policy() {
+        let untimed = HistoryEntry::new("ls");
+        let include = entry_filter(Some(100), None, UntimedPolicy::Include);
+        let exclude = entry_filter(Some(100), None, UntimedPolicy::Exclude);
+        let error = entry_filter(None, Some(100), UntimedPolicy::Error);
+        assert_eq!(include.check(&untimed), Verdict::Keep);
+        assert_eq!(exclude.check(&untimed), Verdict::Skip);
+        assert_eq!(error.check(&untimed), Verdict::MissingTimestamp);
+    }
+
+    #[test]
+    fn test_entry_filter_check_timed_never_keeps_untimed() {
+        let untimed = HistoryEntry::new("ls");
+        let include = entry_filter(None, None, UntimedPolicy::Include);
+        let error = entry_filter(None, None, UntimedPolicy::Error);
+        assert_eq!(include.check_timed(&untimed), Verdict::Skip);
+        assert_eq!(error.check_timed(&untimed), Verdict::MissingTimestamp);
+    }
+
+    #[test]
+    fn test_entry_filter_path_prefix() {
+        let filter = EntryFilter {
+            path_prefix: Some(PathPrefix::new("/srv/app", None)),
+            ..entry_filter(None, None, UntimedPolicy::Error)
+        };
+        let paths = ["/srv/app/config.toml".to_string()];
+        let inside = HistoryEntry { paths: &paths, ..HistoryEntry::new("vim") };
+        assert_eq!(filter.check(&inside), Verdict::Keep);
+        assert_eq!(filter.check(&HistoryEntry::new("ls")), Verdict::Skip);
+
+        let in_cwd = HistoryEntry {
+            cwd: Some("/srv/app/src"),
+            ..HistoryEntry::new("make")
+        };
+        assert_eq!(filter.check(&in_cwd), Verdict::Keep);
+    }
+
+    #[test]
+    fn test_entry_filter_unbounded_accepts_untimed() {
+        let filter = entry_filter(None, None, UntimedPolicy::Error);
+        assert_eq!(filter.check(&HistoryEntry::new("ls")), Verdict::Keep);
+    }
 }
@@ -1,68 +1,111 @@
 use ahash::AHashMap;
 
-use crate::app::AppError;
-use crate::history::{self, HistoryFormat};
+use crate::app::sources::{self, Source, SourceCounts};
+use crate::app::trend::PeriodCounts;
+use crate::app::{
+    AppError, EntryFilter, RunConfig, Verdict, ensure_timestamps,
+};
+use crate::history::simple_history::{
+    filtered_commands, for_each_entry_command_name, increment_count,
+};
+use crate::history::{self, HistoryEntry};
 
+/// Count command names across the entries accepted by `filter`.
+///
+/// When `period_counts` is given, timestamped entries are also bucketed
+/// by `config.period` for sparklines, and when `source_counts` is given,
+/// each name is also counted against the position of its source in
+/// `sources`.
 pub(super) fn load_command_counts(
-    file: &str,
-    ignore: &[String],
-    no_hist: bool,
+    config: &RunConfig,
+    sources: &[Source],
+    filter: &EntryFilter,
+    mut period_counts: Option<&mut PeriodCounts>,
+    mut source_counts: Option<&mut SourceCounts>,
 ) -> Result<AHashMap<String, usize>, AppError> {
-    if no_hist {
-        return history::count_from_file(file, ignore, no_hist).map_err(
-            |source| AppError::HistoryRead {
-                parser: "raw",
-                path: file.to_string(),
-                source,
+    let filtered_commands = filtered_commands(&config.ignore, config.no_hist);
+    let mut cmd_count = AHashMap::default();
+    let mut missing_timestamps = 0;
+
+    for_each_source_entry(sources, config.no_hist, |source, entry| {
+        match filter.check(entry) {
+            Verdict::Keep => {}
+            Verdict::Skip => return,
+            Verdict::MissingTimestamp => {
+                missing_timestamps += 1;
+                return;
+            }
+        }
+        let start = entry
+            .timestamp
+            .and_then(|ts| config.zone.datetime(ts))
+            .map(|datetime| config.period.start(datetime.date()));
+
+        for_each_entry_command_name(
+            entry,
+            &filtered_commands,
+            config.no_hist,
+            |name| {
+                increment_count(&mut cmd_count, name);
+                if let (Some(period_counts), Some(start)) =
+                    (period_counts.as_deref_mut(), start)
+                {
+                    period_counts.add(name, start);
+                }
+                if let Some(source_counts) = source_counts.as_deref_mut() {
+                    source_counts.add(name, source);
+                }
             },
         );
-    }
+    })?;
 
-    let history_format =
-        history::detect_history_format(file).map_err(|source| {
-            AppError::HistoryRead {
-                parser: "shell",
-                path: file.to_string(),
-                source,
-            }
-        })?;
-    match history_format {
-        HistoryFormat::Fish => history::fish::count_from_file(
-            file, ignore, no_hist,
-        )
-        .map_err(|source| AppError::HistoryRead {
-            parser: "fish",
-            path: file.to_string(),
-            source,
-        }),
-        HistoryFormat::Shell => history::count_from_file(file, ignore, no_hist)
-            .map_err(|source| AppError::HistoryRead {
-                parser: "shell",
-                path: file.to_string(),
-                source,
-            }),
-        HistoryFormat::PowerShell => history::powershell::count_from_file(
-            file, ignore, no_hist,
-        )
-        .map_err(|source| AppError::HistoryRead {
-            parser: "powershell",
-            path: file.to_string(),
-            source,
-        }),
-        HistoryFormat::Tcsh => history::tcsh::count_from_file(
-            file, ignore, no_hist,
+    ensure_timestamps(&config.input_label(), missing_timestamps)?;
+    Ok(cmd_count)
+}
+
+/// Detect the format of every input file and call `f` for every entry
+/// they contain, one file after the other.
+pub(super) fn for_each_entry<F>(
+    config: &RunConfig,
+    mut f: F,
+) -> Result<(), AppError>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let sources = sources::detect_sources(config)?;
+    for_each_source_entry(&sources, config.no_hist, |_, entry| f(entry))
+}
+
+/// Call `f` for every entry of every source, along with the position of
+/// the source it came from.
+pub(super) fn for_each_source_entry<F>(
+    sources: &[Source],
+    no_hist: bool,
+    mut f: F,
+) -> Result<(), AppError>
+where
+    F: FnMut(usize, &HistoryEntry<'_>),
+{
+    for (index, source) in sources.iter().enumerate() {
+        history::for_each_entry(
+            source.format,
+            &source.path,
+            no_hist,
+            |entry| f(index, entry),
         )
-        .map_err(|source| AppError::HistoryRead {
-            parser: "tcsh",
-            path: file.to_string(),
-            source,
-        }),
+        .map_err(|source_err| AppError::HistoryRead {
+            parser: if no_hist { "raw" } else { source.format.name() },
+            path: source.path.clone(),
+            source: source_err,
+        })?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::history::HistoryFormat;
     use std::fs;
     use std::io::Write;
     use std::path::PathBuf;
@@ -101,6 +144,123 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_detect_history_format_bash_timestamped_fixture() {
+        let path = fixtures_path().join("bash_timestamped_history");
+        assert_eq!(
+            history::detect_history_format(path.to_str().unwrap()).unwrap(),
+            HistoryFormat::BashTimestamped
+        );
+    }
+
+    #[test]
+    fn test_detect_history_format_nushell_fixtures() {
+        let dir = fixtures_path().join("nushell");
+        let text = dir.join("history.txt");
+        let sqlite = dir.join("history.sqlite3");
+        assert_eq!(
+            history::detect_history_format(text.to_str().unwrap()).unwrap(),
+            HistoryFormat::Nushell
+        );
+        assert_eq!(
+            history::detect_history_format(sqlite.to_str().unwrap()).unwrap(),
+            HistoryFormat::NushellSqlite
+        );
+    }
+
+    #[test]
+    fn test_detect_history_format_repl_names() {
+        let dir = fixtures_path().join("repl");
+        for (name, format) in [
+            (".psql_history", HistoryFormat::Psql),
+            (".sqlite_history", HistoryFormat::SqliteCli),
+            (".python_history", HistoryFormat::PythonRepl),
+            (".node_repl_history", HistoryFormat::NodeRepl),
+            (".irb_history", HistoryFormat::Irb),
+        ] {
+            let path = dir.join(name);
+            assert_eq!(
+                history::detect_history_format(path.to_str().unwrap()).unwrap(),
+                format
+            );
+        }
+        assert_eq!(
+            history::detect_history_format("/home/u/.psql_history-sales")
+                .unwrap(),
+            HistoryFormat::Psql
+        );
+    }
+
+    #[test]
+    fn test_detect_history_format_ksh_fixtures() {
+        let ksh = fixtures_path().join("ksh_history");
+        let mksh = fixtures_path().join("mksh_history");
+        assert_eq!(
+            history::detect_history_format(ksh.to_str().unwrap()).unwrap(),
+            HistoryFormat::Ksh
+        );
+        assert_eq!(
+            history::detect_history_format(mksh.to_str().unwrap()).unwrap(),
+            HistoryFormat::Mksh
+        );
+    }
+
+    #[test]
+    fn test_detect_history_format_database_fixtures() {
+        let atuin = fixtures_path().join("atuin_history.db");
+        let histdb = fixtures_path().join("zsh_histdb.db");
+        assert_eq!(
+            history::detect_history_format(atuin.to_str().unwrap()).unwrap(),
+            HistoryFormat::Atuin
+        );
+        assert_eq!(
+            history::detect_history_format(histdb.to_str().unwrap()).unwrap(),
+            HistoryFormat::ZshHistdb
+        );
+    }
+
+    #[test]
+    fn test_detect_history_format_reads_only_the_head() {
+        let path = unique_temp_path("histop_detect_head").with_extension("gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        );
+        for i in 0..100_000 {
+            writeln!(encoder, "- cmd: echo {}\n  when: {}", i, i).unwrap();
+        }
+        let mut compressed = encoder.finish().unwrap();
+        // A corrupt tail fails the parser, not detection
+        compressed.truncate(compressed.len() / 2);
+        fs::write(&path, compressed).unwrap();
+
+        let format = history::detect_history_format(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(format.unwrap(), HistoryFormat::Fish);
+    }
+
+    #[test]
+    fn test_detect_history_format_xonsh_fixtures() {
+        let dir = fixtures_path().join("xonsh");
+        assert_eq!(
+            history::detect_history_format(dir.to_str().unwrap()).unwrap(),
+            HistoryFormat::Xonsh
+        );
+        let session = xonsh_session_file(&dir);
+        assert_eq!(
+            history::detect_history_format(session.to_str().unwrap()).unwrap(),
+            HistoryFormat::Xonsh
+        );
+    }
+
+    fn xonsh_session_file(dir: &std::path::Path) -> PathBuf {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .unwrap()
+    }
+
     #[test]
     fn test_detect_history_format_tcsh_fixture() {
         let path = fixtures_path().join("tcsh_history");
@@ -147,4 +307,43 @@ mod tests {
         fs::remove_file(path_in_fish_dir).ok();
         fs::remove_dir_all(path).ok();
     }
+
+    #[test]
+    fn test_explain_history_format_scores() {
+        let path = fixtures_path().join("fish_history");
+        let report =
+            history::explain_history_format(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(report.format, HistoryFormat::Fish);
+        assert!(report.lines_inspected > 0);
+        assert_eq!(report.scores[0].0, HistoryFormat::Fish);
+        assert!(report.reasons[0].contains("- cmd: "));
+        assert!(!report.is_ambiguous());
+    }
+
+    #[test]
+    fn test_explain_history_format_decided_by_name() {
+        let path = fixtures_path().join("repl/.python_history");
+        let report =
+            history::explain_history_format(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(report.format, HistoryFormat::PythonRepl);
+        assert!(report.scores.is_empty());
+        assert_eq!(report.reasons.len(), 1);
+    }
+
+    #[test]
+    fn test_explain_history_format_flags_close_scores() {
+        let path = unique_temp_path("ambiguous_history");
+        // Five plain commands against one fish entry: 5 points vs 4 + 2
+        fs::write(&path, "ls\ngit\n- cmd: make\n  when: 1\npwd\ncd\nvim\n")
+            .unwrap();
+
+        let report =
+            history::explain_history_format(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(report.format, HistoryFormat::Fish);
+        assert!(report.is_ambiguous());
+    }
 }
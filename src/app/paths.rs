@@ -0,0 +1,267 @@
+use ahash::{AHashMap, AHashSet};
+
+use crate::app::parser;
+use crate::app::{
+    AppError, EntryFilter, RankedCommand, RunConfig, Verdict,
+    ensure_timestamps, filter_and_sort_commands, output_limit,
+};
+
+/// Most referenced paths and the directories containing them
+pub(super) struct PathReport {
+    pub(super) paths: Vec<RankedCommand>,
+    pub(super) directories: Vec<RankedCommand>,
+}
+
+/// Count the paths recorded with each entry, as fish does in `paths:`.
+///
+/// Each entry counts at most once per path and once per directory.
+/// Whether a path names a directory is read from the history alone, never
+/// from this machine's filesystem, since the history may come from another
+/// machine or outlive the files it names.
+pub(super) fn collect_paths(
+    config: &RunConfig
+) -> Result<PathReport, AppError> {
+    let filter = EntryFilter::new(config);
+    let mut path_counts: AHashMap<String, usize> = AHashMap::default();
+    let mut known_directories: AHashSet<String> = AHashSet::default();
+    // Distinct paths of each entry, kept until every directory is known
+    let mut entry_paths: Vec<Vec<String>> = Vec::new();
+    let mut missing_timestamps = 0;
+
+    parser::for_each_entry(config, |entry| {
+        match filter.check(entry) {
+            Verdict::Keep => {}
+            Verdict::Skip => return,
+            Verdict::MissingTimestamp => {
+                missing_timestamps += 1;
+                return;
+            }
+        }
+        if let Some(cwd) = entry.cwd {
+            insert_directory(&mut known_directories, cwd);
+            insert_ancestors(&mut known_directories, cwd);
+        }
+        if entry.paths.is_empty() {
+            return;
+        }
+
+        let mut paths = Vec::with_capacity(entry.paths.len());
+        for (i, path) in entry.paths.iter().enumerate() {
+            if entry.paths[..i].contains(path) {
+                continue;
+            }
+            *path_counts.entry(path.clone()).or_insert(0) += 1;
+            insert_ancestors(&mut known_directories, path);
+            paths.push(path.clone());
+        }
+        entry_paths.push(paths);
+    })?;
+
+    ensure_timestamps(&config.input_label(), missing_timestamps)?;
+    if entry_paths.is_empty() {
+        return Err(AppError::MissingPaths { path: config.input_label() });
+    }
+
+    let is_dir = |path: &str| {
+        known_directories.contains(path) || is_directory_name(path)
+    };
+    let mut directory_counts: AHashMap<String, usize> = AHashMap::default();
+    for paths in &entry_paths {
+        let mut directories: Vec<&str> = Vec::with_capacity(paths.len());
+        for path in paths {
+            if let Some(directory) = directory_of(path, is_dir)
+                && !directories.contains(&directory)
+            {
+                directories.push(directory);
+            }
+        }
+        for directory in directories {
+            *directory_counts.entry(directory.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    let limit = |mut ranked: Vec<RankedCommand>| {
+        ranked.truncate(output_limit(ranked.len(), config.all, config.count));
+        ranked
+    };
+    Ok(PathReport {
+        paths: limit(filter_and_sort_commands(path_counts, config.more_than)),
+        directories: limit(filter_and_sort_commands(
+            directory_counts,
+            config.more_than,
+        )),
+    })
+}
+
+/// Directory of `path`: the path itself when it ends with `/` or `is_dir`
+/// says so, otherwise everything before its last `/`.
+fn directory_of(
+    path: &str,
+    is_dir: impl Fn(&str) -> bool,
+) -> Option<&str> {
+    if path.len() > 1
+        && let Some(directory) = path.strip_suffix('/')
+    {
+        return Some(directory);
+    }
+    if is_dir(path) {
+        return Some(path);
+    }
+    match path.rfind('/') {
+        Some(0) => Some("/"),
+        Some(i) => Some(&path[..i]),
+        None => None,
+    }
+}
+
+fn insert_directory(
+    directories: &mut AHashSet<String>,
+    directory: &str,
+) {
+    if !directories.contains(directory) {
+        directories.insert(directory.to_string());
+    }
+}
+
+/// Record every directory that `path` is spelled as lying in
+fn insert_ancestors(
+    directories: &mut AHashSet<String>,
+    path: &str,
+) {
+    for (i, _) in path.match_indices('/') {
+        insert_directory(directories, if i == 0 { "/" } else { &path[..i] });
+    }
+}
+
+/// Whether `path` can only name a directory, like `~`, `.` or `src/..`
+fn is_directory_name(path: &str) -> bool {
+    let last = path.rsplit('/').next().unwrap_or(path);
+    matches!(last, "." | "..") || path == "~"
+}
+
+/// Directory tree given to `--path-prefix`
+pub(crate) struct PathPrefix {
+    prefix: String,
+    /// The prefix spelled with `~` when it lies under the home directory,
+    /// since fish records paths as they were typed
+    tilde_prefix: Option<String>,
+}
+
+impl PathPrefix {
+    pub(crate) fn new(
+        prefix: &str,
+        home: Option<&str>,
+    ) -> Self {
+        let home = home.map(|home| home.trim_end_matches('/'));
+        let prefix = match (prefix.strip_prefix('~'), home) {
+            (Some(rest), Some(home))
+                if rest.is_empty() || rest.starts_with('/') =>
+            {
+                format!("{}{}", home, rest)
+            }
+            _ => prefix.to_string(),
+        };
+        let prefix = match prefix.trim_end_matches('/') {
+            "" => "/".to_string(),
+            trimmed => trimmed.to_string(),
+        };
+        let tilde_prefix = home
+            .filter(|home| !home.is_empty())
+            .and_then(|home| prefix.strip_prefix(home))
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+            .map(|rest| format!("~{}", rest));
+
+        Self { prefix, tilde_prefix }
+    }
+
+    /// Whether `path` lies in this tree
+    pub(crate) fn matches(
+        &self,
+        path: &str,
+    ) -> bool {
+        is_under(path, &self.prefix)
+            || self
+                .tilde_prefix
+                .as_deref()
+                .is_some_and(|prefix| is_under(path, prefix))
+    }
+
+    /// Whether any of `paths` lies in this tree
+    pub(crate) fn matches_any(
+        &self,
+        paths: &[String],
+    ) -> bool {
+        paths.iter().any(|path| self.matches(path))
+    }
+}
+
+#[inline]
+fn is_under(
+    path: &str,
+    prefix: &str,
+) -> bool {
+    if prefix == "/" {
+        return path.starts_with('/');
+    }
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directory_of() {
+        let no_dirs = |_: &str| false;
+        assert_eq!(
+            directory_of("/home/user/notes.txt", no_dirs),
+            Some("/home/user")
+        );
+        assert_eq!(directory_of("src/", no_dirs), Some("src"));
+        assert_eq!(directory_of("/etc", no_dirs), Some("/"));
+        assert_eq!(directory_of("/", no_dirs), Some("/"));
+        assert_eq!(directory_of("README.md", no_dirs), None);
+        assert_eq!(directory_of("/etc", |path| path == "/etc"), Some("/etc"));
+    }
+
+    #[test]
+    fn test_directories_known_from_recorded_paths() {
+        let mut known = AHashSet::default();
+        insert_ancestors(&mut known, "/home/user/work/notes.txt");
+        insert_ancestors(&mut known, "src/lib.rs");
+        let is_dir =
+            |path: &str| known.contains(path) || is_directory_name(path);
+        assert_eq!(
+            directory_of("/home/user/work", is_dir),
+            Some("/home/user/work")
+        );
+        assert_eq!(directory_of("/home/user", is_dir), Some("/home/user"));
+        assert_eq!(directory_of("src", is_dir), Some("src"));
+        assert_eq!(directory_of("/etc/hosts", is_dir), Some("/etc"));
+        assert_eq!(directory_of("~", is_dir), Some("~"));
+        assert_eq!(directory_of("../..", is_dir), Some("../.."));
+        assert_eq!(directory_of("README.md", is_dir), None);
+    }
+
+    #[test]
+    fn test_path_prefix_matches_tree_only() {
+        let prefix = PathPrefix::new("/home/user/work/foo/", None);
+        let paths = |path: &str| vec![path.to_string()];
+        assert!(prefix.matches_any(&paths("/home/user/work/foo")));
+        assert!(prefix.matches_any(&paths("/home/user/work/foo/src/lib.rs")));
+        assert!(!prefix.matches_any(&paths("/home/user/work/foobar")));
+        assert!(!prefix.matches_any(&[]));
+    }
+
+    #[test]
+    fn test_path_prefix_tilde() {
+        let prefix = PathPrefix::new("~/work", Some("/home/user"));
+        assert!(prefix.matches_any(&["/home/user/work/a".to_string()]));
+        assert!(prefix.matches_any(&["~/work/a".to_string()]));
+        assert!(!prefix.matches_any(&["~/play".to_string()]));
+
+        let prefix = PathPrefix::new("/home/user/work", Some("/home/user/"));
+        assert!(prefix.matches_any(&["~/work".to_string()]));
+    }
+}
@@ -1,20 +1,400 @@
 use std::io::{self, BufWriter, Write};
 
+use crate::app::discover::HistorySummary;
+use crate::app::durations::CommandDurations;
+use crate::app::paths::PathReport;
+use crate::app::sources::SourceBreakdown;
+use crate::app::trend::Trend;
 use crate::app::{AppError, RankedCommand, RunConfig};
+use crate::history::DetectionReport;
 use crate::output::bar::{self, BarConfig, BarItem};
-use crate::output::color::Colorizer;
-use crate::output::{self, CommandEntry, OutputFormat};
+use crate::output::color::{Color, Colorizer};
+use crate::output::heatmap::{self, Heatmap};
+use crate::output::{
+    self, CommandEntry, DetectionEntry, DurationEntry, HistoryFileEntry,
+    OutputFormat, PathEntry, SourceEntry, TrendEntry,
+};
+use crate::shared::time::format_duration;
 
 pub(super) fn write_output(
     commands: &[RankedCommand],
     n: usize,
+    sparklines: Option<&[String]>,
+    breakdown: Option<&SourceBreakdown>,
     config: &RunConfig,
 ) -> Result<(), AppError> {
+    match (config.output_format, breakdown) {
+        (OutputFormat::Json, Some(breakdown)) => write_stdout(
+            &(output::format_sources_json(
+                &build_source_entries(commands, n, breakdown),
+                &breakdown.labels,
+            ) + "\n"),
+        ),
+        (OutputFormat::Csv, Some(breakdown)) => {
+            write_stdout(&output::format_sources_csv(
+                &build_source_entries(commands, n, breakdown),
+                &breakdown.labels,
+            ))
+        }
+        (OutputFormat::Json, None) => write_json_output(commands, n),
+        (OutputFormat::Csv, None) => write_csv_output(commands, n),
+        (OutputFormat::Text, _) => {
+            write_text_output(commands, n, sparklines, breakdown, config)
+        }
+    }
+}
+
+pub(super) fn write_heatmap_output(
+    heatmap: &Heatmap,
+    command: Option<&str>,
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    match config.output_format {
+        OutputFormat::Json => write_stdout(
+            &(output::format_heatmap_json(
+                heatmap,
+                command,
+                config.zone.name(),
+            ) + "\n"),
+        ),
+        OutputFormat::Csv => write_stdout(&output::format_heatmap_csv(heatmap)),
+        OutputFormat::Text => {
+            let colorizer = Colorizer::new(config.color_mode);
+            let stdout = io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+            heatmap::write_heatmap(&mut writer, heatmap, &colorizer)
+                .and_then(|_| writer.flush())
+                .map_err(map_output_error)
+        }
+    }
+}
+
+pub(super) fn write_trend_output(
+    trend: &Trend,
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    match config.output_format {
+        OutputFormat::Json => write_stdout(
+            &(output::format_trend_json(&build_trend_entries(trend, config))
+                + "\n"),
+        ),
+        OutputFormat::Csv => write_stdout(&output::format_trend_csv(
+            &build_trend_entries(trend, config),
+        )),
+        OutputFormat::Text => write_trend_text(trend, config),
+    }
+}
+
+fn build_trend_entries(
+    trend: &Trend,
+    config: &RunConfig,
+) -> Vec<TrendEntry> {
+    trend
+        .periods
+        .iter()
+        .enumerate()
+        .flat_map(|(i, start)| {
+            let period = config.period.label(*start);
+            trend.series.iter().map(move |series| TrendEntry {
+                period: period.clone(),
+                command: series.command.clone(),
+                count: series.counts[i],
+            })
+        })
+        .collect()
+}
+
+fn write_trend_text(
+    trend: &Trend,
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    let labels: Vec<String> =
+        trend.periods.iter().map(|start| config.period.label(*start)).collect();
+    let bar_config = BarConfig {
+        size: if config.no_bar { 0 } else { config.bar_size },
+        show_percentage: true,
+        show_cumulative: false,
+        scale_to_max: true,
+    };
+    let colorizer = Colorizer::new(config.color_mode);
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    let mut write_all = || -> io::Result<()> {
+        for (i, series) in trend.series.iter().enumerate() {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            writeln!(
+                writer,
+                "{}",
+                colorizer.paint(Color::Bold, &series.command)
+            )?;
+
+            let items: Vec<BarItem> = labels
+                .iter()
+                .zip(series.counts.iter())
+                .map(|(label, count)| BarItem::new(label.as_str(), *count))
+                .collect();
+            let rendered = bar::render_bars(&items, &bar_config);
+            bar::write_bars(
+                &mut writer,
+                &rendered,
+                !config.no_bar,
+                &colorizer,
+            )?;
+        }
+        writer.flush()
+    };
+    write_all().map_err(map_output_error)
+}
+
+pub(super) fn write_durations_output(
+    commands: &[CommandDurations],
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    match config.output_format {
+        OutputFormat::Json => write_stdout(
+            &(output::format_durations_json(&build_duration_entries(commands))
+                + "\n"),
+        ),
+        OutputFormat::Csv => write_stdout(&output::format_durations_csv(
+            &build_duration_entries(commands),
+        )),
+        OutputFormat::Text => write_durations_text(commands, config),
+    }
+}
+
+fn build_duration_entries(commands: &[CommandDurations]) -> Vec<DurationEntry> {
+    let total = commands
+        .iter()
+        .fold(0_u64, |total, command| total.saturating_add(command.total));
+
+    commands
+        .iter()
+        .map(|command| DurationEntry {
+            command: command.name.clone(),
+            count: command.count,
+            total_seconds: command.total,
+            median_seconds: command.median,
+            percentage: if total > 0 {
+                command.total as f64 / total as f64 * 100.0
+            } else {
+                0.0
+            },
+        })
+        .collect()
+}
+
+/// Bars sized by total time, with the total in place of the count and
+/// the median as an extra column.
+fn write_durations_text(
+    commands: &[CommandDurations],
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    let items: Vec<BarItem> = commands
+        .iter()
+        .map(|command| {
+            BarItem::new(command.name.as_str(), command.total as usize)
+        })
+        .collect();
+    let bar_config = BarConfig {
+        size: if config.no_bar { 0 } else { config.bar_size },
+        show_percentage: !config.no_perc,
+        show_cumulative: !config.no_cumu,
+        scale_to_max: false,
+    };
+    let colorizer = Colorizer::new(config.color_mode);
+
+    let totals: Vec<String> =
+        commands.iter().map(|command| format_duration(command.total)).collect();
+    let medians: Vec<String> = commands
+        .iter()
+        .map(|command| format!("median {}", format_duration(command.median)))
+        .collect();
+    let total_width = totals.iter().map(|s| s.len()).max().unwrap_or(0);
+    let median_width = medians.iter().map(|s| s.len()).max().unwrap_or(0);
+
+    let mut rendered = bar::render_bars(&items, &bar_config);
+    for ((bar, total), median) in rendered.iter_mut().zip(&totals).zip(&medians)
+    {
+        bar.count_str = format!("{:>width$}", total, width = total_width);
+        bar.detail = Some(format!("{:<width$}", median, width = median_width));
+    }
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    bar::write_bars(&mut writer, &rendered, !config.no_bar, &colorizer)
+        .and_then(|_| writer.flush())
+        .map_err(map_output_error)
+}
+
+pub(super) fn write_paths_output(
+    report: &PathReport,
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    match config.output_format {
+        OutputFormat::Json => write_stdout(
+            &(output::format_paths_json(
+                &build_path_entries(&report.paths),
+                &build_path_entries(&report.directories),
+            ) + "\n"),
+        ),
+        OutputFormat::Csv => write_stdout(&output::format_paths_csv(
+            &build_path_entries(&report.paths),
+            &build_path_entries(&report.directories),
+        )),
+        OutputFormat::Text => write_paths_text(report, config),
+    }
+}
+
+fn build_path_entries(ranked: &[RankedCommand]) -> Vec<PathEntry> {
+    ranked
+        .iter()
+        .map(|entry| PathEntry { path: entry.name.clone(), count: entry.count })
+        .collect()
+}
+
+/// One ranking section for paths and one for directories
+fn write_paths_text(
+    report: &PathReport,
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    let bar_config = BarConfig {
+        size: if config.no_bar { 0 } else { config.bar_size },
+        show_percentage: !config.no_perc,
+        show_cumulative: !config.no_cumu,
+        scale_to_max: false,
+    };
+    let colorizer = Colorizer::new(config.color_mode);
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    let mut write_all = || -> io::Result<()> {
+        let sections =
+            [("Paths", &report.paths), ("Directories", &report.directories)];
+        for (i, (title, ranked)) in sections.into_iter().enumerate() {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            writeln!(writer, "{}", colorizer.paint(Color::Bold, title))?;
+
+            let items: Vec<BarItem> = ranked
+                .iter()
+                .map(|entry| BarItem::new(entry.name.as_str(), entry.count))
+                .collect();
+            let rendered = bar::render_bars(&items, &bar_config);
+            bar::write_bars(
+                &mut writer,
+                &rendered,
+                !config.no_bar,
+                &colorizer,
+            )?;
+        }
+        writer.flush()
+    };
+    write_all().map_err(map_output_error)
+}
+
+pub(super) fn write_discover_output(
+    summaries: &[HistorySummary],
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    let entries = build_history_file_entries(summaries, config);
     match config.output_format {
-        OutputFormat::Json => write_json_output(commands, n),
-        OutputFormat::Csv => write_csv_output(commands, n),
-        OutputFormat::Text => write_text_output(commands, n, config),
+        OutputFormat::Json => {
+            write_stdout(&(output::format_history_files_json(&entries) + "\n"))
+        }
+        OutputFormat::Csv => {
+            write_stdout(&output::format_history_files_csv(&entries))
+        }
+        OutputFormat::Text => write_discover_text(&entries, config),
+    }
+}
+
+fn build_history_file_entries(
+    summaries: &[HistorySummary],
+    config: &RunConfig,
+) -> Vec<HistoryFileEntry> {
+    let date = |timestamp: Option<i64>| {
+        timestamp
+            .and_then(|ts| config.zone.datetime(ts))
+            .map(|datetime| datetime.format("%Y-%m-%d").to_string())
+    };
+    summaries
+        .iter()
+        .map(|summary| HistoryFileEntry {
+            path: summary.path.clone(),
+            format: summary.format.to_string(),
+            entries: summary.entries,
+            first: date(summary.first),
+            last: date(summary.last),
+        })
+        .collect()
+}
+
+/// One aligned row per history file, `-` standing in for missing dates
+fn write_discover_text(
+    entries: &[HistoryFileEntry],
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    let header = ["FORMAT", "ENTRIES", "FIRST", "LAST", "PATH"];
+    let rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|entry| {
+            [
+                entry.format.clone(),
+                entry.entries.to_string(),
+                entry.first.clone().unwrap_or_else(|| "-".to_string()),
+                entry.last.clone().unwrap_or_else(|| "-".to_string()),
+                entry.path.clone(),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
     }
+    let colorizer = Colorizer::new(config.color_mode);
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    let mut write_all = || -> io::Result<()> {
+        let header_line = format!(
+            "{:<w0$}  {:>w1$}  {:<w2$}  {:<w3$}  {}",
+            header[0],
+            header[1],
+            header[2],
+            header[3],
+            header[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        );
+        writeln!(writer, "{}", colorizer.paint(Color::Bold, &header_line))?;
+        for [format, count, first, last, path] in &rows {
+            writeln!(
+                writer,
+                "{:<w0$}  {}  {:<w2$}  {:<w3$}  {}",
+                format,
+                colorizer.paint(
+                    Color::Cyan,
+                    &format!("{:>w1$}", count, w1 = widths[1])
+                ),
+                first,
+                last,
+                path,
+                w0 = widths[0],
+                w2 = widths[2],
+                w3 = widths[3],
+            )?;
+        }
+        writer.flush()
+    };
+    write_all().map_err(map_output_error)
 }
 
 fn build_command_entries(
@@ -30,6 +410,54 @@ fn build_command_entries(
         .collect()
 }
 
+fn build_source_entries(
+    commands: &[RankedCommand],
+    n: usize,
+    breakdown: &SourceBreakdown,
+) -> Vec<SourceEntry> {
+    build_command_entries(commands, n)
+        .into_iter()
+        .zip(&breakdown.counts)
+        .map(|(entry, counts)| SourceEntry {
+            command: entry.command,
+            count: entry.count,
+            percentage: entry.percentage,
+            sources: counts.clone(),
+        })
+        .collect()
+}
+
+/// One "label count" column per source, with counts aligned across rows
+fn source_columns(breakdown: &SourceBreakdown) -> Vec<String> {
+    let widths: Vec<usize> = (0..breakdown.labels.len())
+        .map(|source| {
+            breakdown
+                .counts
+                .iter()
+                .map(|counts| counts[source].to_string().len())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    breakdown
+        .counts
+        .iter()
+        .map(|counts| {
+            breakdown
+                .labels
+                .iter()
+                .zip(counts)
+                .zip(&widths)
+                .map(|((label, count), width)| {
+                    format!("{} {:>width$}", label, count, width = width)
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+        })
+        .collect()
+}
+
 fn write_json_output(
     commands: &[RankedCommand],
     n: usize,
@@ -49,6 +477,8 @@ fn write_csv_output(
 fn write_text_output(
     commands: &[RankedCommand],
     n: usize,
+    sparklines: Option<&[String]>,
+    breakdown: Option<&SourceBreakdown>,
     config: &RunConfig,
 ) -> Result<(), AppError> {
     let items: Vec<BarItem> = commands
@@ -61,10 +491,25 @@ fn write_text_output(
         size: if config.no_bar { 0 } else { config.bar_size },
         show_percentage: !config.no_perc,
         show_cumulative: !config.no_cumu,
+        scale_to_max: false,
     };
 
     let colorizer = Colorizer::new(config.color_mode);
-    let rendered = bar::render_bars(&items, &bar_config);
+    let mut rendered = bar::render_bars(&items, &bar_config);
+    if let Some(sparklines) = sparklines {
+        for (bar, sparkline) in rendered.iter_mut().zip(sparklines) {
+            bar.detail = Some(sparkline.clone());
+        }
+    }
+    if let Some(breakdown) = breakdown {
+        for (bar, columns) in rendered.iter_mut().zip(source_columns(breakdown))
+        {
+            bar.detail = Some(match bar.detail.take() {
+                Some(sparkline) => format!("{}   {}", sparkline, columns),
+                None => columns,
+            });
+        }
+    }
 
     let stdout = io::stdout();
     let mut writer = BufWriter::new(stdout.lock());
@@ -73,6 +518,89 @@ fn write_text_output(
         .map_err(map_output_error)
 }
 
+pub(super) fn write_detection_output(
+    reports: &[(String, DetectionReport)],
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    let entries: Vec<DetectionEntry> = reports
+        .iter()
+        .map(|(path, report)| DetectionEntry {
+            path: path.clone(),
+            format: report.format.name().to_string(),
+            lines_inspected: report.lines_inspected,
+            ambiguous: report.is_ambiguous(),
+            scores: report
+                .scores
+                .iter()
+                .map(|(format, score)| (format.name().to_string(), *score))
+                .collect(),
+            reasons: report.reasons.clone(),
+        })
+        .collect();
+    match config.output_format {
+        OutputFormat::Json => {
+            write_stdout(&(output::format_detections_json(&entries) + "\n"))
+        }
+        OutputFormat::Csv => {
+            write_stdout(&output::format_detections_csv(&entries))
+        }
+        OutputFormat::Text => write_detection_text(&entries, config),
+    }
+}
+
+/// One block per file: the format, the scores when lines were read, and
+/// the reasons behind them
+fn write_detection_text(
+    entries: &[DetectionEntry],
+    config: &RunConfig,
+) -> Result<(), AppError> {
+    let colorizer = Colorizer::new(config.color_mode);
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    let mut write_all = || -> io::Result<()> {
+        for (i, entry) in entries.iter().enumerate() {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            writeln!(
+                writer,
+                "{}: {}",
+                colorizer.paint(Color::Bold, &entry.path),
+                colorizer.paint(Color::Cyan, &entry.format)
+            )?;
+            if !entry.scores.is_empty() {
+                let scores: Vec<String> = entry
+                    .scores
+                    .iter()
+                    .map(|(format, score)| format!("{} {}", format, score))
+                    .collect();
+                writeln!(
+                    writer,
+                    "  {} lines inspected; scores: {}",
+                    entry.lines_inspected,
+                    scores.join(", ")
+                )?;
+            }
+            for reason in &entry.reasons {
+                writeln!(writer, "  - {}", reason)?;
+            }
+            if entry.ambiguous {
+                writeln!(
+                    writer,
+                    "  {}",
+                    colorizer.paint(
+                        Color::Yellow,
+                        "ambiguous: the two best scores are close; use --format if the guess is wrong"
+                    )
+                )?;
+            }
+        }
+        writer.flush()
+    };
+    write_all().map_err(map_output_error)
+}
+
 fn write_stdout(output: &str) -> Result<(), AppError> {
     let stdout = io::stdout();
     let mut writer = BufWriter::new(stdout.lock());
@@ -109,4 +637,16 @@ mod tests {
         assert!((entries[0].percentage - 60.0).abs() < f64::EPSILON);
         assert!((entries[1].percentage - 40.0).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_source_columns_align_counts() {
+        let breakdown = SourceBreakdown {
+            labels: vec!["bash".to_string(), "fish".to_string()],
+            counts: vec![vec![12, 3], vec![4, 0]],
+        };
+        assert_eq!(
+            source_columns(&breakdown),
+            vec!["bash 12  fish 3", "bash  4  fish 0"]
+        );
+    }
 }
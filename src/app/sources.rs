@@ -0,0 +1,181 @@
+use ahash::AHashMap;
+
+use crate::app::{AppError, RunConfig};
+use crate::history::{self, DetectionReport, HistoryFormat};
+
+/// One input history file and its detected format
+pub(super) struct Source {
+    pub(super) path: String,
+    pub(super) format: HistoryFormat,
+}
+
+/// Detect the format of every input file, in the order they were given,
+/// unless the configuration names it.
+pub(super) fn detect_sources(
+    config: &RunConfig
+) -> Result<Vec<Source>, AppError> {
+    config
+        .files
+        .iter()
+        .map(|path| {
+            let format = if config.no_hist {
+                HistoryFormat::Shell
+            } else if let Some(format) = chosen_format(config, path) {
+                format
+            } else {
+                history::detect_history_format(path).map_err(|source| {
+                    AppError::HistoryRead {
+                        parser: "shell",
+                        path: path.clone(),
+                        source,
+                    }
+                })?
+            };
+            Ok(Source { path: path.clone(), format })
+        })
+        .collect()
+}
+
+/// Explain how the format of every input file is detected, or that the
+/// configuration names it.
+pub(super) fn explain_sources(
+    config: &RunConfig
+) -> Result<Vec<(String, DetectionReport)>, AppError> {
+    config
+        .files
+        .iter()
+        .map(|path| {
+            if let Some(format) = chosen_format(config, path) {
+                let report = DetectionReport::decided(
+                    format,
+                    "format set by --format/config",
+                );
+                return Ok((path.clone(), report));
+            }
+            let report =
+                history::explain_history_format(path).map_err(|source| {
+                    AppError::HistoryRead {
+                        parser: "shell",
+                        path: path.clone(),
+                        source,
+                    }
+                })?;
+            Ok((path.clone(), report))
+        })
+        .collect()
+}
+
+/// Format that `--format` or the config file sets for `path`
+fn chosen_format(
+    config: &RunConfig,
+    path: &str,
+) -> Option<HistoryFormat> {
+    config
+        .formats
+        .iter()
+        .find(|(file, _)| file == path)
+        .map(|(_, format)| *format)
+}
+
+/// Column label for each source: its format name, or its path when
+/// several sources share a format.
+pub(super) fn source_labels(sources: &[Source]) -> Vec<String> {
+    sources
+        .iter()
+        .map(|source| {
+            let name = source.format.name();
+            let shared = sources
+                .iter()
+                .filter(|other| other.format.name() == name)
+                .count()
+                > 1;
+            if shared { source.path.clone() } else { name.to_string() }
+        })
+        .collect()
+}
+
+/// Command counts split by the source they were read from
+pub(super) struct SourceCounts {
+    sources: usize,
+    counts: AHashMap<String, Vec<usize>>,
+}
+
+impl SourceCounts {
+    pub(super) fn new(sources: usize) -> Self {
+        Self { sources, counts: AHashMap::default() }
+    }
+
+    pub(super) fn add(
+        &mut self,
+        name: &str,
+        source: usize,
+    ) {
+        let counts = match self.counts.get_mut(name) {
+            Some(counts) => counts,
+            None => self
+                .counts
+                .entry(name.to_string())
+                .or_insert_with(|| vec![0; self.sources]),
+        };
+        counts[source] += 1;
+    }
+
+    /// Counts of `name` in each source
+    pub(super) fn get(
+        &self,
+        name: &str,
+    ) -> Vec<usize> {
+        self.counts.get(name).cloned().unwrap_or_else(|| vec![0; self.sources])
+    }
+}
+
+/// Per-source counts of the ranked commands, for `--by-source`
+pub(crate) struct SourceBreakdown {
+    pub(crate) labels: Vec<String>,
+    /// Counts of each ranked command, in ranking order, one per label
+    pub(crate) counts: Vec<Vec<usize>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(
+        path: &str,
+        format: HistoryFormat,
+    ) -> Source {
+        Source { path: path.to_string(), format }
+    }
+
+    #[test]
+    fn test_source_labels_use_format_names() {
+        let sources = [
+            source("/home/u/.bash_history", HistoryFormat::Shell),
+            source("/home/u/fish_history", HistoryFormat::Fish),
+        ];
+        assert_eq!(source_labels(&sources), vec!["shell", "fish"]);
+    }
+
+    #[test]
+    fn test_source_labels_fall_back_to_paths() {
+        let sources = [
+            source("work/.bash_history", HistoryFormat::BashTimestamped),
+            source("home/.bash_history", HistoryFormat::BashTimestamped),
+            source("home/fish_history", HistoryFormat::Fish),
+        ];
+        assert_eq!(
+            source_labels(&sources),
+            vec!["work/.bash_history", "home/.bash_history", "fish"]
+        );
+    }
+
+    #[test]
+    fn test_source_counts() {
+        let mut counts = SourceCounts::new(2);
+        counts.add("git", 0);
+        counts.add("git", 1);
+        counts.add("git", 1);
+        assert_eq!(counts.get("git"), vec![1, 2]);
+        assert_eq!(counts.get("ls"), vec![0, 0]);
+    }
+}
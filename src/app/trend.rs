@@ -0,0 +1,267 @@
+use ahash::AHashMap;
+use chrono::NaiveDate;
+
+use crate::app::parser;
+use crate::app::{
+    AppError, EntryFilter, RankedCommand, RunConfig, Verdict,
+    ensure_timestamps, output_limit,
+};
+use crate::history::simple_history::{
+    filtered_commands, for_each_entry_command_name,
+};
+use crate::output::sparkline::sparkline;
+use crate::shared::time::Period;
+
+/// Command counts bucketed by the start date of their period
+#[derive(Default)]
+pub(super) struct PeriodCounts {
+    buckets: AHashMap<String, AHashMap<NaiveDate, usize>>,
+}
+
+impl PeriodCounts {
+    pub(super) fn add(
+        &mut self,
+        name: &str,
+        start: NaiveDate,
+    ) {
+        let counts = match self.buckets.get_mut(name) {
+            Some(counts) => counts,
+            None => self.buckets.entry(name.to_string()).or_default(),
+        };
+        *counts.entry(start).or_insert(0) += 1;
+    }
+
+    /// Start of the most recent period with any activity
+    pub(super) fn latest(&self) -> Option<NaiveDate> {
+        self.buckets.values().flat_map(|counts| counts.keys()).max().copied()
+    }
+
+    /// Counts of `name` for each of the given period starts
+    pub(super) fn series(
+        &self,
+        name: &str,
+        periods: &[NaiveDate],
+    ) -> Vec<usize> {
+        let counts = self.buckets.get(name);
+        periods
+            .iter()
+            .map(|start| {
+                counts.and_then(|c| c.get(start)).copied().unwrap_or(0)
+            })
+            .collect()
+    }
+}
+
+/// Per-period counts of one command
+pub(crate) struct TrendSeries {
+    pub(crate) command: String,
+    /// One count per entry of [`Trend::periods`]
+    pub(crate) counts: Vec<usize>,
+}
+
+/// Command usage over a contiguous run of periods
+pub(crate) struct Trend {
+    /// Start date of every period, oldest first, without gaps
+    pub(crate) periods: Vec<NaiveDate>,
+    pub(crate) series: Vec<TrendSeries>,
+}
+
+/// Bucket command usage by period.
+///
+/// With `command` set only that command is tracked; otherwise the most
+/// used commands are kept, limited like the ranking by `-c`/`-a`.
+pub(super) fn collect_trend(
+    config: &RunConfig,
+    command: Option<&str>,
+    period: Period,
+) -> Result<Trend, AppError> {
+    let filter = EntryFilter::new(config);
+    let filtered_commands = filtered_commands(&config.ignore, config.no_hist);
+    let mut period_counts = PeriodCounts::default();
+    let mut missing_timestamps = 0;
+
+    parser::for_each_entry(config, |entry| {
+        match filter.check_timed(entry) {
+            Verdict::Keep => {}
+            Verdict::Skip => return,
+            Verdict::MissingTimestamp => {
+                missing_timestamps += 1;
+                return;
+            }
+        }
+        let Some(datetime) =
+            entry.timestamp.and_then(|ts| config.zone.datetime(ts))
+        else {
+            return;
+        };
+        let start = period.start(datetime.date());
+
+        for_each_entry_command_name(
+            entry,
+            &filtered_commands,
+            config.no_hist,
+            |name| {
+                if command.is_none_or(|command| command == name) {
+                    period_counts.add(name, start);
+                }
+            },
+        );
+    })?;
+
+    ensure_timestamps(&config.input_label(), missing_timestamps)?;
+    Ok(build_trend(period_counts, period, config.all, config.count))
+}
+
+/// Sparkline of each command's activity over the `periods` periods
+/// ending with the most recent one in the history.
+///
+/// Returns `None` when no entry had a timestamp, so callers can leave the
+/// column out instead of showing empty sparklines.
+pub(super) fn build_sparklines(
+    period_counts: &PeriodCounts,
+    commands: &[RankedCommand],
+    period: Period,
+    periods: usize,
+) -> Option<Vec<String>> {
+    let latest = period_counts.latest()?;
+    let mut starts: Vec<NaiveDate> =
+        std::iter::successors(Some(latest), |start| period.previous(*start))
+            .take(periods)
+            .collect();
+    starts.reverse();
+
+    Some(
+        commands
+            .iter()
+            .map(|command| {
+                sparkline(&period_counts.series(&command.name, &starts))
+            })
+            .collect(),
+    )
+}
+
+fn build_trend(
+    period_counts: PeriodCounts,
+    period: Period,
+    all: bool,
+    count: usize,
+) -> Trend {
+    let mut ranked: Vec<(String, AHashMap<NaiveDate, usize>, usize)> =
+        period_counts
+            .buckets
+            .into_iter()
+            .map(|(name, counts)| {
+                let total = counts.values().sum();
+                (name, counts, total)
+            })
+            .collect();
+    ranked.sort_unstable_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(output_limit(ranked.len(), all, count));
+
+    let first = ranked.iter().flat_map(|(_, counts, _)| counts.keys()).min();
+    let last = ranked.iter().flat_map(|(_, counts, _)| counts.keys()).max();
+    let mut periods = Vec::new();
+    if let (Some(&first), Some(&last)) = (first, last) {
+        let mut start = Some(first);
+        while let Some(current) = start.filter(|current| *current <= last) {
+            periods.push(current);
+            start = period.next(current);
+        }
+    }
+
+    let series = ranked
+        .into_iter()
+        .map(|(command, counts, _)| TrendSeries {
+            counts: periods
+                .iter()
+                .map(|start| counts.get(start).copied().unwrap_or(0))
+                .collect(),
+            command,
+        })
+        .collect();
+
+    Trend { periods, series }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(
+        month: u32,
+        day: u32,
+    ) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_period_counts_series_and_latest() {
+        let mut counts = PeriodCounts::default();
+        counts.add("git", date(1, 1));
+        counts.add("git", date(1, 1));
+        counts.add("ls", date(2, 1));
+
+        assert_eq!(counts.latest(), Some(date(2, 1)));
+        assert_eq!(counts.series("git", &[date(1, 1), date(2, 1)]), vec![2, 0]);
+        assert_eq!(counts.series("cd", &[date(1, 1)]), vec![0]);
+        assert_eq!(PeriodCounts::default().latest(), None);
+    }
+
+    #[test]
+    fn test_build_trend_fills_gaps_and_ranks() {
+        let mut counts = PeriodCounts::default();
+        counts.add("git", date(1, 1));
+        counts.add("git", date(1, 1));
+        counts.add("git", date(3, 1));
+        for _ in 0..5 {
+            counts.add("ls", date(2, 1));
+        }
+        counts.add("cd", date(2, 1));
+
+        let trend = build_trend(counts, Period::Month, false, 2);
+        assert_eq!(trend.periods, vec![date(1, 1), date(2, 1), date(3, 1)]);
+        assert_eq!(trend.series.len(), 2);
+        assert_eq!(trend.series[0].command, "ls");
+        assert_eq!(trend.series[0].counts, vec![0, 5, 0]);
+        assert_eq!(trend.series[1].command, "git");
+        assert_eq!(trend.series[1].counts, vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_build_sparklines_ends_at_latest_period() {
+        let mut counts = PeriodCounts::default();
+        counts.add("git", date(1, 1));
+        counts.add("git", date(3, 1));
+        counts.add("git", date(3, 1));
+        let commands = vec![
+            RankedCommand { name: "git".to_string(), count: 3 },
+            RankedCommand { name: "ls".to_string(), count: 1 },
+        ];
+
+        let sparklines =
+            build_sparklines(&counts, &commands, Period::Month, 4).unwrap();
+        assert_eq!(sparklines, vec![" ▄ █".to_string(), "    ".to_string()]);
+    }
+
+    #[test]
+    fn test_build_sparklines_without_timestamps() {
+        let commands = vec![RankedCommand { name: "ls".to_string(), count: 1 }];
+        assert_eq!(
+            build_sparklines(
+                &PeriodCounts::default(),
+                &commands,
+                Period::Week,
+                8
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_build_trend_empty() {
+        let trend =
+            build_trend(PeriodCounts::default(), Period::Week, false, 25);
+        assert!(trend.periods.is_empty());
+        assert!(trend.series.is_empty());
+    }
+}
@@ -1,12 +1,14 @@
 //! Simple TOML-like configuration file parser.
 //!
 //! Parses a subset of TOML for histop configuration without external dependencies.
-//! Supports: strings, integers, and arrays of strings.
+//! Supports: strings, integers, arrays of strings, and a `[formats]` table
+//! mapping history file paths to the format they are written in.
 
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+use crate::history::{FORMAT_NAMES, HistoryFormat};
 use crate::output::color::ColorMode;
 
 /// Configuration loaded from file
@@ -22,6 +24,8 @@ pub struct FileConfig {
     pub color: Option<ColorMode>,
     /// More than threshold
     pub more_than: Option<usize>,
+    /// History files read in a given format instead of a detected one
+    pub formats: Vec<(String, HistoryFormat)>,
 }
 
 impl FileConfig {
@@ -45,9 +49,25 @@ impl FileConfig {
     /// Parse configuration from string content
     pub fn parse(content: &str) -> Result<Self, String> {
         let mut config = FileConfig::default();
-        let values = parse_toml(content)?;
+        let parsed_toml = parse_toml(content)?;
+
+        for (path, parsed) in parsed_toml.formats {
+            let name = parse_string(&parsed.value).map_err(|e| {
+                format!(
+                    "Line {}: invalid format for '{}': {}",
+                    parsed.line, path, e
+                )
+            })?;
+            let format = HistoryFormat::parse(name).ok_or_else(|| {
+                format!(
+                    "Line {}: invalid format '{}' for '{}'. Use one of: {}",
+                    parsed.line, name, path, FORMAT_NAMES
+                )
+            })?;
+            config.formats.push((path, format));
+        }
 
-        for (key, parsed) in values {
+        for (key, parsed) in parsed_toml.values {
             match key.as_str() {
                 "ignore" => {
                     let arr =
@@ -130,9 +150,19 @@ struct ParsedValue {
     line: usize,
 }
 
+/// Keys of a parsed config file
+struct ParsedToml {
+    /// Top-level keys
+    values: HashMap<String, ParsedValue>,
+    /// Entries of the `[formats]` table, in file order
+    formats: Vec<(String, ParsedValue)>,
+}
+
 /// Parse a simple TOML file (subset of TOML spec)
-fn parse_toml(content: &str) -> Result<HashMap<String, ParsedValue>, String> {
+fn parse_toml(content: &str) -> Result<ParsedToml, String> {
     let mut values = HashMap::new();
+    let mut formats = Vec::new();
+    let mut in_formats = false;
 
     for (line_num, line) in content.lines().enumerate() {
         let line = line.trim();
@@ -142,23 +172,24 @@ fn parse_toml(content: &str) -> Result<HashMap<String, ParsedValue>, String> {
             continue;
         }
 
-        // Skip section headers for now (we only support top-level keys)
+        // Only `[formats]` is a table; keys under other headers are read
+        // as top-level keys
         if line.starts_with('[') {
+            in_formats = line == "[formats]";
             continue;
         }
 
         // Parse key = value
-        if let Some(eq_pos) = line.find('=') {
-            let key = line[..eq_pos].trim();
-            let value_str = line[eq_pos + 1..].trim();
-
+        if let Some((key, value_str)) = split_key_value(line) {
             let value = parse_value(value_str)
                 .map_err(|e| format!("Line {}: {}", line_num + 1, e))?;
+            let parsed = ParsedValue { value, line: line_num + 1 };
 
-            values.insert(
-                key.to_string(),
-                ParsedValue { value, line: line_num + 1 },
-            );
+            if in_formats {
+                formats.push((key.to_string(), parsed));
+            } else {
+                values.insert(key.to_string(), parsed);
+            }
         } else {
             return Err(format!(
                 "Line {}: malformed line (expected 'key = value')",
@@ -167,7 +198,21 @@ fn parse_toml(content: &str) -> Result<HashMap<String, ParsedValue>, String> {
         }
     }
 
-    Ok(values)
+    Ok(ParsedToml { values, formats })
+}
+
+/// Split `key = value`, where the key may be quoted so that it can hold
+/// a path containing `=`.
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    for quote in ['"', '\''] {
+        if let Some(rest) = line.strip_prefix(quote) {
+            let end = rest.find(quote)?;
+            let value = rest[end + 1..].trim_start().strip_prefix('=')?;
+            return Some((&rest[..end], value.trim()));
+        }
+    }
+    let (key, value) = line.split_once('=')?;
+    Some((key.trim(), value.trim()))
 }
 
 /// Parse a TOML value
@@ -440,6 +485,33 @@ count = 10 # inline comments not supported, this will fail
         assert!(err.contains("unterminated quoted string"), "got: {}", err);
     }
 
+    #[test]
+    fn test_parse_formats_table() {
+        let content = r#"
+count = 10
+
+[formats]
+"~/notes/history.txt" = "fish"
+"/srv/a=b/.zsh_history" = zsh
+"#;
+        let config = FileConfig::parse(content).unwrap();
+        assert_eq!(config.count, Some(10));
+        assert_eq!(
+            config.formats,
+            vec![
+                ("~/notes/history.txt".to_string(), HistoryFormat::Fish),
+                ("/srv/a=b/.zsh_history".to_string(), HistoryFormat::Shell),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_formats_table_invalid_format_rejected() {
+        let content = "[formats]\n\"~/.history\" = \"elvish\"";
+        let err = FileConfig::parse(content).unwrap_err();
+        assert!(err.contains("invalid format 'elvish'"), "got: {}", err);
+    }
+
     #[test]
     fn test_parse_array_with_unterminated_quote_rejected() {
         let content = r#"ignore = ["ls, "cd"]"#;
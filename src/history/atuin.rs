@@ -0,0 +1,69 @@
+//! Atuin history database parser
+//!
+//! Atuin records every command in an SQLite database, usually
+//! `~/.local/share/atuin/history.db`, together with its start time and
+//! duration in nanoseconds, exit code, working directory, session and
+//! `host:user` name.
+
+use std::io;
+
+use super::entry::HistoryEntry;
+use super::sqlite::{has_column, open_read_only, to_io_error};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Read an atuin `history.db` and call `f` for every entry, in the order
+/// the commands were run.
+///
+/// Entries deleted in atuin are skipped.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let conn = open_read_only(file_path)?;
+    // `deleted_at` only exists in databases written by atuin 14 and later
+    let query = if has_column(&conn, "history", "deleted_at")? {
+        "SELECT command, timestamp, duration, cwd, exit, hostname, session \
+         FROM history WHERE deleted_at IS NULL ORDER BY timestamp"
+    } else {
+        "SELECT command, timestamp, duration, cwd, exit, hostname, session \
+         FROM history ORDER BY timestamp"
+    };
+    let mut stmt = conn.prepare(query).map_err(to_io_error)?;
+    let mut rows = stmt.query([]).map_err(to_io_error)?;
+
+    while let Some(row) = rows.next().map_err(to_io_error)? {
+        // Entries that are not valid UTF-8 are skipped like in text files
+        let Ok(command) = row.get_ref(0).map_err(to_io_error)?.as_str() else {
+            continue;
+        };
+        if command.trim().is_empty() {
+            continue;
+        }
+        let timestamp_ns: Option<i64> = row.get(1).map_err(to_io_error)?;
+        // Atuin stores -1 while a command is still running
+        let duration_ns: Option<i64> = row.get(2).map_err(to_io_error)?;
+        let cwd = row.get_ref(3).map_err(to_io_error)?.as_str_or_null();
+        let exit_status: Option<i64> = row.get(4).map_err(to_io_error)?;
+        let hostname = row.get_ref(5).map_err(to_io_error)?.as_str_or_null();
+        let session = row.get_ref(6).map_err(to_io_error)?.as_str_or_null();
+
+        f(&HistoryEntry {
+            timestamp: timestamp_ns
+                .map(|ns| ns.div_euclid(NANOS_PER_SECOND as i64)),
+            duration: duration_ns
+                .and_then(|ns| u64::try_from(ns).ok())
+                .map(|ns| ns / NANOS_PER_SECOND),
+            cwd: cwd.ok().flatten(),
+            exit_status,
+            hostname: hostname.ok().flatten(),
+            session: session.ok().flatten(),
+            ..HistoryEntry::new(command)
+        });
+    }
+
+    Ok(())
+}
@@ -1,37 +1,248 @@
-use std::fs;
-use std::io::{self, BufRead, BufReader};
+use bstr::ByteSlice;
+use std::io;
+use std::path::Path;
+
+use super::entry::Syntax;
+use super::{input, ksh, sqlite, xonsh};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HistoryFormat {
     Shell,
+    /// Bash history written with `HISTTIMEFORMAT`, `#<epoch>` before entries
+    BashTimestamped,
     Fish,
     PowerShell,
     Tcsh,
+    /// Nushell plain text `history.txt`
+    Nushell,
+    /// Nushell SQLite `history.sqlite3`
+    NushellSqlite,
+    /// Xonsh JSON session files, as a directory or a single file
+    Xonsh,
+    /// Atuin SQLite `history.db`
+    Atuin,
+    /// zsh-histdb SQLite `zsh-history.db`
+    ZshHistdb,
+    /// ksh93 binary `.sh_history`
+    Ksh,
+    /// mksh binary `.mksh_history`
+    Mksh,
+    /// Python REPL `.python_history`
+    PythonRepl,
+    /// Node.js REPL `.node_repl_history`
+    NodeRepl,
+    /// psql `.psql_history`
+    Psql,
+    /// sqlite3 shell `.sqlite_history`
+    SqliteCli,
+    /// Ruby irb `.irb_history`
+    Irb,
+    /// One command per line, without any history metadata; never detected
+    Raw,
+}
+
+/// Names accepted by [`HistoryFormat::parse`], for help and error messages
+pub const FORMAT_NAMES: &str = "bash, zsh, sh, fish, tcsh, powershell, ksh, mksh, nushell, nushell-sqlite, xonsh, atuin, zsh-histdb, python, node, psql, sqlite, irb, raw";
+
+impl HistoryFormat {
+    /// Short parser name used in error messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Shell => "shell",
+            Self::BashTimestamped => "bash",
+            Self::Fish => "fish",
+            Self::PowerShell => "powershell",
+            Self::Tcsh => "tcsh",
+            Self::Nushell | Self::NushellSqlite => "nushell",
+            Self::Xonsh => "xonsh",
+            Self::Atuin => "atuin",
+            Self::ZshHistdb => "zsh-histdb",
+            Self::Ksh => "ksh",
+            Self::Mksh => "mksh",
+            Self::PythonRepl => "python",
+            Self::NodeRepl => "node",
+            Self::Psql => "psql",
+            Self::SqliteCli => "sqlite",
+            Self::Irb => "irb",
+            Self::Raw => "raw",
+        }
+    }
+
+    /// Parse a format name given by the user (see [`FORMAT_NAMES`])
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s.to_ascii_lowercase().as_str() {
+            // bash's own parser also reads files without timestamps
+            "bash" => Self::BashTimestamped,
+            "zsh" | "sh" | "ash" | "shell" => Self::Shell,
+            "fish" => Self::Fish,
+            "tcsh" | "csh" => Self::Tcsh,
+            "powershell" | "pwsh" => Self::PowerShell,
+            "ksh" | "ksh93" => Self::Ksh,
+            "mksh" => Self::Mksh,
+            "nushell" | "nu" => Self::Nushell,
+            "nushell-sqlite" => Self::NushellSqlite,
+            "xonsh" => Self::Xonsh,
+            "atuin" => Self::Atuin,
+            "zsh-histdb" | "histdb" => Self::ZshHistdb,
+            "python" => Self::PythonRepl,
+            "node" => Self::NodeRepl,
+            "psql" => Self::Psql,
+            "sqlite" => Self::SqliteCli,
+            "irb" => Self::Irb,
+            "raw" => Self::Raw,
+            _ => return None,
+        })
+    }
+
+    /// Language of the commands recorded in this format
+    pub fn syntax(&self) -> Syntax {
+        match self {
+            Self::PythonRepl => Syntax::Python,
+            Self::NodeRepl => Syntax::JavaScript,
+            Self::Psql | Self::SqliteCli => Syntax::Sql,
+            Self::Irb => Syntax::Ruby,
+            Self::Fish => Syntax::Fish,
+            _ => Syntax::Shell,
+        }
+    }
+}
+
+/// How [`explain_history_format`] decided on a file's format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectionReport {
+    pub format: HistoryFormat,
+    /// Score of each text format, empty when the file's name, magic bytes
+    /// or schema decided without reading its lines
+    pub scores: Vec<(HistoryFormat, u32)>,
+    /// Non-empty lines read to score the text formats
+    pub lines_inspected: u32,
+    /// Evidence behind the decision
+    pub reasons: Vec<String>,
+}
+
+impl DetectionReport {
+    /// A report for a format decided by one piece of evidence
+    pub fn decided(
+        format: HistoryFormat,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self {
+            format,
+            scores: Vec::new(),
+            lines_inspected: 0,
+            reasons: vec![reason.into()],
+        }
+    }
+
+    /// Whether the two best scores are too close to trust the result:
+    /// the runner-up reaches three quarters of the best score.
+    pub fn is_ambiguous(&self) -> bool {
+        let mut scores: Vec<u32> =
+            self.scores.iter().map(|(_, score)| *score).collect();
+        scores.sort_unstable_by(|a, b| b.cmp(a));
+        match scores[..] {
+            [best, second, ..] => second > 0 && second * 4 >= best * 3,
+            _ => false,
+        }
+    }
 }
 
 pub fn detect_history_format(path: &str) -> Result<HistoryFormat, io::Error> {
+    explain_history_format(path).map(|report| report.format)
+}
+
+/// Detect a file's format and report the evidence for it.
+pub fn explain_history_format(
+    path: &str
+) -> Result<DetectionReport, io::Error> {
     // PowerShell usually uses "ConsoleHost_history.txt".
     if path.ends_with("ConsoleHost_history.txt") {
-        return Ok(HistoryFormat::PowerShell);
+        return Ok(DetectionReport::decided(
+            HistoryFormat::PowerShell,
+            "file is named ConsoleHost_history.txt",
+        ));
+    }
+
+    // REPL histories are plain lines like a bash history; only their
+    // name tells which language they hold.
+    if let Some(format) = repl_format(path) {
+        return Ok(DetectionReport::decided(
+            format,
+            format!("file name is the {} REPL history", format.name()),
+        ));
+    }
+
+    if Path::new(path).is_dir() {
+        return detect_directory_format(path).map(|format| {
+            DetectionReport::decided(
+                format,
+                "directory holds xonsh JSON session files",
+            )
+        });
+    }
+
+    // The magic bytes and the first lines decide; the parser reads the rest
+    let input = input::head(path, INSPECTED_LINES)?;
+
+    if sqlite::is_sqlite(&input) {
+        return detect_sqlite_format(path).map(|format| {
+            DetectionReport::decided(
+                format,
+                format!("SQLite database with the {} schema", format.name()),
+            )
+        });
+    }
+
+    if let Some(format) = detect_binary_format(&input) {
+        return Ok(DetectionReport::decided(
+            format,
+            format!("file starts with the {} magic bytes", format.name()),
+        ));
+    }
+
+    // Nushell's plain text history is one command per line, which only
+    // its location tells apart from a bash history.
+    if path.ends_with("nushell/history.txt") {
+        return Ok(DetectionReport::decided(
+            HistoryFormat::Nushell,
+            "file is nushell/history.txt",
+        ));
+    }
+
+    if is_xonsh_session(&input) {
+        return Ok(DetectionReport::decided(
+            HistoryFormat::Xonsh,
+            "file is a JSON object with xonsh session keys",
+        ));
     }
 
-    let file = fs::File::open(path)?;
-    let reader = BufReader::new(file);
+    Ok(score_text_formats(&input))
+}
+
+/// Non-empty lines scored to tell the text formats apart
+const INSPECTED_LINES: usize = 64;
+
+/// Line kinds counted while scoring the text formats
+#[derive(Default)]
+struct LineCounts {
+    fish_commands: u32,
+    fish_fields: u32,
+    fish_indented: u32,
+    zsh_extended: u32,
+    tcsh_timestamps: u32,
+    bash_timestamps: u32,
+    plain: u32,
+}
 
-    let mut fish_score = 0_u32;
-    let mut shell_score = 0_u32;
-    let mut tcsh_score = 0_u32;
+/// Score the text formats on the first non-empty lines of the input.
+fn score_text_formats(bytes: &[u8]) -> DetectionReport {
+    let mut counts = LineCounts::default();
     let mut inspected = 0_u32;
 
-    for line_result in reader.lines() {
-        let line = match line_result {
-            Ok(line) => line,
-            Err(e) => {
-                if e.kind() == io::ErrorKind::InvalidData {
-                    continue;
-                }
-                return Err(e);
-            }
+    for line_bytes in bytes.lines() {
+        // Lines that are not UTF-8 say nothing about the format
+        let Ok(line) = line_bytes.to_str() else {
+            continue;
         };
 
         let trimmed = line.trim();
@@ -41,36 +252,160 @@ pub fn detect_history_format(path: &str) -> Result<HistoryFormat, io::Error> {
 
         inspected += 1;
         if trimmed.starts_with("- cmd: ") {
-            fish_score += 4;
+            counts.fish_commands += 1;
         } else if trimmed.starts_with("when: ") || trimmed.starts_with("paths:")
         {
-            fish_score += 2;
+            counts.fish_fields += 1;
         } else if line.starts_with("  when: ")
             || line.starts_with("  paths: ")
             || line.starts_with("  - ")
         {
-            fish_score += 1;
+            counts.fish_indented += 1;
         } else if line.starts_with(": ") && line.contains(';') {
-            shell_score += 3;
+            counts.zsh_extended += 1;
         } else if line.starts_with("#+")
             && line.len() > 2
             && line[2..].chars().all(char::is_numeric)
         {
-            tcsh_score += 5;
+            counts.tcsh_timestamps += 1;
+        } else if line.len() > 1
+            && line.starts_with('#')
+            && line[1..].bytes().all(|b| b.is_ascii_digit())
+        {
+            counts.bash_timestamps += 1;
         } else {
-            shell_score += 1;
+            counts.plain += 1;
         }
 
-        if inspected >= 64 {
+        if inspected as usize >= INSPECTED_LINES {
             break;
         }
     }
 
-    if fish_score > shell_score && fish_score > tcsh_score {
-        Ok(HistoryFormat::Fish)
+    let fish_score = counts.fish_commands * 4
+        + counts.fish_fields * 2
+        + counts.fish_indented;
+    let shell_score = counts.zsh_extended * 3 + counts.plain;
+    let tcsh_score = counts.tcsh_timestamps * 5;
+    let bash_timestamp_score = counts.bash_timestamps * 3;
+
+    let format = if fish_score > shell_score && fish_score > tcsh_score {
+        HistoryFormat::Fish
     } else if tcsh_score > shell_score && tcsh_score > fish_score {
-        Ok(HistoryFormat::Tcsh)
+        HistoryFormat::Tcsh
+    } else if bash_timestamp_score > shell_score
+        && bash_timestamp_score > fish_score
+    {
+        HistoryFormat::BashTimestamped
+    } else {
+        HistoryFormat::Shell
+    };
+
+    let mut reasons: Vec<String> = [
+        (counts.fish_commands, "start with \"- cmd: \" (fish, 4 points)"),
+        (counts.fish_fields, "are fish when:/paths: fields (2 points)"),
+        (counts.fish_indented, "are indented fish fields (1 point)"),
+        (
+            counts.zsh_extended,
+            "are zsh \": <time>:<elapsed>;\" entries (3 points)",
+        ),
+        (counts.tcsh_timestamps, "are tcsh #+<time> stamps (5 points)"),
+        (counts.bash_timestamps, "are bash #<time> stamps (3 points)"),
+        (counts.plain, "are plain commands (shell, 1 point)"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, kind)| format!("{} of {} lines {}", count, inspected, kind))
+    .collect();
+    if inspected == 0 {
+        reasons.push("no lines to inspect; assuming shell".to_string());
+    }
+
+    DetectionReport {
+        format,
+        scores: vec![
+            (HistoryFormat::Fish, fish_score),
+            (HistoryFormat::Shell, shell_score),
+            (HistoryFormat::Tcsh, tcsh_score),
+            (HistoryFormat::BashTimestamped, bash_timestamp_score),
+        ],
+        lines_inspected: inspected,
+        reasons,
+    }
+}
+
+/// Tell the SQLite history databases apart by their schema.
+fn detect_sqlite_format(path: &str) -> Result<HistoryFormat, io::Error> {
+    let conn = sqlite::open_read_only(path)?;
+    if sqlite::has_column(&conn, "history", "command_line")? {
+        return Ok(HistoryFormat::NushellSqlite);
+    }
+    if sqlite::has_column(&conn, "history", "command")?
+        && sqlite::has_column(&conn, "history", "timestamp")?
+    {
+        return Ok(HistoryFormat::Atuin);
+    }
+    if sqlite::has_column(&conn, "history", "command_id")?
+        && sqlite::has_column(&conn, "commands", "argv")?
+    {
+        return Ok(HistoryFormat::ZshHistdb);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "unsupported SQLite history database",
+    ))
+}
+
+/// REPL history named by its usual file name, including psql's per
+/// database `.psql_history-<dbname>`
+fn repl_format(path: &str) -> Option<HistoryFormat> {
+    let name = Path::new(path).file_name()?.to_str()?;
+    let name = name.strip_prefix('.').unwrap_or(name);
+    if name.starts_with("psql_history") {
+        return Some(HistoryFormat::Psql);
+    }
+    match name {
+        "python_history" => Some(HistoryFormat::PythonRepl),
+        "node_repl_history" => Some(HistoryFormat::NodeRepl),
+        "sqlite_history" => Some(HistoryFormat::SqliteCli),
+        "irb_history" => Some(HistoryFormat::Irb),
+        _ => None,
+    }
+}
+
+/// Recognise the binary ksh histories by their magic bytes.
+fn detect_binary_format(bytes: &[u8]) -> Option<HistoryFormat> {
+    if bytes.starts_with(&ksh::KSH_MAGIC) {
+        Some(HistoryFormat::Ksh)
+    } else if bytes.starts_with(&ksh::MKSH_MAGIC) {
+        Some(HistoryFormat::Mksh)
     } else {
-        Ok(HistoryFormat::Shell)
+        None
+    }
+}
+
+/// Only xonsh keeps its history as a directory of session files.
+fn detect_directory_format(path: &str) -> Result<HistoryFormat, io::Error> {
+    if xonsh::session_files(path)?.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "directory contains no xonsh JSON history",
+        ));
     }
+    Ok(HistoryFormat::Xonsh)
+}
+
+/// Whether the input looks like a xonsh session: a JSON object that names
+/// the session keys near its start.
+fn is_xonsh_session(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(4096)];
+
+    let starts_object = head
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|&b| b == b'{');
+    let mentions_key =
+        |key: &[u8]| head.windows(key.len()).any(|window| window == key);
+    starts_object
+        && (mentions_key(b"\"cmds\"") || mentions_key(b"\"sessionid\""))
 }
@@ -0,0 +1,99 @@
+//! Parsed history entries shared by every history parser.
+//!
+//! Parsers walk their input once and hand each recorded command, together
+//! with any metadata the format carries, to a caller-supplied callback.
+//! Counting, filtering and time-based reports are all consumers of this
+//! entry stream.
+
+/// Language of an entry's command text, which decides how it is split
+/// into command names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// Shell command lines
+    #[default]
+    Shell,
+    /// fish command lines, with fish's reserved words and quoting
+    Fish,
+    /// SQL with psql or sqlite meta-commands
+    Sql,
+    Python,
+    JavaScript,
+    Ruby,
+}
+
+/// A single command recorded in a history file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryEntry<'a> {
+    /// Command text as typed by the user
+    pub command: &'a str,
+    /// Start time in seconds since the Unix epoch, if recorded
+    pub timestamp: Option<i64>,
+    /// Elapsed wall time in seconds, if recorded
+    pub duration: Option<u64>,
+    /// Paths referenced by the command, if recorded
+    pub paths: &'a [String],
+    /// Working directory the command ran in, if recorded
+    pub cwd: Option<&'a str>,
+    /// Exit status of the command, if recorded
+    pub exit_status: Option<i64>,
+    /// Host the command ran on, if recorded
+    pub hostname: Option<&'a str>,
+    /// Identifier of the shell session, if recorded
+    pub session: Option<&'a str>,
+    /// Language of `command`
+    pub syntax: Syntax,
+}
+
+impl<'a> HistoryEntry<'a> {
+    /// Create an entry that carries no metadata
+    #[inline]
+    pub fn new(command: &'a str) -> Self {
+        Self {
+            command,
+            timestamp: None,
+            duration: None,
+            paths: &[],
+            cwd: None,
+            exit_status: None,
+            hostname: None,
+            session: None,
+            syntax: Syntax::Shell,
+        }
+    }
+}
+
+/// Parse a Unix timestamp consisting only of ASCII digits.
+#[inline]
+pub(crate) fn parse_timestamp(s: &str) -> Option<i64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_entry_has_no_metadata() {
+        let entry = HistoryEntry::new("ls -la");
+        assert_eq!(entry.command, "ls -la");
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.duration, None);
+        assert!(entry.paths.is_empty());
+        assert_eq!(entry.cwd, None);
+        assert_eq!(entry.exit_status, None);
+        assert_eq!(entry.hostname, None);
+        assert_eq!(entry.session, None);
+        assert_eq!(entry.syntax, Syntax::Shell);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1680820391"), Some(1680820391));
+        assert_eq!(parse_timestamp(""), None);
+        assert_eq!(parse_timestamp("+1680820391"), None);
+        assert_eq!(parse_timestamp("16808x"), None);
+    }
+}
@@ -7,11 +7,20 @@
 //!   paths:
 //!     - /some/path
 //! ```
+//!
+//! The `cmd` value is escaped: newlines are written as `\n` and
+//! backslashes as `\\`.
 
-use ahash::{AHashMap, AHashSet};
+use ahash::AHashMap;
 use bstr::ByteSlice;
-use memmap2::Mmap;
-use std::fs;
+use std::borrow::Cow;
+
+use super::entry::{HistoryEntry, Syntax, parse_timestamp};
+use super::input;
+use super::simple_history::{
+    filtered_commands, for_each_entry_command_name, increment_count,
+    trim_line_end,
+};
 
 /// Parse fish_history file and count commands
 ///
@@ -27,135 +36,183 @@ pub fn count_from_file(
     no_hist: bool,
 ) -> Result<AHashMap<String, usize>, std::io::Error> {
     let mut cmd_count: AHashMap<String, usize> = AHashMap::default();
+    let filtered_commands = filtered_commands(ignore, no_hist);
 
-    let mut filtered_commands: AHashSet<&str> =
-        AHashSet::with_capacity(ignore.len() + 2);
-    if !no_hist {
-        filtered_commands.insert("sudo");
-        filtered_commands.insert("doas");
-    }
-    for s in ignore {
-        filtered_commands.insert(s.as_str());
-    }
-
-    let file = fs::File::open(file_path)?;
-    let mmap = unsafe { Mmap::map(&file)? };
-    count_from_bytes(&mmap, &mut cmd_count, &filtered_commands, no_hist);
+    for_each_entry(file_path, |entry| {
+        for_each_entry_command_name(
+            entry,
+            &filtered_commands,
+            no_hist,
+            |name| increment_count(&mut cmd_count, name),
+        )
+    })?;
 
     Ok(cmd_count)
 }
 
-fn count_from_bytes(
-    bytes: &[u8],
-    cmd_count: &mut AHashMap<String, usize>,
-    filtered_commands: &AHashSet<&str>,
-    no_hist: bool,
-) {
-    let mut current_cmd = String::with_capacity(256);
+/// Parse fish_history file and call `f` for every entry, including its
+/// `when:` timestamp and `paths:` items.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), std::io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let input = input::open(file_path)?;
+    entries_from_bytes(&input, &mut f);
 
-    for line_bytes in bstr::ByteSlice::lines(bytes) {
-        if !current_cmd.is_empty() && is_ascii_metadata_line(line_bytes) {
-            super::simple_history::count_commands(
-                cmd_count,
-                &current_cmd,
-                filtered_commands,
-                no_hist,
-            );
-            current_cmd.clear();
+    Ok(())
+}
+
+/// Fish entry being assembled from its `- cmd:` line and metadata lines
+#[derive(Default)]
+struct PendingEntry {
+    cmd: String,
+    timestamp: Option<i64>,
+    paths: Vec<String>,
+    in_metadata: bool,
+    in_paths: bool,
+}
+
+impl PendingEntry {
+    fn flush<F>(
+        &mut self,
+        f: &mut F,
+    ) where
+        F: FnMut(&HistoryEntry<'_>),
+    {
+        if !self.cmd.is_empty() {
+            let command = unescape_cmd(&self.cmd);
+            f(&HistoryEntry {
+                timestamp: self.timestamp,
+                paths: &self.paths,
+                syntax: Syntax::Fish,
+                ..HistoryEntry::new(&command)
+            });
+        }
+        self.cmd.clear();
+        self.timestamp = None;
+        self.paths.clear();
+        self.in_metadata = false;
+        self.in_paths = false;
+    }
+}
+
+/// Decode the `\n` and `\\` escapes fish writes in the `cmd` field.
+///
+/// Any other backslash sequence is kept as written.
+fn unescape_cmd(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut decoded = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
             continue;
         }
+        match chars.next() {
+            Some('n') => decoded.push('\n'),
+            Some('\\') => decoded.push('\\'),
+            Some(other) => {
+                decoded.push('\\');
+                decoded.push(other);
+            }
+            None => decoded.push('\\'),
+        }
+    }
+    Cow::Owned(decoded)
+}
+
+fn entries_from_bytes<F>(
+    bytes: &[u8],
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let mut pending = PendingEntry {
+        cmd: String::with_capacity(256),
+        ..PendingEntry::default()
+    };
 
+    for line_bytes in bstr::ByteSlice::lines(bytes) {
         let line = match line_bytes.to_str() {
-            Ok(s) => super::simple_history::trim_line_end(s),
+            Ok(s) => trim_line_end(s),
             Err(_) => continue,
         };
 
-        process_line(
-            line,
-            &mut current_cmd,
-            cmd_count,
-            filtered_commands,
-            no_hist,
-        );
+        process_line(line, &mut pending, f);
     }
 
-    if !current_cmd.is_empty() {
-        super::simple_history::count_commands(
-            cmd_count,
-            &current_cmd,
-            filtered_commands,
-            no_hist,
-        );
-    }
+    pending.flush(f);
 }
 
 #[inline]
-fn is_ascii_metadata_line(line_bytes: &[u8]) -> bool {
-    line_bytes.is_ascii()
-        && (line_bytes.starts_with(b"  when: ")
-            || line_bytes.starts_with(b"  paths:")
-            || line_bytes.starts_with(b"  - "))
+fn is_metadata_line(line: &str) -> bool {
+    line.starts_with("  when: ")
+        || line.starts_with("  paths:")
+        || line.starts_with("  - ")
 }
 
-fn process_line(
+fn process_line<F>(
     trimmed_line: &str,
-    current_cmd: &mut String,
-    cmd_count: &mut AHashMap<String, usize>,
-    filtered_commands: &AHashSet<&str>,
-    no_hist: bool,
-) {
+    pending: &mut PendingEntry,
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
     // Fish history command lines start with "- cmd: "
     if let Some(cmd) = trimmed_line.strip_prefix("- cmd: ") {
-        if !current_cmd.is_empty() {
-            super::simple_history::count_commands(
-                cmd_count,
-                current_cmd,
-                filtered_commands,
-                no_hist,
-            );
-        }
-        current_cmd.clear();
-        current_cmd.push_str(cmd);
+        pending.flush(f);
+        pending.cmd.push_str(cmd);
         return;
     }
 
-    if !current_cmd.is_empty() {
-        // Multiline fish command continuation:
-        // - cmd: doas -- \
-        //   systemctl stop sshd
-        if current_cmd.ends_with('\\')
-            && trimmed_line.starts_with("  ")
-            && !trimmed_line.starts_with("  when: ")
-            && !trimmed_line.starts_with("  paths:")
-            && !trimmed_line.starts_with("  - ")
-        {
-            current_cmd.pop();
-            let len = current_cmd.trim_end().len();
-            current_cmd.truncate(len);
-            current_cmd.push(' ');
-            current_cmd.push_str(trimmed_line.trim_start());
-            return;
-        }
+    if pending.cmd.is_empty() {
+        return;
+    }
 
-        // Metadata ends the current command entry
-        if trimmed_line.starts_with("  when: ")
-            || trimmed_line.starts_with("  paths:")
-            || trimmed_line.starts_with("  - ")
-        {
-            super::simple_history::count_commands(
-                cmd_count,
-                current_cmd,
-                filtered_commands,
-                no_hist,
-            );
-            current_cmd.clear();
-        }
+    // Multiline fish command continuation:
+    // - cmd: doas -- \
+    //   systemctl stop sshd
+    if !pending.in_metadata
+        && pending.cmd.ends_with('\\')
+        && trimmed_line.starts_with("  ")
+        && !is_metadata_line(trimmed_line)
+    {
+        let cmd = &mut pending.cmd;
+        cmd.pop();
+        let len = cmd.trim_end().len();
+        cmd.truncate(len);
+        cmd.push(' ');
+        cmd.push_str(trimmed_line.trim_start());
+        return;
+    }
+
+    // Metadata ends the command text of the current entry
+    if let Some(when) = trimmed_line.strip_prefix("  when: ") {
+        pending.in_metadata = true;
+        pending.in_paths = false;
+        pending.timestamp = parse_timestamp(when.trim());
+    } else if trimmed_line.starts_with("  paths:") {
+        pending.in_metadata = true;
+        pending.in_paths = true;
+    } else if let Some(path) = trimmed_line.strip_prefix("    - ")
+        && pending.in_paths
+    {
+        pending.paths.push(path.trim().to_string());
+    } else if trimmed_line.starts_with("  - ") {
+        pending.in_metadata = true;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_count_simple_commands() {
@@ -258,4 +315,78 @@ mod tests {
 
         fs::remove_file(path).ok();
     }
+
+    #[test]
+    fn test_entries_carry_when_and_paths() {
+        let bytes = b"- cmd: ls -la\n  when: 1680820391\n- cmd: cd /tmp\n  when: 1680820392\n  paths:\n    - /tmp\n    - /var/tmp\n- cmd: pwd\n";
+        let mut entries = Vec::new();
+        entries_from_bytes(bytes, &mut |entry: &HistoryEntry<'_>| {
+            entries.push((
+                entry.command.to_string(),
+                entry.timestamp,
+                entry.paths.to_vec(),
+            ))
+        });
+
+        assert_eq!(
+            entries,
+            vec![
+                ("ls -la".to_string(), Some(1680820391), vec![]),
+                (
+                    "cd /tmp".to_string(),
+                    Some(1680820392),
+                    vec!["/tmp".to_string(), "/var/tmp".to_string()]
+                ),
+                ("pwd".to_string(), None, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unescape_cmd() {
+        assert_eq!(unescape_cmd("ls -la"), "ls -la");
+        assert_eq!(unescape_cmd("begin\\n  make\\nend"), "begin\n  make\nend");
+        assert_eq!(unescape_cmd("echo a\\\\b"), "echo a\\b");
+        assert_eq!(unescape_cmd("echo \\$HOME\\"), "echo \\$HOME\\");
+    }
+
+    #[test]
+    fn test_multiline_function_counts_each_line() {
+        let bytes = b"- cmd: begin\\n  git fetch\\n  cargo build\\nend\n  when: 1680820391\n- cmd: echo a\\\\b | wc -c\n";
+        let mut cmd_count = AHashMap::default();
+        let filtered = filtered_commands(&[], false);
+        entries_from_bytes(bytes, &mut |entry: &HistoryEntry<'_>| {
+            for_each_entry_command_name(entry, &filtered, false, |name| {
+                increment_count(&mut cmd_count, name)
+            })
+        });
+
+        assert_eq!(cmd_count.get("git"), Some(&1));
+        assert_eq!(cmd_count.get("cargo"), Some(&1));
+        assert_eq!(cmd_count.get("echo"), Some(&1));
+        assert_eq!(cmd_count.get("wc"), Some(&1));
+        assert_eq!(cmd_count.len(), 4);
+    }
+
+    #[test]
+    fn test_fish_reserved_words_are_not_counted() {
+        let bytes = b"- cmd: for f in *; echo $f; end\n- cmd: make; and ./run; or echo fail\n";
+        let mut cmd_count = AHashMap::default();
+        let filtered = filtered_commands(&[], false);
+        entries_from_bytes(bytes, &mut |entry: &HistoryEntry<'_>| {
+            for_each_entry_command_name(entry, &filtered, false, |name| {
+                increment_count(&mut cmd_count, name)
+            })
+        });
+
+        let mut names: Vec<(&str, usize)> = cmd_count
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![("echo", 2), ("for", 1), ("make", 1), ("run", 1)]
+        );
+    }
 }
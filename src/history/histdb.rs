@@ -0,0 +1,66 @@
+//! zsh-histdb database parser
+//!
+//! zsh-histdb keeps history in an SQLite database, usually
+//! `~/.histdb/zsh-history.db`. Command texts and `(host, dir)` places are
+//! stored once in their own tables and referenced from `history`, which
+//! holds the start time, duration and exit status of every command.
+
+use std::io;
+
+use super::entry::HistoryEntry;
+use super::sqlite::{open_read_only, to_io_error};
+
+/// Read a zsh-histdb database and call `f` for every entry, in the order
+/// the commands were run.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let conn = open_read_only(file_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT commands.argv, history.start_time, history.duration, \
+                    places.dir, history.exit_status, places.host, \
+                    history.session \
+             FROM history \
+             JOIN commands ON commands.id = history.command_id \
+             LEFT JOIN places ON places.id = history.place_id \
+             ORDER BY history.id",
+        )
+        .map_err(to_io_error)?;
+    let mut rows = stmt.query([]).map_err(to_io_error)?;
+
+    while let Some(row) = rows.next().map_err(to_io_error)? {
+        // Entries that are not valid UTF-8 are skipped like in text files
+        let Ok(command) = row.get_ref(0).map_err(to_io_error)?.as_str() else {
+            continue;
+        };
+        if command.trim().is_empty() {
+            continue;
+        }
+        let start_time: Option<i64> = row.get(1).map_err(to_io_error)?;
+        // Commands interrupted before zsh-histdb saw them finish have no
+        // duration
+        let duration: Option<i64> = row.get(2).map_err(to_io_error)?;
+        let cwd = row.get_ref(3).map_err(to_io_error)?.as_str_or_null();
+        let exit_status: Option<i64> = row.get(4).map_err(to_io_error)?;
+        let hostname = row.get_ref(5).map_err(to_io_error)?.as_str_or_null();
+        let session: Option<i64> = row.get(6).map_err(to_io_error)?;
+        let session = session.map(|session| session.to_string());
+
+        f(&HistoryEntry {
+            timestamp: start_time,
+            duration: duration.and_then(|secs| u64::try_from(secs).ok()),
+            cwd: cwd.ok().flatten(),
+            exit_status,
+            hostname: hostname.ok().flatten(),
+            session: session.as_deref(),
+            ..HistoryEntry::new(command)
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,436 @@
+//! Access to the bytes of a history input.
+//!
+//! Regular files are memory-mapped. Stdin (`-`) and other inputs that can
+//! only be read once, such as FIFOs and process substitution, are read to
+//! the end the first time they are opened and kept, so the same input can
+//! be sniffed by format detection and then replayed into whichever parser
+//! wins. Files that cannot be mapped are read into memory instead.
+//!
+//! A shell may rewrite its history while it is read. Mapping waits briefly
+//! for writers that announce themselves. A file that a writer still holds
+//! after the wait, or whose length changes while it is mapped, is copied
+//! instead, up to the length it had when it was opened, since touching
+//! mapped pages past the new end of a file raises SIGBUS.
+//!
+//! Inputs compressed with gzip or zstd, recognised by their magic bytes,
+//! are decompressed into memory.
+
+use ahash::AHashMap;
+use flate2::read::MultiGzDecoder;
+use memmap2::{Mmap, MmapOptions};
+use ruzstd::decoding::StreamingDecoder;
+use std::fs::{self, TryLockError};
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::ops::Deref;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Path naming stdin
+pub(crate) const STDIN: &str = "-";
+
+/// How long to wait for a shell that is rewriting a history file
+const WRITER_WAIT: Duration = Duration::from_millis(500);
+
+const WRITER_POLL: Duration = Duration::from_millis(10);
+
+/// Age after which zsh itself treats a leftover lock file as stale
+const ZSH_STALE_LOCK: Duration = Duration::from_secs(10);
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Most bytes [`head`] reads, for inputs with long or no lines
+const HEAD_LIMIT: usize = 1 << 20;
+
+/// Contents of an opened input
+pub(crate) enum Input {
+    /// Mapped while holding a shared lock on the file, which writers that
+    /// lock it, like fish, wait for
+    Mapped {
+        mmap: Mmap,
+        _file: fs::File,
+    },
+    Buffered(Arc<[u8]>),
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped { mmap, .. } => mmap,
+            Self::Buffered(bytes) => bytes,
+        }
+    }
+}
+
+/// Open `path`, reading streams, unmappable files and compressed files
+/// into memory.
+pub(crate) fn open(path: &str) -> io::Result<Input> {
+    let input = open_raw(path)?;
+    match decompress(&input)? {
+        Some(bytes) => Ok(Input::Buffered(bytes.into())),
+        None => Ok(input),
+    }
+}
+
+fn open_raw(path: &str) -> io::Result<Input> {
+    if is_stream(path) {
+        return buffered_stream(path).map(Input::Buffered);
+    }
+    let file = fs::File::open(path)?;
+    // Map the length the file has now; later appends are not read
+    let len = file.metadata()?.len();
+    if !wait_for_writers(path, &file) {
+        return read_snapshot(file, len);
+    }
+
+    let Ok(map_len) = usize::try_from(len) else {
+        return read_snapshot(file, len);
+    };
+    // Some network filesystems refuse to map files
+    let Ok(mmap) = (unsafe { MmapOptions::new().len(map_len).map(&file) })
+    else {
+        return read_snapshot(file, len);
+    };
+    if file.metadata()?.len() != len {
+        // Rewritten while being mapped
+        drop(mmap);
+        return read_snapshot(file, len);
+    }
+    Ok(Input::Mapped { mmap, _file: file })
+}
+
+/// The first `lines` lines of `path`, decompressed, for sniffing its
+/// format without reading or decompressing the rest of a file.
+///
+/// A stream is read whole, since its parser has to replay it anyway.
+pub(crate) fn head(
+    path: &str,
+    lines: usize,
+) -> io::Result<Input> {
+    if is_stream(path) {
+        return open(path);
+    }
+    let mut file = BufReader::new(fs::File::open(path)?);
+    let mut head = Vec::new();
+    if file.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        read_lines(MultiGzDecoder::new(file), lines, &mut head)?;
+    } else if file.fill_buf()?.starts_with(&ZSTD_MAGIC) {
+        // Each decoder reads one frame, and an archive may hold several
+        while !file.fill_buf()?.is_empty() {
+            let decoder = StreamingDecoder::new(&mut file)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if read_lines(decoder, lines, &mut head)? {
+                break;
+            }
+        }
+    } else {
+        read_lines(file, lines, &mut head)?;
+    }
+    Ok(Input::Buffered(head.into()))
+}
+
+/// Append from `reader` to `head` until it holds `lines` lines or
+/// [`HEAD_LIMIT`] bytes. Returns whether it got that far before `reader`
+/// ran out.
+fn read_lines(
+    mut reader: impl Read,
+    lines: usize,
+    head: &mut Vec<u8>,
+) -> io::Result<bool> {
+    let mut chunk = [0; 8192];
+    let mut scanned = head.len();
+    let mut found = head.iter().filter(|&&b| b == b'\n').count();
+    loop {
+        for (i, &b) in head.iter().enumerate().skip(scanned) {
+            if b == b'\n' {
+                found += 1;
+                if found >= lines {
+                    head.truncate(i + 1);
+                    return Ok(true);
+                }
+            }
+        }
+        scanned = head.len();
+        if head.len() >= HEAD_LIMIT {
+            head.truncate(HEAD_LIMIT);
+            return Ok(true);
+        }
+        match reader.read(&mut chunk) {
+            Ok(0) => return Ok(false),
+            Ok(read) => head.extend_from_slice(&chunk[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Read the file into memory, up to the length it had when it was opened,
+/// which a writer cannot pull the pages from under.
+fn read_snapshot(
+    file: fs::File,
+    len: u64,
+) -> io::Result<Input> {
+    let mut bytes = Vec::with_capacity(usize::try_from(len).unwrap_or(0));
+    file.take(len).read_to_end(&mut bytes)?;
+    Ok(Input::Buffered(bytes.into()))
+}
+
+/// Wait until no shell announces that it is rewriting `path`: zsh creates
+/// `<path>.LOCK` and fish locks the file itself.
+///
+/// Returns whether the writers finished in time, in which case a shared
+/// lock on `file` is held unless the filesystem does not support locks.
+fn wait_for_writers(
+    path: &str,
+    file: &fs::File,
+) -> bool {
+    let zsh_lock = format!("{}.LOCK", path);
+    let deadline = Instant::now() + WRITER_WAIT;
+    loop {
+        if !holds_zsh_lock(Path::new(&zsh_lock)) {
+            match file.try_lock_shared() {
+                Ok(()) => return true,
+                Err(TryLockError::WouldBlock) => {}
+                // The filesystem does not support locks
+                Err(TryLockError::Error(_)) => return true,
+            }
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(WRITER_POLL);
+    }
+}
+
+fn holds_zsh_lock(lock: &Path) -> bool {
+    fs::symlink_metadata(lock)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| {
+            modified.elapsed().is_ok_and(|age| age < ZSH_STALE_LOCK)
+        })
+}
+
+/// Whether `path` is stdin or another input that can only be read once.
+pub(crate) fn is_stream(path: &str) -> bool {
+    path == STDIN
+        || fs::metadata(path)
+            .is_ok_and(|metadata| !metadata.is_file() && !metadata.is_dir())
+}
+
+/// Whether `path` is a stream that nothing has buffered yet, so a line
+/// parser can read it as it arrives through [`stream`].
+pub(crate) fn is_unbuffered_stream(path: &str) -> bool {
+    is_stream(path) && !buffers().lock().unwrap().contains_key(path)
+}
+
+/// Read a stream line by line without keeping it.
+///
+/// A compressed stream has to be decompressed as a whole first.
+pub(crate) fn stream(path: &str) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn Read> = if path == STDIN {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(fs::File::open(path)?)
+    };
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    reader.by_ref().take(ZSTD_MAGIC.len() as u64).read_to_end(&mut head)?;
+    if !is_compressed(&head) {
+        return Ok(Box::new(BufReader::new(Cursor::new(head).chain(reader))));
+    }
+    reader.read_to_end(&mut head)?;
+    let bytes = decompress(&head)?.unwrap_or(head);
+    Ok(Box::new(Cursor::new(bytes)))
+}
+
+/// Whether the file at `path` is compressed
+pub(crate) fn is_compressed_file(path: &str) -> io::Result<bool> {
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    fs::File::open(path)?
+        .take(ZSTD_MAGIC.len() as u64)
+        .read_to_end(&mut head)?;
+    Ok(is_compressed(&head))
+}
+
+fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC) || bytes.starts_with(&ZSTD_MAGIC)
+}
+
+/// The decompressed contents of gzip or zstd data, or `None` when `bytes`
+/// are not compressed.
+fn decompress(bytes: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    let mut decompressed = Vec::new();
+    if bytes.starts_with(&GZIP_MAGIC) {
+        MultiGzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    } else if bytes.starts_with(&ZSTD_MAGIC) {
+        // Each decoder reads one frame, and an archive may hold several
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            StreamingDecoder::new(&mut rest)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                .read_to_end(&mut decompressed)?;
+        }
+    } else {
+        return Ok(None);
+    }
+    Ok(Some(decompressed))
+}
+
+/// Streams read so far, by path
+fn buffers() -> &'static Mutex<AHashMap<String, Arc<[u8]>>> {
+    static BUFFERS: OnceLock<Mutex<AHashMap<String, Arc<[u8]>>>> =
+        OnceLock::new();
+    BUFFERS.get_or_init(Mutex::default)
+}
+
+/// All of a stream, read on first use.
+fn buffered_stream(path: &str) -> io::Result<Arc<[u8]>> {
+    if let Some(bytes) = buffers().lock().unwrap().get(path) {
+        return Ok(Arc::clone(bytes));
+    }
+    let mut bytes = Vec::new();
+    stream(path)?.read_to_end(&mut bytes)?;
+    let bytes: Arc<[u8]> = bytes.into();
+    buffers().lock().unwrap().insert(path.to_string(), Arc::clone(&bytes));
+    Ok(bytes)
+}
+
+/// A temporary file written by [`spool`], removed when dropped
+pub(crate) struct Spooled(PathBuf);
+
+impl Deref for Spooled {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Spooled {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Write the contents of a stream or compressed file to a new temporary
+/// file, for readers such as SQLite that need a file. The file is removed
+/// when the returned guard is dropped.
+///
+/// The file is created afresh, readable only by the current user, so a
+/// path planted in the shared temporary directory is never written
+/// through.
+pub(crate) fn spool(path: &str) -> io::Result<Spooled> {
+    static SPOOLED: AtomicUsize = AtomicUsize::new(0);
+
+    let bytes = open(path)?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos());
+    loop {
+        let spooled = std::env::temp_dir().join(format!(
+            "histop_stream_{}_{}_{:08x}",
+            std::process::id(),
+            SPOOLED.fetch_add(1, Ordering::Relaxed),
+            nanos
+        ));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = match options.open(&spooled) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        let spooled = Spooled(spooled);
+        file.write_all(&bytes)?;
+        return Ok(spooled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spool_creates_private_file() {
+        let dir = std::env::temp_dir()
+            .join(format!("histop_spool_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("history.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(b"ls\n").unwrap();
+        fs::write(&source, encoder.finish().unwrap()).unwrap();
+
+        let spooled = spool(source.to_str().unwrap()).unwrap();
+        assert_eq!(fs::read(&*spooled).unwrap(), b"ls\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&*spooled).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let path = spooled.to_path_buf();
+        drop(spooled);
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_copies_file_held_by_writer() {
+        let dir = std::env::temp_dir()
+            .join(format!("histop_open_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let history = dir.join("history");
+        fs::write(&history, b"ls\ngit status\n").unwrap();
+        let path = history.to_str().unwrap();
+
+        let input = open(path).unwrap();
+        assert!(matches!(input, Input::Mapped { .. }));
+        assert_eq!(&*input, b"ls\ngit status\n");
+        drop(input);
+
+        // zsh keeps `<path>.LOCK` while it rewrites the file
+        fs::write(format!("{}.LOCK", path), b"").unwrap();
+        let input = open(path).unwrap();
+        assert!(matches!(input, Input::Buffered(_)));
+        assert_eq!(&*input, b"ls\ngit status\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_head_stops_before_corrupt_tail() {
+        let dir = std::env::temp_dir()
+            .join(format!("histop_head_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("history.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        );
+        for i in 0..100_000 {
+            writeln!(encoder, "echo {}", i).unwrap();
+        }
+        let mut compressed = encoder.finish().unwrap();
+        // Cut the archive short, which only a full read notices
+        compressed.truncate(compressed.len() / 2);
+        fs::write(&source, compressed).unwrap();
+        let path = source.to_str().unwrap();
+
+        assert!(open(path).is_err());
+        assert_eq!(&*head(path, 3).unwrap(), b"echo 0\necho 1\necho 2\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
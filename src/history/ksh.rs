@@ -0,0 +1,192 @@
+//! ksh93 and mksh binary history parsers
+//!
+//! Both shells keep history in a binary file, `~/.sh_history` for ksh and
+//! usually `~/.mksh_history` for mksh, told apart by a two-byte magic:
+//!
+//! - ksh93 (and ksh88) write `0x81 0x01`, then every command followed by
+//!   a NUL. `0x81 0x00` after a NUL starts a command number marker: the
+//!   two bytes, a three-byte number and another NUL.
+//! - mksh (and pdksh) write `0xAB 0xCD`, then records made of `0xFF`, a
+//!   four-byte line number and the NUL-terminated command.
+
+use bstr::ByteSlice;
+use std::io;
+
+use super::entry::HistoryEntry;
+use super::input;
+
+/// Leading bytes of a ksh93 history file
+pub(super) const KSH_MAGIC: [u8; 2] = [0x81, 0x01];
+/// Leading bytes of a mksh history file
+pub(super) const MKSH_MAGIC: [u8; 2] = [0xAB, 0xCD];
+
+/// ksh93 `HIST_CMDNO`, which starts a command number marker
+const KSH_CMDNO: u8 = 0x81;
+/// ksh93 marker: `HIST_CMDNO`, a NUL, a three-byte number and a NUL
+const KSH_MARKER_LEN: usize = 6;
+/// mksh `COMMAND`, which starts every record
+const MKSH_COMMAND: u8 = 0xFF;
+/// Big-endian line number after `MKSH_COMMAND`
+const MKSH_LINE_NUMBER_LEN: usize = 4;
+
+/// Parse a ksh93 history file and call `f` for every entry.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let input = input::open(file_path)?;
+    ksh_entries_from_bytes(&input, &mut f);
+
+    Ok(())
+}
+
+/// Parse a mksh history file and call `f` for every entry.
+pub fn for_each_mksh_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let input = input::open(file_path)?;
+    mksh_entries_from_bytes(&input, &mut f);
+
+    Ok(())
+}
+
+fn ksh_entries_from_bytes<F>(
+    bytes: &[u8],
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let Some(mut rest) = bytes.strip_prefix(&KSH_MAGIC) else {
+        return;
+    };
+    while !rest.is_empty() {
+        if rest.len() >= 2 && rest[0] == KSH_CMDNO && rest[1] == 0 {
+            // The marker's number may contain NULs, so skip it whole
+            rest = rest.get(KSH_MARKER_LEN..).unwrap_or_default();
+            continue;
+        }
+        let (record, next) = split_record(rest);
+        emit_command(record, f);
+        rest = next;
+    }
+}
+
+fn mksh_entries_from_bytes<F>(
+    bytes: &[u8],
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let Some(mut rest) = bytes.strip_prefix(&MKSH_MAGIC) else {
+        return;
+    };
+    while !rest.is_empty() {
+        if rest[0] != MKSH_COMMAND {
+            // Resynchronise on the next record after a damaged one
+            rest = match rest.find_byte(MKSH_COMMAND) {
+                Some(i) => &rest[i..],
+                None => break,
+            };
+            continue;
+        }
+        let Some(body) = rest.get(1 + MKSH_LINE_NUMBER_LEN..) else {
+            break;
+        };
+        let (record, next) = split_record(body);
+        emit_command(record, f);
+        rest = next;
+    }
+}
+
+/// Split off one NUL-terminated record.
+#[inline]
+fn split_record(bytes: &[u8]) -> (&[u8], &[u8]) {
+    match bytes.find_byte(0) {
+        Some(i) => (&bytes[..i], &bytes[i + 1..]),
+        None => (bytes, &[]),
+    }
+}
+
+fn emit_command<F>(
+    record: &[u8],
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    // Entries that are not valid UTF-8 are skipped like in text files
+    let Ok(command) = record.to_str() else {
+        return;
+    };
+    let command = command.trim_end_matches(['\n', '\r']);
+    if command.trim().is_empty() {
+        return;
+    }
+    f(&HistoryEntry::new(command));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_ksh(bytes: &[u8]) -> Vec<String> {
+        let mut entries = Vec::new();
+        ksh_entries_from_bytes(bytes, &mut |entry: &HistoryEntry<'_>| {
+            entries.push(entry.command.to_string())
+        });
+        entries
+    }
+
+    fn collect_mksh(bytes: &[u8]) -> Vec<String> {
+        let mut entries = Vec::new();
+        mksh_entries_from_bytes(bytes, &mut |entry: &HistoryEntry<'_>| {
+            entries.push(entry.command.to_string())
+        });
+        entries
+    }
+
+    #[test]
+    fn test_ksh_records() {
+        let bytes = b"\x81\x01ls -la\n\0for f in *\ndo echo $f\ndone\n\0";
+        assert_eq!(
+            collect_ksh(bytes),
+            vec!["ls -la", "for f in *\ndo echo $f\ndone"]
+        );
+    }
+
+    #[test]
+    fn test_ksh_skips_command_number_marker() {
+        // The second marker's number, 256, contains NULs
+        let bytes = b"\x81\x01\x81\x00\x00\x00\x01\x00git status\n\0\
+                      \x81\x00\x00\x01\x00\x00pwd\n\0";
+        assert_eq!(collect_ksh(bytes), vec!["git status", "pwd"]);
+    }
+
+    #[test]
+    fn test_mksh_records() {
+        let bytes = b"\xAB\xCD\xFF\x00\x00\x00\x01ls -la\0\
+                      \xFF\x00\x00\x00\x02git \xE9\0\
+                      \xFF\x00\x00\x01\x00pwd\0";
+        // The second record is not valid UTF-8 and is skipped
+        assert_eq!(collect_mksh(bytes), vec!["ls -la", "pwd"]);
+    }
+
+    #[test]
+    fn test_mksh_resynchronises_after_damaged_record() {
+        let bytes = b"\xAB\xCD\x00garbage\xFF\x00\x00\x00\x01make\0";
+        assert_eq!(collect_mksh(bytes), vec!["make"]);
+    }
+
+    #[test]
+    fn test_missing_magic_yields_nothing() {
+        assert!(collect_ksh(b"ls -la\0").is_empty());
+        assert!(collect_mksh(b"ls -la\0").is_empty());
+        assert!(collect_mksh(b"\xAB\xCD\xFF\x00\x00").is_empty());
+    }
+}
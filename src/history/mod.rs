@@ -1,11 +1,69 @@
 //! History parsing module.
 
+pub mod atuin;
 pub mod detect;
+pub mod entry;
 pub mod fish;
+pub mod histdb;
+mod input;
+pub mod ksh;
+pub mod nushell;
 pub mod powershell;
+pub mod repl;
 pub mod shell;
 pub mod simple_history;
+mod sqlite;
 pub mod tcsh;
+pub mod xonsh;
 
-pub use detect::{HistoryFormat, detect_history_format};
+pub use detect::{
+    DetectionReport, FORMAT_NAMES, HistoryFormat, detect_history_format,
+    explain_history_format,
+};
+pub use entry::{HistoryEntry, Syntax};
 pub use shell::count_from_file;
+
+/// Walk a history file in the given format and call `f` for every entry.
+///
+/// When `no_hist` is set the file is read as raw lines regardless of
+/// `format`.
+pub fn for_each_entry<F>(
+    format: HistoryFormat,
+    file_path: &str,
+    no_hist: bool,
+    f: F,
+) -> Result<(), std::io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    if no_hist {
+        return shell::for_each_entry(file_path, true, f);
+    }
+
+    match format {
+        HistoryFormat::Shell => shell::for_each_entry(file_path, false, f),
+        HistoryFormat::Raw => shell::for_each_entry(file_path, true, f),
+        HistoryFormat::BashTimestamped => {
+            shell::for_each_timestamped_entry(file_path, f)
+        }
+        HistoryFormat::Fish => fish::for_each_entry(file_path, f),
+        HistoryFormat::PowerShell => powershell::for_each_entry(file_path, f),
+        HistoryFormat::Tcsh => tcsh::for_each_entry(file_path, f),
+        HistoryFormat::Nushell => nushell::for_each_entry(file_path, f),
+        HistoryFormat::NushellSqlite => {
+            nushell::for_each_sqlite_entry(file_path, f)
+        }
+        HistoryFormat::Xonsh => xonsh::for_each_entry(file_path, f),
+        HistoryFormat::Atuin => atuin::for_each_entry(file_path, f),
+        HistoryFormat::ZshHistdb => histdb::for_each_entry(file_path, f),
+        HistoryFormat::Ksh => ksh::for_each_entry(file_path, f),
+        HistoryFormat::Mksh => ksh::for_each_mksh_entry(file_path, f),
+        HistoryFormat::PythonRepl
+        | HistoryFormat::NodeRepl
+        | HistoryFormat::Psql
+        | HistoryFormat::SqliteCli
+        | HistoryFormat::Irb => {
+            repl::for_each_entry(file_path, format.syntax(), f)
+        }
+    }
+}
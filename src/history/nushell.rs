@@ -0,0 +1,130 @@
+//! Nushell history parser
+//!
+//! Nushell keeps its history either as plain text (`history.txt`, one
+//! entry per line with embedded newlines written as `<\n>`) or in an
+//! SQLite database (`history.sqlite3`) that also records the start time,
+//! working directory, duration and exit status of every command.
+
+use bstr::ByteSlice;
+use std::borrow::Cow;
+use std::io;
+
+use super::entry::HistoryEntry;
+use super::input;
+use super::simple_history::trim_line_end;
+use super::sqlite::{open_read_only, to_io_error};
+
+/// How the plain text format writes a newline inside an entry
+const NEWLINE_ESCAPE: &str = "<\\n>";
+
+/// Parse a plain text `history.txt` and call `f` for every entry.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let input = input::open(file_path)?;
+    entries_from_bytes(&input, &mut f);
+
+    Ok(())
+}
+
+fn entries_from_bytes<F>(
+    bytes: &[u8],
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    for line_bytes in bstr::ByteSlice::lines(bytes) {
+        let Ok(line) = line_bytes.to_str() else {
+            continue;
+        };
+        let line = trim_line_end(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = if line.contains(NEWLINE_ESCAPE) {
+            Cow::Owned(line.replace(NEWLINE_ESCAPE, "\n"))
+        } else {
+            Cow::Borrowed(line)
+        };
+        f(&HistoryEntry::new(&command));
+    }
+}
+
+/// Read a `history.sqlite3` database and call `f` for every entry, in the
+/// order the commands were run.
+pub fn for_each_sqlite_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let conn = open_read_only(file_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT command_line, start_timestamp, duration_ms, cwd, exit_status \
+             FROM history ORDER BY id",
+        )
+        .map_err(to_io_error)?;
+    let mut rows = stmt.query([]).map_err(to_io_error)?;
+
+    while let Some(row) = rows.next().map_err(to_io_error)? {
+        // Entries that are not valid UTF-8 are skipped like in text files
+        let Ok(command) = row.get_ref(0).map_err(to_io_error)?.as_str() else {
+            continue;
+        };
+        if command.trim().is_empty() {
+            continue;
+        }
+        let start_ms: Option<i64> = row.get(1).map_err(to_io_error)?;
+        let duration_ms: Option<i64> = row.get(2).map_err(to_io_error)?;
+        let cwd = row.get_ref(3).map_err(to_io_error)?.as_str_or_null();
+        let exit_status: Option<i64> = row.get(4).map_err(to_io_error)?;
+
+        f(&HistoryEntry {
+            timestamp: start_ms.map(|ms| ms.div_euclid(1000)),
+            duration: duration_ms
+                .and_then(|ms| u64::try_from(ms).ok())
+                .map(|ms| ms / 1000),
+            cwd: cwd.ok().flatten(),
+            exit_status,
+            ..HistoryEntry::new(command)
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_entries(bytes: &[u8]) -> Vec<String> {
+        let mut entries = Vec::new();
+        entries_from_bytes(bytes, &mut |entry: &HistoryEntry<'_>| {
+            entries.push(entry.command.to_string())
+        });
+        entries
+    }
+
+    #[test]
+    fn test_plaintext_entries() {
+        assert_eq!(
+            collect_entries(b"ls -la\n\ngit status\r\n"),
+            vec!["ls -la".to_string(), "git status".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_plaintext_newline_escape() {
+        assert_eq!(
+            collect_entries(b"def greet [] {<\\n>  echo hi<\\n>}\n"),
+            vec!["def greet [] {\n  echo hi\n}".to_string()]
+        );
+    }
+}
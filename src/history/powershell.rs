@@ -1,5 +1,7 @@
 use ahash::AHashMap;
 
+use super::entry::HistoryEntry;
+
 pub fn count_from_file(
     file_path: &str,
     ignore: &[String],
@@ -11,6 +13,20 @@ pub fn count_from_file(
     })
 }
 
+/// Walk a PowerShell history file and call `f` for every entry.
+///
+/// PSReadLine does not record any metadata, so entries only carry the
+/// command text.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    f: F,
+) -> Result<(), std::io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    super::simple_history::for_each_entry(file_path, |_| false, |_| None, f)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
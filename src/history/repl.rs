@@ -0,0 +1,137 @@
+//! REPL history parser (python, node, psql, sqlite, irb)
+//!
+//! These tools keep one entry per line through readline or libedit.
+//! libedit files start with `_HiStOrY_V2_` and escape spaces and other
+//! special characters as octal (`\040`), and psql stores the newlines of
+//! a multi-line query as `0x01`.
+
+use bstr::ByteSlice;
+use std::borrow::Cow;
+use std::io;
+
+use super::entry::{HistoryEntry, Syntax};
+use super::input;
+use super::simple_history::trim_line_end;
+
+/// First line of a history file written by libedit
+const LIBEDIT_HEADER: &[u8] = b"_HiStOrY_V2_";
+/// psql's stand-in for a newline inside a history entry
+const PSQL_NEWLINE: char = '\u{1}';
+
+/// Parse a REPL history and call `f` for every entry, tagged with the
+/// language it is written in.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    syntax: Syntax,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let input = input::open(file_path)?;
+    entries_from_bytes(&input, syntax, &mut f);
+
+    Ok(())
+}
+
+fn entries_from_bytes<F>(
+    bytes: &[u8],
+    syntax: Syntax,
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let mut lines = bstr::ByteSlice::lines(bytes).peekable();
+    let libedit = lines.next_if(|line| *line == LIBEDIT_HEADER).is_some();
+
+    for line_bytes in lines {
+        let line_bytes = if libedit {
+            Cow::Owned(unvis(line_bytes))
+        } else {
+            Cow::Borrowed(line_bytes)
+        };
+        // Entries that are not valid UTF-8 are skipped like in text files
+        let Ok(line) = line_bytes.to_str() else {
+            continue;
+        };
+        let line = trim_line_end(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = if line.contains(PSQL_NEWLINE) {
+            Cow::Owned(line.replace(PSQL_NEWLINE, "\n"))
+        } else {
+            Cow::Borrowed(line)
+        };
+        f(&HistoryEntry { syntax, ..HistoryEntry::new(&command) });
+    }
+}
+
+/// Undo libedit's escaping: `\ooo` octal bytes and `\\`.
+fn unvis(line: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(line.len());
+    let mut i = 0;
+    while i < line.len() {
+        if line[i] == b'\\' {
+            if line.get(i + 1) == Some(&b'\\') {
+                out.push(b'\\');
+                i += 2;
+                continue;
+            }
+            if let Some(digits) = line.get(i + 1..i + 4)
+                && digits.iter().all(|b| (b'0'..=b'7').contains(b))
+            {
+                let value = digits
+                    .iter()
+                    .fold(0_u32, |acc, b| acc * 8 + u32::from(b - b'0'));
+                if let Ok(byte) = u8::try_from(value) {
+                    out.push(byte);
+                    i += 4;
+                    continue;
+                }
+            }
+        }
+        out.push(line[i]);
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_entries(bytes: &[u8]) -> Vec<String> {
+        let mut entries = Vec::new();
+        entries_from_bytes(bytes, Syntax::Sql, &mut |entry| {
+            assert_eq!(entry.syntax, Syntax::Sql);
+            entries.push(entry.command.to_string())
+        });
+        entries
+    }
+
+    #[test]
+    fn test_readline_entries() {
+        assert_eq!(
+            collect_entries(b"\\dt\n\nselect *\x01from users;\r\n"),
+            vec!["\\dt".to_string(), "select *\nfrom users;".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_libedit_entries_are_unescaped() {
+        assert_eq!(
+            collect_entries(
+                b"_HiStOrY_V2_\n\\\\d\\040users\nselect\\040'\\303\\251';\n"
+            ),
+            vec!["\\d users".to_string(), "select 'é';".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_unvis_keeps_unknown_escapes() {
+        assert_eq!(unvis(b"a\\nb\\04"), b"a\\nb\\04".to_vec());
+        assert_eq!(unvis(b"\\777"), b"\\777".to_vec());
+    }
+}
@@ -1,12 +1,12 @@
 //! Shell history parsing module
 
-use ahash::{AHashMap, AHashSet};
+use ahash::AHashMap;
 use bstr::ByteSlice;
-use memmap2::Mmap;
-use std::fs;
-use std::io::{BufRead, BufReader};
+use std::io::BufRead;
 
-use crate::shared::command_parse::{SplitCommands, get_first_word};
+use super::entry::{HistoryEntry, parse_timestamp};
+use super::input;
+use super::simple_history::{count_commands, filtered_commands};
 
 /// Count commands from a history file
 ///
@@ -17,57 +17,178 @@ pub fn count_from_file(
     no_hist: bool,
 ) -> Result<AHashMap<String, usize>, std::io::Error> {
     let mut cmd_count: AHashMap<String, usize> = AHashMap::default();
+    let filtered_commands = filtered_commands(ignore, no_hist);
 
-    let mut filtered_commands: AHashSet<&str> =
-        AHashSet::with_capacity(ignore.len() + 2);
-    if !no_hist {
-        filtered_commands.insert("sudo");
-        filtered_commands.insert("doas");
-    }
-    for s in ignore {
-        filtered_commands.insert(s.as_str());
+    for_each_entry(file_path, no_hist, |entry| {
+        count_commands(
+            &mut cmd_count,
+            entry.command,
+            &filtered_commands,
+            no_hist,
+        )
+    })?;
+
+    Ok(cmd_count)
+}
+
+/// Walk a bash/zsh history file and call `f` for every entry.
+///
+/// zsh extended history (`: <start>:<elapsed>;cmd`) provides the timestamp
+/// and duration, and bash `HISTTIMEFORMAT` lines (`#1680820391`) provide
+/// the timestamp of the entry that follows them. When `no_hist` is set the
+/// input is treated as raw lines without any metadata.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    no_hist: bool,
+    mut f: F,
+) -> Result<(), std::io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    if input::is_unbuffered_stream(file_path) {
+        let reader = input::stream(file_path)?;
+        entries_from_reader(reader, no_hist, &mut f)?;
+    } else {
+        let input = input::open(file_path)?;
+        entries_from_bytes(&input, no_hist, &mut f);
     }
 
-    if file_path == "-" {
-        let stdin = std::io::stdin();
-        let reader = BufReader::new(stdin.lock());
-        count_from_reader(reader, &mut cmd_count, &filtered_commands, no_hist)?;
+    Ok(())
+}
+
+/// Walk a bash history written with `HISTTIMEFORMAT` set and call `f`
+/// for every entry.
+///
+/// Every `#<epoch>` line starts a new entry, and all lines up to the next
+/// one belong to it, so multi-line commands keep their timestamp and reach
+/// `f` as a single entry. Lines before the first timestamp were written
+/// without one and are emitted one entry per line.
+pub fn for_each_timestamped_entry<F>(
+    file_path: &str,
+    mut f: F,
+) -> Result<(), std::io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let mut pending = TimestampedEntry::default();
+    if input::is_unbuffered_stream(file_path) {
+        let mut reader = input::stream(file_path)?;
+        let mut line_buf = Vec::with_capacity(256);
+        loop {
+            line_buf.clear();
+            if reader.read_until(b'\n', &mut line_buf)? == 0 {
+                break;
+            }
+            if let Ok(line) = line_buf.to_str() {
+                pending.process_line(trim_line_end(line), &mut f);
+            }
+        }
     } else {
-        let file = fs::File::open(file_path)?;
-        let mmap = unsafe { Mmap::map(&file)? };
-        count_from_bytes(&mmap, &mut cmd_count, &filtered_commands, no_hist);
+        let input = input::open(file_path)?;
+        for line_bytes in bstr::ByteSlice::lines(&input[..]) {
+            if let Ok(line) = line_bytes.to_str() {
+                pending.process_line(trim_line_end(line), &mut f);
+            }
+        }
     }
+    pending.flush(&mut f);
 
-    Ok(cmd_count)
+    Ok(())
+}
+
+/// Bash entry being assembled from the lines after its `#<epoch>` line
+#[derive(Default)]
+struct TimestampedEntry {
+    command: String,
+    timestamp: Option<i64>,
 }
 
-fn count_from_bytes(
+impl TimestampedEntry {
+    fn process_line<F>(
+        &mut self,
+        line: &str,
+        f: &mut F,
+    ) where
+        F: FnMut(&HistoryEntry<'_>),
+    {
+        if let Some(timestamp) = parse_bash_timestamp(line) {
+            self.flush(f);
+            self.timestamp = Some(timestamp);
+            return;
+        }
+
+        if self.timestamp.is_none() {
+            if !line.trim().is_empty() {
+                f(&HistoryEntry::new(line));
+            }
+            return;
+        }
+
+        if !self.command.is_empty() {
+            self.command.push('\n');
+        } else if line.trim().is_empty() {
+            return;
+        }
+        self.command.push_str(line);
+    }
+
+    fn flush<F>(
+        &mut self,
+        f: &mut F,
+    ) where
+        F: FnMut(&HistoryEntry<'_>),
+    {
+        let command = self.command.trim_end();
+        if !command.is_empty() {
+            f(&HistoryEntry {
+                timestamp: self.timestamp,
+                ..HistoryEntry::new(command)
+            });
+        }
+        self.command.clear();
+        self.timestamp = None;
+    }
+}
+
+#[derive(Default)]
+struct LineState {
+    /// Inside a backslash-continued entry whose head was already emitted
+    skip: bool,
+    /// Timestamp from a bash `#<epoch>` line, waiting for its entry
+    pending_timestamp: Option<i64>,
+}
+
+fn entries_from_bytes<F>(
     bytes: &[u8],
-    cmd_count: &mut AHashMap<String, usize>,
-    filtered_commands: &AHashSet<&str>,
     no_hist: bool,
-) {
-    let mut skip = false;
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let mut state = LineState::default();
+    let mut unmetafied = Vec::new();
     for line_bytes in bstr::ByteSlice::lines(bytes) {
-        let line = match line_bytes.to_str() {
-            Ok(s) => trim_line_end(s),
-            Err(_) => {
-                continue;
-            }
+        let Some(line) = decode_line(line_bytes, no_hist, &mut unmetafied)
+        else {
+            continue;
         };
 
-        process_line(line, &mut skip, cmd_count, filtered_commands, no_hist);
+        process_line(trim_line_end(line), &mut state, no_hist, f);
     }
 }
 
-fn count_from_reader<R: BufRead>(
+fn entries_from_reader<R, F>(
     mut reader: R,
-    cmd_count: &mut AHashMap<String, usize>,
-    filtered_commands: &AHashSet<&str>,
     no_hist: bool,
-) -> std::io::Result<()> {
-    let mut skip = false;
+    f: &mut F,
+) -> std::io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let mut state = LineState::default();
     let mut line_buf = Vec::with_capacity(256);
+    let mut unmetafied = Vec::new();
 
     loop {
         line_buf.clear();
@@ -76,111 +197,139 @@ fn count_from_reader<R: BufRead>(
             break;
         }
 
-        let line = match line_buf.to_str() {
-            Ok(s) => trim_line_end(s),
-            Err(_) => continue,
+        let Some(line) = decode_line(&line_buf, no_hist, &mut unmetafied)
+        else {
+            continue;
         };
 
-        process_line(line, &mut skip, cmd_count, filtered_commands, no_hist);
+        process_line(trim_line_end(line), &mut state, no_hist, f);
     }
 
     Ok(())
 }
 
-fn process_line(
+fn process_line<F>(
     trimmed_line: &str,
-    skip: &mut bool,
-    cmd_count: &mut AHashMap<String, usize>,
-    filtered_commands: &AHashSet<&str>,
+    state: &mut LineState,
     no_hist: bool,
-) {
+    f: &mut F,
+) where
+    F: FnMut(&HistoryEntry<'_>),
+{
     // Handle zsh extended history format: ": timestamp:0;command"
     // specific check for not no_hist, because we want to treat the file as raw if no_hist is true
     let is_zsh_extended = !no_hist && trimmed_line.starts_with(": ");
-    let actual_line = if is_zsh_extended {
-        if let Some((_, cmd)) = trimmed_line.split_once(';') {
-            cmd
+    let (actual_line, timestamp, duration) = if is_zsh_extended {
+        if let Some((meta, cmd)) = trimmed_line.split_once(';') {
+            let (timestamp, duration) = parse_zsh_metadata(&meta[2..]);
+            (cmd, timestamp, duration)
         } else {
             // Metadata line without command, skip
-            *skip = true;
+            state.skip = true;
             return;
         }
     } else {
-        trimmed_line
+        (trimmed_line, None, None)
     };
 
-    match (
-        *skip,
-        is_zsh_extended && !trimmed_line.contains(';'),
-        !no_hist && actual_line.ends_with('\\'),
-    ) {
-        (false, false, false) => {
-            count_commands(cmd_count, actual_line, filtered_commands, no_hist);
-        }
-        (false, false, true) => {
-            count_commands(cmd_count, actual_line, filtered_commands, no_hist);
-            *skip = true;
-        }
-        (false, true, _) => {
-            *skip = true;
-        }
-        (true, _, true) => {
-            *skip = true;
-        }
-        (true, _, false) => {
-            *skip = false;
-        }
+    if !no_hist
+        && !state.skip
+        && !is_zsh_extended
+        && let Some(timestamp) = parse_bash_timestamp(trimmed_line)
+    {
+        state.pending_timestamp = Some(timestamp);
+        return;
     }
-}
 
-#[inline]
-fn trim_line_end(line: &str) -> &str {
-    line.trim_end_matches(['\n', '\r'])
+    let continues = !no_hist && actual_line.ends_with('\\');
+    if !state.skip && !actual_line.trim().is_empty() {
+        f(&HistoryEntry {
+            timestamp: timestamp.or(state.pending_timestamp.take()),
+            duration,
+            ..HistoryEntry::new(actual_line)
+        });
+    }
+    state.skip = continues;
 }
 
-fn count_commands(
-    cmd_count: &mut AHashMap<String, usize>,
-    line: &str,
-    filtered_commands: &AHashSet<&str>,
+/// zsh's `Meta` byte: the next byte was stored XOR 0x20
+const ZSH_META: u8 = 0x83;
+
+/// Decode a history line as UTF-8.
+///
+/// zsh writes its history "metafied", which turns every multibyte
+/// character containing a byte in 0x83..=0xa2 into invalid UTF-8. Lines
+/// that fail to decode and contain the Meta byte are unmetafied into
+/// `buf` and decoded again; raw (`no_hist`) input is never rewritten.
+fn decode_line<'a>(
+    bytes: &'a [u8],
     no_hist: bool,
+    buf: &'a mut Vec<u8>,
+) -> Option<&'a str> {
+    if let Ok(line) = bytes.to_str() {
+        return Some(line);
+    }
+    if no_hist || !bytes.contains(&ZSH_META) {
+        return None;
+    }
+    unmetafy(bytes, buf);
+    buf.to_str().ok()
+}
+
+/// Undo zsh metafication of `bytes` into `out`.
+fn unmetafy(
+    bytes: &[u8],
+    out: &mut Vec<u8>,
 ) {
-    if no_hist {
-        if let Some(first_word) = line.split_whitespace().next()
-            && !filtered_commands.contains(first_word)
+    out.clear();
+    let mut iter = bytes.iter();
+    while let Some(&byte) = iter.next() {
+        if byte == ZSH_META
+            && let Some(&next) = iter.next()
         {
-            increment_count(cmd_count, first_word);
+            out.push(next ^ 0x20);
+        } else {
+            out.push(byte);
         }
-        return;
     }
+}
 
-    if line.contains('|') {
-        for subcommand in SplitCommands::new(line) {
-            if let Some(first_word) =
-                get_first_word(subcommand, filtered_commands)
-            {
-                increment_count(cmd_count, first_word);
-            }
-        }
-    } else if let Some(first_word) = get_first_word(line, filtered_commands) {
-        increment_count(cmd_count, first_word);
-    }
+/// Parse the `<start>:<elapsed>` part of a zsh extended history line.
+fn parse_zsh_metadata(meta: &str) -> (Option<i64>, Option<u64>) {
+    let (start, elapsed) = meta.split_once(':').unwrap_or((meta, ""));
+    (parse_timestamp(start.trim()), elapsed.trim().parse().ok())
 }
 
+/// Parse a bash `HISTTIMEFORMAT` timestamp line (`#1680820391`).
 #[inline]
-fn increment_count(
-    cmd_count: &mut AHashMap<String, usize>,
-    first_word: &str,
-) {
-    if let Some(existing) = cmd_count.get_mut(first_word) {
-        *existing += 1;
-    } else {
-        cmd_count.insert(first_word.to_string(), 1);
-    }
+fn parse_bash_timestamp(line: &str) -> Option<i64> {
+    line.strip_prefix('#').and_then(parse_timestamp)
+}
+
+#[inline]
+fn trim_line_end(line: &str) -> &str {
+    line.trim_end_matches(['\n', '\r'])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ahash::AHashSet;
+
+    fn collect_entries(
+        bytes: &[u8],
+        no_hist: bool,
+    ) -> Vec<(String, Option<i64>, Option<u64>)> {
+        let mut entries = Vec::new();
+        entries_from_bytes(bytes, no_hist, &mut |entry: &HistoryEntry<'_>| {
+            entries.push((
+                entry.command.to_string(),
+                entry.timestamp,
+                entry.duration,
+            ))
+        });
+        entries
+    }
 
     #[test]
     fn test_count_commands_simple() {
@@ -198,4 +347,179 @@ mod tests {
         assert_eq!(cmd_count.get("ls"), Some(&1));
         assert_eq!(cmd_count.get("grep"), Some(&1));
     }
+
+    #[test]
+    fn test_count_commands_with_lists_and_compound_commands() {
+        let mut cmd_count = AHashMap::default();
+        let filters = AHashSet::from_iter(vec!["sudo", "doas"]);
+        count_commands(
+            &mut cmd_count,
+            "cd foo && make 2>&1 || sudo ./fix & for f in *; do echo $f; done",
+            &filters,
+            false,
+        );
+        let mut names: Vec<(&str, usize)> = cmd_count
+            .iter()
+            .map(|(name, count)| (name.as_str(), *count))
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![("cd", 1), ("echo", 1), ("fix", 1), ("for", 1), ("make", 1)]
+        );
+    }
+
+    #[test]
+    fn test_zsh_extended_entries_carry_timestamp_and_duration() {
+        let entries = collect_entries(
+            b": 1680820391:0;ls -la\n: 1680820392:38;cargo build\n",
+            false,
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("ls -la".to_string(), Some(1680820391), Some(0)),
+                ("cargo build".to_string(), Some(1680820392), Some(38)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zsh_multiline_entry_emits_head_only() {
+        let entries = collect_entries(
+            b": 1680820391:2;make \\\n  -j8\n: 1680820399:0;ls\n",
+            false,
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("make \\".to_string(), Some(1680820391), Some(2)),
+                ("ls".to_string(), Some(1680820399), Some(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bash_timestamp_line_attaches_to_next_entry() {
+        let entries =
+            collect_entries(b"#1680820391\nls -la\ngit status\n", false);
+        assert_eq!(
+            entries,
+            vec![
+                ("ls -la".to_string(), Some(1680820391), None),
+                ("git status".to_string(), None, None),
+            ]
+        );
+    }
+
+    fn collect_timestamped_entries(bytes: &[u8]) -> Vec<(String, Option<i64>)> {
+        let mut entries = Vec::new();
+        let mut pending = TimestampedEntry::default();
+        let mut push = |entry: &HistoryEntry<'_>| {
+            entries.push((entry.command.to_string(), entry.timestamp))
+        };
+        for line in bstr::ByteSlice::lines(bytes) {
+            pending.process_line(line.to_str().unwrap(), &mut push);
+        }
+        pending.flush(&mut push);
+        entries
+    }
+
+    #[test]
+    fn test_timestamped_multiline_entry_keeps_timestamp() {
+        let entries = collect_timestamped_entries(
+            b"#1680820391\nfor f in *; do\n  echo $f\ndone\n#1680820399\nls\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "for f in *; do\n  echo $f\ndone".to_string(),
+                    Some(1680820391)
+                ),
+                ("ls".to_string(), Some(1680820399)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timestamped_lines_before_first_timestamp() {
+        let entries = collect_timestamped_entries(
+            b"git status\n\nls -la\n#1680820391\n\ncargo build\n\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("git status".to_string(), None),
+                ("ls -la".to_string(), None),
+                ("cargo build".to_string(), Some(1680820391)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timestamped_comment_line_stays_in_entry() {
+        let entries =
+            collect_timestamped_entries(b"#1680820391\n# note\nmake\n");
+        assert_eq!(
+            entries,
+            vec![("# note\nmake".to_string(), Some(1680820391))]
+        );
+    }
+
+    /// Metafy `s` the way zsh does when writing its history file
+    fn metafy(s: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        for &byte in s.as_bytes() {
+            if byte == 0 || (ZSH_META..=0xa2).contains(&byte) {
+                out.push(ZSH_META);
+                out.push(byte ^ 0x20);
+            } else {
+                out.push(byte);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_unmetafy_round_trip() {
+        let mut out = Vec::new();
+        unmetafy(&metafy("cd ~/ドキュメント/à relatório"), &mut out);
+        assert_eq!(out, "cd ~/ドキュメント/à relatório".as_bytes());
+    }
+
+    #[test]
+    fn test_metafied_zsh_lines_are_decoded() {
+        let mut bytes = b": 1680820391:0;".to_vec();
+        bytes.extend(metafy("vim 日本語.txt\n"));
+        bytes.extend(b": 1680820392:0;");
+        bytes.extend(metafy("ls ação à\n"));
+
+        let entries = collect_entries(&bytes, false);
+        assert_eq!(
+            entries,
+            vec![
+                ("vim 日本語.txt".to_string(), Some(1680820391), Some(0)),
+                ("ls ação à".to_string(), Some(1680820392), Some(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metafied_line_stays_raw_in_no_hist_mode() {
+        let entries = collect_entries(&metafy("echo 日本\n"), true);
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_no_hist_entries_have_no_metadata() {
+        let entries = collect_entries(b": 1680820391:0;ls\n#1\n", true);
+        assert_eq!(
+            entries,
+            vec![
+                (": 1680820391:0;ls".to_string(), None, None),
+                ("#1".to_string(), None, None),
+            ]
+        );
+    }
 }
@@ -4,14 +4,19 @@
 //! entire file, matching the behavior of `shell.rs` and `fish.rs`.
 
 use ahash::{AHashMap, AHashSet};
-use std::fs::File;
 use std::io::BufRead;
-use std::io::BufReader;
 
 use bstr::ByteSlice;
-use memmap2::Mmap;
 
-use crate::shared::command_parse::{SplitCommands, get_first_word};
+use super::entry::{HistoryEntry, Syntax};
+use super::input;
+use crate::shared::command_parse::{
+    FISH, POSIX_SHELL, ShellLanguage, SplitCommands, get_first_word_for,
+};
+use crate::shared::statement_parse::{
+    JAVASCRIPT, PYTHON, RUBY, for_each_script_statement_name,
+    for_each_sql_statement_name,
+};
 
 /// Count commands from a history file, skipping lines that fail the
 /// provided `skip_line` predicate or contain invalid UTF-8.
@@ -25,19 +30,58 @@ where
     F: Fn(&str) -> bool,
 {
     let mut cmd_count = AHashMap::default();
+    let filtered_commands = filtered_commands(ignore, no_hist);
 
-    let mut filtered_commands = AHashSet::with_capacity(ignore.len() + 2);
-    if !no_hist {
-        filtered_commands.insert("sudo");
-        filtered_commands.insert("doas");
-    }
-    for s in ignore {
-        filtered_commands.insert(s.as_str());
-    }
+    for_each_entry(
+        file_path,
+        skip_line,
+        |_| None,
+        |entry| {
+            count_commands(
+                &mut cmd_count,
+                entry.command,
+                &filtered_commands,
+                no_hist,
+            )
+        },
+    )?;
+
+    Ok(cmd_count)
+}
+
+/// Walk a line-based history file and call `f` for every command line.
+///
+/// Lines for which `timestamp_of` returns a value are treated as metadata
+/// and their timestamp is attached to the next command line. Lines matching
+/// `skip_line`, blank lines and invalid UTF-8 lines are ignored.
+pub fn for_each_entry<S, T, F>(
+    file_path: &str,
+    skip_line: S,
+    timestamp_of: T,
+    mut f: F,
+) -> Result<(), std::io::Error>
+where
+    S: Fn(&str) -> bool,
+    T: Fn(&str) -> Option<i64>,
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let mut pending_timestamp = None;
+    let mut process_line = |line: &str| {
+        if let Some(timestamp) = timestamp_of(line) {
+            pending_timestamp = Some(timestamp);
+            return;
+        }
+        if line.trim().is_empty() || skip_line(line) {
+            return;
+        }
+        f(&HistoryEntry {
+            timestamp: pending_timestamp.take(),
+            ..HistoryEntry::new(line)
+        });
+    };
 
-    if file_path == "-" {
-        let stdin = std::io::stdin();
-        let mut reader = BufReader::new(stdin.lock());
+    if input::is_unbuffered_stream(file_path) {
+        let mut reader = input::stream(file_path)?;
         let mut line_buf = Vec::with_capacity(256);
         loop {
             line_buf.clear();
@@ -49,30 +93,40 @@ where
                 Ok(s) => trim_line_end(s),
                 Err(_) => continue,
             };
-            if line.trim().is_empty() || skip_line(line) {
-                continue;
-            }
-            count_commands(&mut cmd_count, line, &filtered_commands, no_hist);
+            process_line(line);
         }
     } else {
-        let file = File::open(file_path)?;
-        let mmap = unsafe { Mmap::map(&file)? };
+        let input = input::open(file_path)?;
 
-        for line_bytes in bstr::ByteSlice::lines(&*mmap) {
+        for line_bytes in bstr::ByteSlice::lines(&input[..]) {
             let line = match line_bytes.to_str() {
                 Ok(s) => trim_line_end(s),
                 Err(_) => continue,
             };
-
-            if line.trim().is_empty() || skip_line(line) {
-                continue;
-            }
-
-            count_commands(&mut cmd_count, line, &filtered_commands, no_hist);
+            process_line(line);
         }
     }
 
-    Ok(cmd_count)
+    Ok(())
+}
+
+/// Build the set of commands skipped while looking for the first word.
+///
+/// Privilege wrappers (`sudo`, `doas`) are skipped in history mode so the
+/// wrapped command is counted instead.
+pub(crate) fn filtered_commands(
+    ignore: &[String],
+    no_hist: bool,
+) -> AHashSet<&str> {
+    let mut filtered_commands = AHashSet::with_capacity(ignore.len() + 2);
+    if !no_hist {
+        filtered_commands.insert("sudo");
+        filtered_commands.insert("doas");
+    }
+    for s in ignore {
+        filtered_commands.insert(s.as_str());
+    }
+    filtered_commands
 }
 
 #[inline]
@@ -80,27 +134,121 @@ pub(super) fn trim_line_end(line: &str) -> &str {
     line.trim_end_matches(['\n', '\r'])
 }
 
-pub(super) fn count_commands(
+/// Bytes that may separate the commands of an entry
+const COMMAND_SEPARATORS: &[u8] = b"\n;&|";
+
+/// Count the command names found in a single history line.
+pub(crate) fn count_commands(
     cmd_count: &mut AHashMap<String, usize>,
     line: &str,
     filtered_commands: &AHashSet<&str>,
     no_hist: bool,
 ) {
-    if !no_hist && line.as_bytes().find_byte(b'|').is_some() {
-        for subcommand in SplitCommands::new(line) {
+    for_each_command_name(
+        line,
+        filtered_commands,
+        no_hist,
+        &POSIX_SHELL,
+        |first_word| increment_count(cmd_count, first_word),
+    );
+}
+
+/// Call `f` with every command name found in a single history entry
+/// written in `shell`.
+///
+/// In raw mode (`no_hist`) only the first whitespace-separated word is
+/// used; otherwise the entry is split into simple commands on newlines,
+/// pipes and list operators, and wrappers, reserved words, flags and
+/// environment assignments are skipped.
+pub(crate) fn for_each_command_name<'a, F>(
+    line: &'a str,
+    filtered_commands: &AHashSet<&str>,
+    no_hist: bool,
+    shell: &'static ShellLanguage,
+    mut f: F,
+) where
+    F: FnMut(&'a str),
+{
+    if no_hist {
+        if let Some(first_word) = line.split_whitespace().next()
+            && !filtered_commands.contains(first_word)
+        {
+            f(first_word);
+        }
+        return;
+    }
+
+    if line.as_bytes().find_byteset(COMMAND_SEPARATORS).is_some() {
+        for command in SplitCommands::for_shell(line, shell) {
             if let Some(first_word) =
-                get_first_word(subcommand, filtered_commands)
+                get_first_word_for(command, filtered_commands, shell)
             {
-                increment_count(cmd_count, first_word);
+                f(first_word);
             }
         }
-    } else if let Some(first_word) = get_first_word(line, filtered_commands) {
-        increment_count(cmd_count, first_word);
+    } else if let Some(first_word) =
+        get_first_word_for(line, filtered_commands, shell)
+    {
+        f(first_word);
+    }
+}
+
+/// Call `f` with every command name in `entry`, split according to the
+/// entry's syntax.
+///
+/// REPL entries are named by statement instead of by program; `-i` still
+/// skips the names it lists.
+pub(crate) fn for_each_entry_command_name<'a, F>(
+    entry: &HistoryEntry<'a>,
+    filtered_commands: &AHashSet<&str>,
+    no_hist: bool,
+    mut f: F,
+) where
+    F: FnMut(&'a str),
+{
+    let mut unless_filtered = |name: &'a str| {
+        if !filtered_commands.contains(name) {
+            f(name);
+        }
+    };
+    match entry.syntax {
+        Syntax::Shell => for_each_command_name(
+            entry.command,
+            filtered_commands,
+            no_hist,
+            &POSIX_SHELL,
+            f,
+        ),
+        Syntax::Fish => for_each_command_name(
+            entry.command,
+            filtered_commands,
+            no_hist,
+            &FISH,
+            f,
+        ),
+        Syntax::Sql => {
+            for_each_sql_statement_name(entry.command, &mut unless_filtered)
+        }
+        Syntax::Python => for_each_script_statement_name(
+            entry.command,
+            &PYTHON,
+            &mut unless_filtered,
+        ),
+        Syntax::JavaScript => for_each_script_statement_name(
+            entry.command,
+            &JAVASCRIPT,
+            &mut unless_filtered,
+        ),
+        Syntax::Ruby => for_each_script_statement_name(
+            entry.command,
+            &RUBY,
+            &mut unless_filtered,
+        ),
     }
 }
 
 #[inline]
-fn increment_count(
+pub(crate) fn increment_count(
     cmd_count: &mut AHashMap<String, usize>,
     first_word: &str,
 ) {
@@ -166,4 +314,37 @@ mod tests {
 
         fs::remove_file(path).ok();
     }
+
+    #[test]
+    fn test_timestamp_lines_attach_to_next_entry() {
+        let now_nanos =
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "test_simple_history_entries_{}_{}.txt",
+            std::process::id(),
+            now_nanos
+        ));
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "#+1680820391").unwrap();
+        writeln!(file, "ls -la").unwrap();
+        writeln!(file, "git status").unwrap();
+
+        let mut entries = Vec::new();
+        for_each_entry(
+            path.to_str().unwrap(),
+            |_| false,
+            |line| line.strip_prefix("#+").and_then(|ts| ts.parse().ok()),
+            |entry| entries.push((entry.command.to_string(), entry.timestamp)),
+        )
+        .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("ls -la".to_string(), Some(1680820391)),
+                ("git status".to_string(), None),
+            ]
+        );
+
+        fs::remove_file(path).ok();
+    }
 }
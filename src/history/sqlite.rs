@@ -0,0 +1,92 @@
+//! Helpers shared by the parsers of SQLite history databases.
+
+use rusqlite::{Connection, OpenFlags};
+use std::io;
+use std::path::Path;
+
+use super::input;
+
+/// Every SQLite database file starts with this header
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+/// Whether `bytes` start like an SQLite database.
+pub(crate) fn is_sqlite(bytes: &[u8]) -> bool {
+    bytes.starts_with(SQLITE_MAGIC)
+}
+
+/// Open a history database without taking a write lock on it.
+///
+/// A database piped to stdin or a FIFO, or a compressed one, is copied to
+/// a temporary file first, which is removed again once SQLite has read its
+/// header or failed to.
+pub(crate) fn open_read_only(path: &str) -> io::Result<Connection> {
+    if !input::is_stream(path) && !input::is_compressed_file(path)? {
+        return open_file(Path::new(path));
+    }
+    let spooled = input::spool(path)?;
+    open_file(&spooled).and_then(|conn| {
+        conn.query_row("PRAGMA schema_version", [], |_| Ok(()))
+            .map_err(to_io_error)?;
+        Ok(conn)
+    })
+}
+
+fn open_file(path: &Path) -> io::Result<Connection> {
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(to_io_error)
+}
+
+/// Whether `table` exists and has a column named `column`.
+pub(crate) fn has_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+) -> io::Result<bool> {
+    let mut stmt = conn
+        .prepare("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2")
+        .map_err(to_io_error)?;
+    stmt.exists([table, column]).map_err(to_io_error)
+}
+
+pub(crate) fn to_io_error(e: rusqlite::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn test_failed_open_removes_spooled_file() {
+        let source = std::env::temp_dir()
+            .join(format!("histop_sqlite_test_{}.db.gz", std::process::id()));
+        let marker = format!("not a database {}", std::process::id());
+        let mut encoder = flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(marker.as_bytes()).unwrap();
+        fs::write(&source, encoder.finish().unwrap()).unwrap();
+
+        assert!(open_read_only(source.to_str().unwrap()).is_err());
+        fs::remove_file(&source).unwrap();
+
+        let prefix = format!("histop_stream_{}_", std::process::id());
+        let left_behind = fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry.file_name().to_string_lossy().starts_with(&prefix)
+            })
+            .any(|entry| {
+                fs::read(entry.path())
+                    .is_ok_and(|bytes| bytes == marker.as_bytes())
+            });
+        assert!(!left_behind);
+    }
+}
@@ -1,5 +1,7 @@
 use ahash::AHashMap;
 
+use super::entry::{HistoryEntry, parse_timestamp};
+
 pub fn count_from_file(
     file_path: &str,
     ignore: &[String],
@@ -11,6 +13,23 @@ pub fn count_from_file(
     })
 }
 
+/// Walk a tcsh history file and call `f` for every entry, attaching the
+/// `#+<epoch>` timestamp that precedes it.
+pub fn for_each_entry<F>(
+    file_path: &str,
+    f: F,
+) -> Result<(), std::io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    super::simple_history::for_each_entry(
+        file_path,
+        |line| line.trim().starts_with('#'),
+        |line| line.trim().strip_prefix("#+").and_then(parse_timestamp),
+        f,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,4 +84,35 @@ mod tests {
 
         fs::remove_file(path).ok();
     }
+
+    #[test]
+    fn test_entries_carry_timestamps() {
+        let now_nanos =
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "test_tcsh_entries_{}_{}.history",
+            std::process::id(),
+            now_nanos
+        ));
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "#+1680820391").unwrap();
+        writeln!(file, "ls -la").unwrap();
+        writeln!(file, "#+1680820392").unwrap();
+        writeln!(file, "git status").unwrap();
+
+        let mut timestamps = Vec::new();
+        for_each_entry(path.to_str().unwrap(), |entry| {
+            timestamps.push((entry.command.to_string(), entry.timestamp))
+        })
+        .unwrap();
+        assert_eq!(
+            timestamps,
+            vec![
+                ("ls -la".to_string(), Some(1680820391)),
+                ("git status".to_string(), Some(1680820392)),
+            ]
+        );
+
+        fs::remove_file(path).ok();
+    }
 }
@@ -0,0 +1,279 @@
+//! Xonsh JSON history parser
+//!
+//! Xonsh writes one JSON file per session, usually under
+//! `~/.local/share/xonsh/history_json/`. Every command of the session is
+//! an object in `data.cmds` holding the input (`inp`), the start and end
+//! time (`ts`) and the return code (`rtn`).
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::entry::{HistoryEntry, Syntax};
+use super::input;
+use crate::shared::json::{self, JsonValue};
+use crate::shared::statement_parse::is_python_statement;
+
+/// One command read from a session file
+struct XonshCommand {
+    input: String,
+    start: Option<f64>,
+    end: Option<f64>,
+    rtn: Option<i64>,
+}
+
+/// Commands of one session, with the time the session started
+struct Session {
+    start: f64,
+    commands: Vec<XonshCommand>,
+}
+
+/// Read a xonsh history directory, or a single session file, and call `f`
+/// for every command.
+///
+/// Sessions are replayed in the order they started. Session files that
+/// are not valid xonsh JSON are skipped, since xonsh may be writing to
+/// one of them right now.
+pub fn for_each_entry<F>(
+    path: &str,
+    mut f: F,
+) -> Result<(), io::Error>
+where
+    F: FnMut(&HistoryEntry<'_>),
+{
+    let mut sessions = Vec::new();
+    if Path::new(path).is_dir() {
+        for file in session_files(path)? {
+            if let Some(session) = read_session(&file)? {
+                sessions.push(session);
+            }
+        }
+    } else {
+        let input = input::open(path)?;
+        let session = parse_session_bytes(&input).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a xonsh JSON history file",
+            )
+        })?;
+        sessions.push(session);
+    }
+    sessions.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    for command in sessions.iter().flat_map(|session| &session.commands) {
+        let (input, syntax) = classify(command.input.trim_end());
+        if input.trim().is_empty() {
+            continue;
+        }
+        f(&HistoryEntry {
+            timestamp: command.start.map(|start| start.floor() as i64),
+            duration: command
+                .start
+                .zip(command.end)
+                .filter(|(start, end)| end >= start)
+                .map(|(start, end)| (end - start) as u64),
+            exit_status: command.rtn,
+            syntax,
+            ..HistoryEntry::new(input)
+        });
+    }
+
+    Ok(())
+}
+
+/// The `*.json` files in a history directory, sorted by name.
+pub(super) fn session_files(dir: &str) -> Result<Vec<PathBuf>, io::Error> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "json") && path.is_file()
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Parse one session file, or `None` when it is not xonsh JSON.
+fn read_session(path: &Path) -> Result<Option<Session>, io::Error> {
+    Ok(parse_session_bytes(&fs::read(path)?))
+}
+
+fn parse_session_bytes(bytes: &[u8]) -> Option<Session> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    json::parse(text).and_then(|document| parse_session(&document))
+}
+
+fn parse_session(document: &JsonValue) -> Option<Session> {
+    // Older files and hand-made exports may lack the `data` wrapper
+    let data = document.get("data").unwrap_or(document);
+    let commands: Vec<XonshCommand> = data
+        .get("cmds")?
+        .as_array()?
+        .iter()
+        .filter_map(parse_command)
+        .collect();
+
+    let start = data
+        .get("ts")
+        .and_then(|ts| ts.as_array())
+        .and_then(|ts| ts.first())
+        .and_then(JsonValue::as_f64)
+        .or_else(|| commands.iter().find_map(|command| command.start))
+        .unwrap_or(0.0);
+
+    Some(Session { start, commands })
+}
+
+fn parse_command(value: &JsonValue) -> Option<XonshCommand> {
+    let input = value.get("inp")?.as_str()?.to_string();
+    let ts = value.get("ts").and_then(JsonValue::as_array).unwrap_or(&[]);
+    Some(XonshCommand {
+        input,
+        start: ts.first().and_then(JsonValue::as_f64),
+        end: ts.get(1).and_then(JsonValue::as_f64),
+        rtn: value.get("rtn").and_then(JsonValue::as_i64),
+    })
+}
+
+/// The command text of an input and whether it is Python or a subprocess
+/// command, which xonsh tells apart by trying to parse it as Python.
+fn classify(input: &str) -> (&str, Syntax) {
+    let body = subprocess_body(input);
+    if body.len() < input.len() {
+        return (body, Syntax::Shell);
+    }
+    let first_line = input.lines().next().unwrap_or("");
+    if is_python_statement(first_line) {
+        (input, Syntax::Python)
+    } else {
+        (input, Syntax::Shell)
+    }
+}
+
+/// Unwrap a whole-input subprocess capture such as `$(ls)` or `![make]`,
+/// so it counts as the command it runs.
+fn subprocess_body(input: &str) -> &str {
+    for (open, close) in
+        [("$(", b')'), ("!(", b')'), ("$[", b']'), ("![", b']')]
+    {
+        if let Some(body) = input
+            .strip_prefix(open)
+            .and_then(|rest| rest.strip_suffix(close as char))
+            && is_balanced(body, open.as_bytes()[1], close)
+        {
+            return body;
+        }
+    }
+    input
+}
+
+/// Whether no `close` in `body` lacks a matching `open` before it
+fn is_balanced(
+    body: &str,
+    open: u8,
+    close: u8,
+) -> bool {
+    let mut depth = 0_usize;
+    for b in body.bytes() {
+        if b == open {
+            depth += 1;
+        } else if b == close {
+            let Some(outer) = depth.checked_sub(1) else {
+                return false;
+            };
+            depth = outer;
+        }
+    }
+    depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::simple_history::{
+        filtered_commands, for_each_entry_command_name,
+    };
+
+    fn parse(text: &str) -> Option<Session> {
+        parse_session(&json::parse(text).unwrap())
+    }
+
+    #[test]
+    fn test_parse_session() {
+        let session = parse(
+            r#"{"locked": false, "data": {"cmds": [
+                {"inp": "ls -la\n", "rtn": 0, "ts": [100.25, 102.5]},
+                {"inp": "x = 1\n", "ts": [103.0, 103.0]},
+                {"out": "no input"}
+            ], "ts": [99.5, 200.0]}}"#,
+        )
+        .unwrap();
+        assert_eq!(session.start, 99.5);
+        assert_eq!(session.commands.len(), 2);
+        assert_eq!(session.commands[0].input, "ls -la\n");
+        assert_eq!(session.commands[0].start, Some(100.25));
+        assert_eq!(session.commands[0].end, Some(102.5));
+        assert_eq!(session.commands[0].rtn, Some(0));
+        assert_eq!(session.commands[1].rtn, None);
+    }
+
+    #[test]
+    fn test_parse_session_requires_cmds() {
+        assert!(parse(r#"{"data": {"env": {}}}"#).is_none());
+        assert!(parse(r#"{"cmds": []}"#).is_some());
+    }
+
+    #[test]
+    fn test_classify_python_and_subprocess_entries() {
+        assert_eq!(
+            classify("print('hello world')"),
+            ("print('hello world')", Syntax::Python)
+        );
+        assert_eq!(classify("x = 1"), ("x = 1", Syntax::Python));
+        assert_eq!(
+            classify("for f in $(ls).split():\n    echo @(f)"),
+            ("for f in $(ls).split():\n    echo @(f)", Syntax::Python)
+        );
+        assert_eq!(classify("$(git status)"), ("git status", Syntax::Shell));
+        assert_eq!(classify("![make -j8]"), ("make -j8", Syntax::Shell));
+        assert_eq!(classify("ls -la"), ("ls -la", Syntax::Shell));
+    }
+
+    #[test]
+    fn test_entries_name_python_and_commands() {
+        let path = std::env::temp_dir()
+            .join(format!("histop_xonsh_mixed_{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"data": {"cmds": [
+                {"inp": "print('hello world')\n", "ts": [1.0, 2.0]},
+                {"inp": "x = 1\n", "ts": [3.0, 3.0]},
+                {"inp": "import os\n", "ts": [4.0, 4.0]},
+                {"inp": "$(git status)\n", "ts": [5.0, 5.0]},
+                {"inp": "ls -la\n", "ts": [6.0, 6.0]}
+            ]}}"#,
+        )
+        .unwrap();
+
+        let filtered = filtered_commands(&[], false);
+        let mut names = Vec::new();
+        for_each_entry(path.to_str().unwrap(), |entry| {
+            for_each_entry_command_name(entry, &filtered, false, |name| {
+                names.push(name.to_string())
+            })
+        })
+        .unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(names, vec!["print", "import", "git", "ls"]);
+    }
+
+    #[test]
+    fn test_subprocess_body() {
+        assert_eq!(subprocess_body("$(git status)"), "git status");
+        assert_eq!(subprocess_body("![make -j8]"), "make -j8");
+        assert_eq!(subprocess_body("$(echo $(pwd))"), "echo $(pwd)");
+        assert_eq!(subprocess_body("$(ls) + $(pwd)"), "$(ls) + $(pwd)");
+        assert_eq!(subprocess_body("print($(pwd))"), "print($(pwd))");
+    }
+}
@@ -2,16 +2,42 @@
 
 use std::{env, fs, io::IsTerminal, path::Path, path::PathBuf, process};
 
+use histop::app::Report;
 use histop::config::FileConfig;
+use histop::history::{FORMAT_NAMES, HistoryFormat, detect_history_format};
 use histop::output::OutputFormat;
 use histop::output::color::ColorMode;
+use histop::shared::time::{Bound, Period, UntimedPolicy, Zone};
+use histop::shared::{glob, rotation};
+
+use crate::interface::rc_files;
+
+const REPORT_CONFLICT_ERROR: &str = "Conflicting report modes: use only one of --heatmap, --trend, --durations, --paths, --discover or --detect-only";
+
+const INPUT_CONFLICT_ERROR: &str = "Conflicting input file arguments: use either -f <FILE> or positional FILE, not both";
+
+const ALL_HISTORIES_INPUT_ERROR: &str = "Conflicting input file arguments: --all-histories reads every discovered history, do not pass FILE as well";
+
+const FORMAT_NO_HIST_ERROR: &str = "Conflicting options: -nh reads plain lines without a history format, do not pass --format as well";
+
+/// Most periods a sparkline may span, a year of days
+const MAX_SPARKLINE_PERIODS: usize = 365;
+
+/// Shells whose history locations `--discover` checks
+const KNOWN_SHELLS: [&str; 10] = [
+    "bash", "zsh", "fish", "pwsh", "ksh", "mksh", "tcsh", "ash", "nu", "xonsh",
+];
+
+const BY_SOURCE_REPORT_ERROR: &str =
+    "--by-source only applies to the command ranking";
 
 const NO_HIST_INPUT_ERROR: &str = "When using -nh without FILE, provide input through stdin (pipe or \
      redirection), or pass FILE with -f/positional argument";
 
 #[derive(Default)]
 struct CliOverrides {
-    file: Option<String>,
+    files: Vec<String>,
+    positional_files: Vec<String>,
     count: Option<usize>,
     all: bool,
     more_than: Option<usize>,
@@ -23,12 +49,27 @@ struct CliOverrides {
     no_perc: bool,
     output_format: Option<OutputFormat>,
     color_mode: Option<ColorMode>,
+    /// Resolved once the time zone is known
+    since: Option<String>,
+    until: Option<String>,
+    untimed: Option<UntimedPolicy>,
+    report: Option<Report>,
+    utc: bool,
+    period: Option<Period>,
+    sparkline: Option<usize>,
+    path_prefix: Option<String>,
+    by_source: bool,
+    all_histories: bool,
+    rotated: bool,
+    format: Option<HistoryFormat>,
     config_path: Option<String>,
 }
 
 /// Application configuration parsed from CLI arguments
 pub struct Config {
-    pub file: String,
+    pub files: Vec<String>,
+    /// Input files read in a given format instead of a detected one
+    pub formats: Vec<(String, HistoryFormat)>,
     pub count: usize,
     pub all: bool,
     pub more_than: usize,
@@ -40,12 +81,22 @@ pub struct Config {
     pub no_perc: bool,
     pub output_format: OutputFormat,
     pub color_mode: ColorMode,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub untimed: UntimedPolicy,
+    pub report: Report,
+    pub zone: Zone,
+    pub period: Period,
+    pub sparkline: Option<usize>,
+    pub path_prefix: Option<String>,
+    pub by_source: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            file: String::new(),
+            files: Vec::new(),
+            formats: Vec::new(),
             count: 25,
             all: false,
             more_than: 0,
@@ -57,6 +108,15 @@ impl Default for Config {
             no_perc: false,
             output_format: OutputFormat::Text,
             color_mode: ColorMode::Auto,
+            since: None,
+            until: None,
+            untimed: UntimedPolicy::Error,
+            report: Report::Ranking,
+            zone: Zone::Local,
+            period: Period::Week,
+            sparkline: None,
+            path_prefix: None,
+            by_source: false,
         }
     }
 }
@@ -80,12 +140,12 @@ impl Config {
                 }
                 "-f" => {
                     let value = require_value_argument(&args, &mut i, "-f")?;
-                    if cli_overrides.file.is_some() {
-                        return Err(
-                            "Conflicting input file arguments: use either -f <FILE> or positional FILE, not both".to_string(),
-                        );
+                    if !cli_overrides.positional_files.is_empty() {
+                        return Err(INPUT_CONFLICT_ERROR.to_string());
+                    }
+                    for path in expand_input(&value)? {
+                        push_unique(&mut cli_overrides.files, path);
                     }
-                    cli_overrides.file = Some(value);
                 }
                 "-c" => {
                     let value = require_value_argument(&args, &mut i, "-c")?;
@@ -143,21 +203,115 @@ impl Config {
                         })?,
                     );
                 }
+                "--since" => {
+                    let value =
+                        require_value_argument(&args, &mut i, "--since")?;
+                    cli_overrides.since = Some(value);
+                }
+                "--until" => {
+                    let value =
+                        require_value_argument(&args, &mut i, "--until")?;
+                    cli_overrides.until = Some(value);
+                }
+                "--untimed" => {
+                    let value =
+                        require_value_argument(&args, &mut i, "--untimed")?;
+                    cli_overrides.untimed = Some(
+                        UntimedPolicy::parse(&value).ok_or_else(|| {
+                            format!("Invalid untimed policy: {}. Use include, exclude, or error", value)
+                        })?,
+                    );
+                }
+                arg if arg == "--heatmap" || arg.starts_with("--heatmap=") => {
+                    let command = report_command(&args, &mut i)?;
+                    set_report(
+                        &mut cli_overrides,
+                        Report::Heatmap { command },
+                    )?;
+                }
+                arg if arg == "--trend" || arg.starts_with("--trend=") => {
+                    let command = report_command(&args, &mut i)?;
+                    set_report(&mut cli_overrides, Report::Trend { command })?;
+                }
+                "--durations" => {
+                    set_report(&mut cli_overrides, Report::Durations)?;
+                }
+                "--paths" => {
+                    set_report(&mut cli_overrides, Report::Paths)?;
+                }
+                "--discover" => {
+                    set_report(&mut cli_overrides, Report::Discover)?;
+                }
+                "--detect-only" => {
+                    set_report(&mut cli_overrides, Report::Detection)?;
+                }
+                "--all-histories" => {
+                    cli_overrides.all_histories = true;
+                }
+                "--rotated" => {
+                    cli_overrides.rotated = true;
+                }
+                "--path-prefix" => {
+                    let value =
+                        require_value_argument(&args, &mut i, "--path-prefix")?;
+                    cli_overrides.path_prefix = Some(value);
+                }
+                "--period" => {
+                    let value =
+                        require_value_argument(&args, &mut i, "--period")?;
+                    cli_overrides.period =
+                        Some(Period::parse(&value).ok_or_else(|| {
+                            format!(
+                                "Invalid period: {}. Use day, week, or month",
+                                value
+                            )
+                        })?);
+                }
+                "--utc" => {
+                    cli_overrides.utc = true;
+                }
+                "--sparkline" => {
+                    let value =
+                        require_value_argument(&args, &mut i, "--sparkline")?;
+                    let periods = parse_usize_argument(&value, "--sparkline")?;
+                    if periods > MAX_SPARKLINE_PERIODS {
+                        return Err(format!(
+                            "Invalid --sparkline argument, must be at most {}",
+                            MAX_SPARKLINE_PERIODS
+                        ));
+                    }
+                    cli_overrides.sparkline = Some(periods);
+                }
+                "--by-source" => {
+                    cli_overrides.by_source = true;
+                }
+                "--format" => {
+                    let value =
+                        require_value_argument(&args, &mut i, "--format")?;
+                    cli_overrides.format = Some(
+                        HistoryFormat::parse(&value).ok_or_else(|| {
+                            format!(
+                                "Invalid history format: {}. Use one of: {}",
+                                value, FORMAT_NAMES
+                            )
+                        })?,
+                    );
+                }
                 "--config" => {
                     let value =
                         require_value_argument(&args, &mut i, "--config")?;
                     cli_overrides.config_path = Some(value);
                 }
                 _ => {
-                    if args[i].starts_with('-') {
+                    if args[i].starts_with('-') && args[i] != "-" {
                         return Err(format!("Invalid option: {}", args[i]));
                     }
-                    if cli_overrides.file.is_some() {
-                        return Err(
-                            "Conflicting input file arguments: use either -f <FILE> or positional FILE, not both".to_string(),
-                        );
+                    if !cli_overrides.files.is_empty() {
+                        return Err(INPUT_CONFLICT_ERROR.to_string());
+                    }
+                    for path in expand_input(&args[i])? {
+                        push_unique(&mut cli_overrides.positional_files, path);
                     }
-                    cli_overrides.file = Some(args[i].clone());
                 }
             }
             i += 1;
@@ -174,15 +328,65 @@ impl Config {
         }
         config.apply_cli_overrides(&cli_overrides);
 
-        if config.file.is_empty() {
+        // Relative bounds and dates are read in the zone of the reports
+        if let Some(ref since) = cli_overrides.since {
+            config.since =
+                Some(config.zone.parse_time_bound(since, Bound::Since)?);
+        }
+        if let Some(ref until) = cli_overrides.until {
+            config.until =
+                Some(config.zone.parse_time_bound(until, Bound::Until)?);
+        }
+
+        if let (Some(since), Some(until)) = (config.since, config.until)
+            && since >= until
+        {
+            return Err("--since must be earlier than --until".to_string());
+        }
+
+        if config.by_source && config.report != Report::Ranking {
+            return Err(BY_SOURCE_REPORT_ERROR.to_string());
+        }
+
+        if config.no_hist && cli_overrides.format.is_some() {
+            return Err(FORMAT_NO_HIST_ERROR.to_string());
+        }
+
+        if cli_overrides.all_histories {
+            if !config.files.is_empty() {
+                return Err(ALL_HISTORIES_INPUT_ERROR.to_string());
+            }
+            config.files = discover_histories()?;
+        } else if config.report == Report::Discover && config.files.is_empty() {
+            config.files = discover_histories()?;
+        }
+
+        if config.files.is_empty() {
             let stdin_is_terminal = std::io::stdin().is_terminal();
             if config.no_hist {
-                config.file = resolve_no_hist_input(stdin_is_terminal)?;
+                config.files = vec![resolve_no_hist_input(stdin_is_terminal)?];
             } else {
-                config.file = get_histfile()?;
+                config.files = vec![get_histfile()?];
+            }
+        }
+
+        if cli_overrides.rotated {
+            let mut files = Vec::new();
+            for file in &config.files {
+                for path in rotation::series(file) {
+                    push_unique(&mut files, path);
+                }
             }
+            config.files = files;
         }
 
+        config.formats = match cli_overrides.format {
+            Some(format) => {
+                config.files.iter().map(|file| (file.clone(), format)).collect()
+            }
+            None => resolve_file_formats(&config.files, &config.formats),
+        };
+
         Ok(config)
     }
 
@@ -206,14 +410,19 @@ impl Config {
         if let Some(more_than) = file_config.more_than {
             self.more_than = more_than;
         }
+        if !file_config.formats.is_empty() {
+            self.formats = file_config.formats.clone();
+        }
     }
 
     fn apply_cli_overrides(
         &mut self,
         overrides: &CliOverrides,
     ) {
-        if let Some(ref file) = overrides.file {
-            self.file = file.clone();
+        if !overrides.files.is_empty() {
+            self.files = overrides.files.clone();
+        } else if !overrides.positional_files.is_empty() {
+            self.files = overrides.positional_files.clone();
         }
         if let Some(count) = overrides.count {
             self.count = count;
@@ -248,6 +457,27 @@ impl Config {
         if let Some(color_mode) = overrides.color_mode {
             self.color_mode = color_mode;
         }
+        if let Some(untimed) = overrides.untimed {
+            self.untimed = untimed;
+        }
+        if let Some(ref report) = overrides.report {
+            self.report = report.clone();
+        }
+        if overrides.utc {
+            self.zone = Zone::Utc;
+        }
+        if let Some(period) = overrides.period {
+            self.period = period;
+        }
+        if overrides.sparkline.is_some() {
+            self.sparkline = overrides.sparkline;
+        }
+        if let Some(ref path_prefix) = overrides.path_prefix {
+            self.path_prefix = Some(path_prefix.clone());
+        }
+        if overrides.by_source {
+            self.by_source = true;
+        }
     }
 }
 
@@ -292,6 +522,102 @@ fn parse_non_negative_usize_argument(
     }
 }
 
+fn set_report(
+    overrides: &mut CliOverrides,
+    report: Report,
+) -> Result<(), String> {
+    if overrides.report.is_some() {
+        return Err(REPORT_CONFLICT_ERROR.to_string());
+    }
+    overrides.report = Some(report);
+    Ok(())
+}
+
+/// The command a report flag is limited to, given as `--flag=CMD` or as
+/// the next argument.
+///
+/// The next argument is taken only when it reads as a command name rather
+/// than a flag or an input (see [`is_command_name`]), so `--heatmap
+/// ~/.zsh_history` reads that file whatever the current directory holds.
+fn report_command(
+    args: &[String],
+    i: &mut usize,
+) -> Result<Option<String>, String> {
+    if let Some((flag, command)) = args[*i].split_once('=') {
+        if command.is_empty() {
+            return Err(format!("Missing value for {}=CMD", flag));
+        }
+        return Ok(Some(command.to_string()));
+    }
+    match args.get(*i + 1) {
+        Some(next) if is_command_name(next) => {
+            *i += 1;
+            Ok(Some(next.clone()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Whether an argument reads as a command name: not a flag, stdin, a
+/// glob pattern, or a path with a directory or starting with `.` or `~`.
+fn is_command_name(arg: &str) -> bool {
+    !arg.is_empty()
+        && !arg.starts_with(['-', '.', '~'])
+        && !arg.contains(['/', std::path::MAIN_SEPARATOR])
+        && !glob::is_pattern(arg)
+}
+
+/// Expand an input file argument that is a glob pattern.
+///
+/// Arguments naming an existing path are taken literally, so a file
+/// whose name contains `*` or `[` can still be read.
+fn expand_input(arg: &str) -> Result<Vec<String>, String> {
+    if !glob::is_pattern(arg) || Path::new(arg).exists() {
+        return Ok(vec![arg.to_string()]);
+    }
+    let paths = glob::expand(arg);
+    if paths.is_empty() {
+        return Err(format!("No history files match {}", arg));
+    }
+    Ok(paths)
+}
+
+/// Formats the config file sets for the input files.
+///
+/// Config paths may start with `~/` and match an input file naming the
+/// same file through another path.
+fn resolve_file_formats(
+    files: &[String],
+    configured: &[(String, HistoryFormat)],
+) -> Vec<(String, HistoryFormat)> {
+    let home = env::var("HOME").ok();
+    let configured: Vec<(PathBuf, HistoryFormat)> = configured
+        .iter()
+        .map(|(path, format)| {
+            let path = match (path.strip_prefix("~/"), home.as_deref()) {
+                (Some(rest), Some(home)) => Path::new(home).join(rest),
+                _ => PathBuf::from(path),
+            };
+            (path, *format)
+        })
+        .collect();
+
+    files
+        .iter()
+        .filter_map(|file| {
+            let canonical = fs::canonicalize(file).ok();
+            configured
+                .iter()
+                .find(|(path, _)| {
+                    path == Path::new(file)
+                        || (canonical.is_some()
+                            && fs::canonicalize(path).ok() == canonical)
+                })
+                .map(|(_, format)| (file.clone(), *format))
+        })
+        .collect()
+}
+
 fn require_value_argument(
     args: &[String],
     i: &mut usize,
@@ -335,19 +661,30 @@ fn get_histfile() -> Result<String, String> {
         push_unique(&mut shell_hints, shell_name.to_string());
     }
 
+    // Settings in startup files come before the usual locations, and
+    // those of the shells hinted at before all others
+    let env_var = |var: &str| env::var(var).ok();
     let mut candidate_paths: Vec<String> = Vec::new();
     for shell in &shell_hints {
+        for candidate in rc_files::history_candidates(&home, shell, env_var) {
+            push_unique(&mut candidate_paths, candidate);
+        }
         for candidate in shell_history_candidates(&home, shell) {
             push_unique(&mut candidate_paths, candidate);
         }
     }
+    for shell in KNOWN_SHELLS {
+        for candidate in rc_files::history_candidates(&home, shell, env_var) {
+            push_unique(&mut candidate_paths, candidate);
+        }
+    }
     for candidate in default_history_candidates(&home) {
         push_unique(&mut candidate_paths, candidate);
     }
 
     for candidate in candidate_paths {
         checked_paths.push(candidate.clone());
-        if is_regular_file(&candidate) {
+        if is_history_source(&candidate) {
             return Ok(candidate);
         }
     }
@@ -358,10 +695,127 @@ fn get_histfile() -> Result<String, String> {
     ))
 }
 
+/// Every history file and directory of the current user, for
+/// `--discover` and `--all-histories`
+fn discover_histories() -> Result<Vec<String>, String> {
+    let home = env::var("HOME").unwrap_or_default();
+    if home.is_empty() {
+        return Err("Could not discover history files: HOME environment variable is not set".to_string());
+    }
+
+    let mut candidates: Vec<String> = Vec::new();
+    if let Ok(histfile) = env::var("HISTFILE")
+        && !histfile.is_empty()
+    {
+        candidates.push(histfile);
+    }
+    let env_var = |var: &str| env::var(var).ok();
+    for candidate in environment_history_candidates(&home, env_var) {
+        push_unique(&mut candidates, candidate);
+    }
+    // Settings in startup files, such as a HISTFILE under ~/.local/state
+    for shell in KNOWN_SHELLS {
+        for candidate in rc_files::history_candidates(&home, shell, env_var) {
+            push_unique(&mut candidates, candidate);
+        }
+    }
+    for shell in KNOWN_SHELLS {
+        for candidate in shell_history_candidates(&home, shell) {
+            push_unique(&mut candidates, candidate);
+        }
+    }
+    for candidate in default_history_candidates(&home) {
+        push_unique(&mut candidates, candidate);
+    }
+
+    // Several candidates may be links to, or spellings of, the same file
+    let mut seen = Vec::new();
+    let mut found = Vec::new();
+    for candidate in &candidates {
+        if !is_history_source(candidate) {
+            continue;
+        }
+        // One unreadable history should not hide the others
+        if let Err(err) = detect_history_format(candidate) {
+            eprintln!("Warning: skipping {}: {}", candidate, err);
+            continue;
+        }
+        let canonical = fs::canonicalize(candidate)
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| candidate.clone());
+        if !seen.contains(&canonical) {
+            seen.push(canonical);
+            found.push(candidate.clone());
+        }
+    }
+
+    if found.is_empty() {
+        return Err(format!(
+            "No history files found. Checked: {}",
+            candidates.join(", ")
+        ));
+    }
+    Ok(found)
+}
+
+/// History locations that shells take from their environment: zsh's
+/// `ZDOTDIR`, fish's `fish_history` session under `XDG_DATA_HOME`, and
+/// PowerShell's PSReadLine directory
+fn environment_history_candidates(
+    home: &str,
+    var: impl Fn(&str) -> Option<String>,
+) -> Vec<String> {
+    let mut candidates = Vec::new();
+    if let Some(zdotdir) = var("ZDOTDIR").filter(|dir| !dir.is_empty()) {
+        candidates.push(format!("{}/.zsh_history", zdotdir));
+    }
+
+    let mut data_homes = Vec::new();
+    if let Some(data_home) = var("XDG_DATA_HOME").filter(|dir| !dir.is_empty())
+    {
+        data_homes.push(data_home);
+    }
+    data_homes.push(format!("{}/.local/share", home));
+
+    // fish names the file after the session; an empty session disables
+    // history and "default" is the usual "fish"
+    let fish_session = match var("fish_history") {
+        Some(session) if session.is_empty() => None,
+        Some(session) if session != "default" => Some(session),
+        _ => Some("fish".to_string()),
+    };
+    for data_home in &data_homes {
+        if let Some(ref session) = fish_session {
+            candidates.push(format!("{}/fish/{}_history", data_home, session));
+        }
+        candidates.push(format!(
+            "{}/powershell/PSReadLine/ConsoleHost_history.txt",
+            data_home
+        ));
+    }
+
+    if let Some(appdata) = var("APPDATA").filter(|dir| !dir.is_empty()) {
+        candidates.push(format!(
+            "{}/Microsoft/Windows/PowerShell/PSReadLine/ConsoleHost_history.txt",
+            appdata
+        ));
+    }
+    candidates
+}
+
 fn is_regular_file(path: &str) -> bool {
     fs::metadata(path).map(|meta| meta.is_file()).unwrap_or(false)
 }
 
+/// A history file, or a directory of session files such as xonsh keeps
+fn is_history_source(path: &str) -> bool {
+    match fs::metadata(path) {
+        Ok(meta) if meta.is_dir() => detect_history_format(path).is_ok(),
+        Ok(meta) => meta.is_file(),
+        Err(_) => false,
+    }
+}
+
 fn push_unique(
     values: &mut Vec<String>,
     value: String,
@@ -387,6 +841,13 @@ fn shell_history_candidates(
             "{}/.local/share/powershell/PSReadLine/ConsoleHost_history.txt",
             home
         )],
+        "ksh" | "ksh93" => vec![format!("{}/.sh_history", home)],
+        "mksh" => vec![
+            format!("{}/.mksh_history", home),
+            format!("{}/.sh_history", home),
+        ],
+        "nu" => nushell_history_candidates(home),
+        "xonsh" => xonsh_history_candidates(home),
         "tcsh" | "csh" => vec![
             format!("{}/.history", home),
             format!("{}/.csh_history", home),
@@ -396,8 +857,61 @@ fn shell_history_candidates(
     }
 }
 
+/// Nushell keeps its history in its config directory, preferring the
+/// SQLite database when both formats exist
+fn nushell_history_candidates(home: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    if let Ok(config_home) = env::var("XDG_CONFIG_HOME")
+        && !config_home.is_empty()
+    {
+        candidates.push(format!("{}/nushell/history.sqlite3", config_home));
+        candidates.push(format!("{}/nushell/history.txt", config_home));
+    }
+    for dir in [".config/nushell", "Library/Application Support/nushell"] {
+        candidates.push(format!("{}/{}/history.sqlite3", home, dir));
+        candidates.push(format!("{}/{}/history.txt", home, dir));
+    }
+    candidates
+}
+
+/// Xonsh writes one JSON file per session into `history_json` under its
+/// data directory
+fn xonsh_history_candidates(home: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    for (var, suffix) in [
+        ("XONSH_DATA_DIR", "history_json"),
+        ("XDG_DATA_HOME", "xonsh/history_json"),
+    ] {
+        if let Ok(dir) = env::var(var)
+            && !dir.is_empty()
+        {
+            candidates.push(format!("{}/{}", dir, suffix));
+        }
+    }
+    candidates.push(format!("{}/.local/share/xonsh/history_json", home));
+    candidates
+}
+
+/// Shell-independent history databases written by atuin and zsh-histdb
+fn database_history_candidates(home: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    if let Ok(data_home) = env::var("XDG_DATA_HOME")
+        && !data_home.is_empty()
+    {
+        candidates.push(format!("{}/atuin/history.db", data_home));
+    }
+    candidates.push(format!("{}/.local/share/atuin/history.db", home));
+    if let Ok(histdb) = env::var("HISTDB_FILE")
+        && !histdb.is_empty()
+    {
+        candidates.push(histdb);
+    }
+    candidates.push(format!("{}/.histdb/zsh-history.db", home));
+    candidates
+}
+
 fn default_history_candidates(home: &str) -> Vec<String> {
-    vec![
+    let mut candidates = vec![
         format!("{}/.bash_history", home),
         format!("{}/.zsh_history", home),
         format!("{}/.config/zsh/.zsh_history", home),
@@ -408,7 +922,13 @@ fn default_history_candidates(home: &str) -> Vec<String> {
             home
         ),
         format!("{}/.history", home),
-    ]
+        format!("{}/.sh_history", home),
+        format!("{}/.mksh_history", home),
+    ];
+    candidates.extend(nushell_history_candidates(home));
+    candidates.extend(xonsh_history_candidates(home));
+    candidates.extend(database_history_candidates(home));
+    candidates
 }
 
 /// Get the parent shell name
@@ -437,19 +957,30 @@ fn get_parent_shell() -> Result<String, String> {
     let cmdline_contents = fs::read(&cmdline_file).map_err(|e| {
         format!("Failed to read {}: {}", cmdline_file.display(), e)
     })?;
-    let first_arg = cmdline_contents
-        .split(|b| *b == b'\0')
-        .next()
-        .ok_or_else(|| "Failed to parse parent cmdline".to_string())?;
-    let first_arg = std::str::from_utf8(first_arg)
+    let cmdline = std::str::from_utf8(&cmdline_contents)
         .map_err(|_| "Failed to decode parent cmdline".to_string())?;
 
-    let parent_cmdline = Path::new(first_arg)
-        .file_name()
-        .and_then(|f| f.to_str())
-        .ok_or_else(|| "Failed to parse parent cmdline".to_string())?;
+    shell_from_cmdline(cmdline)
+        .ok_or_else(|| "Failed to parse parent cmdline".to_string())
+}
 
-    Ok(parent_cmdline.to_string())
+/// Shell name from a NUL separated command line.
+///
+/// Shells written in Python, such as xonsh, run as `python3 /usr/bin/xonsh`,
+/// so for an interpreter the script name is used instead.
+#[cfg(any(target_os = "linux", test))]
+fn shell_from_cmdline(cmdline: &str) -> Option<String> {
+    let file_name = |arg: &str| {
+        Path::new(arg).file_name().and_then(|f| f.to_str()).map(String::from)
+    };
+    let mut args = cmdline.split('\0').filter(|arg| !arg.is_empty());
+    let program = file_name(args.next()?)?;
+    if program.starts_with("python")
+        && let Some(script) = args.find(|arg| !arg.starts_with('-'))
+    {
+        return file_name(script);
+    }
+    Some(program)
 }
 
 /// Fallback for non-Linux platforms: use $SHELL environment variable
@@ -467,6 +998,7 @@ fn get_parent_shell() -> Result<String, String> {
 }
 
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
 
@@ -488,6 +1020,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_shell_history_candidates_nushell() {
+        let candidates = shell_history_candidates("/tmp/home", "nu");
+        assert!(
+            candidates
+                .contains(&"/tmp/home/.config/nushell/history.sqlite3".into())
+        );
+        assert!(
+            candidates
+                .contains(&"/tmp/home/.config/nushell/history.txt".into())
+        );
+        let sqlite = candidates
+            .iter()
+            .position(|c| c.ends_with(".config/nushell/history.sqlite3"));
+        let text = candidates
+            .iter()
+            .position(|c| c.ends_with(".config/nushell/history.txt"));
+        assert!(sqlite < text);
+    }
+
+    #[test]
+    fn test_shell_history_candidates_ksh() {
+        assert_eq!(
+            shell_history_candidates("/tmp/home", "ksh"),
+            vec!["/tmp/home/.sh_history".to_string()]
+        );
+        let candidates = shell_history_candidates("/tmp/home", "mksh");
+        assert_eq!(candidates[0], "/tmp/home/.mksh_history");
+    }
+
     #[test]
     fn test_shell_history_candidates_tcsh() {
         let candidates = shell_history_candidates("/tmp/home", "tcsh");
@@ -507,6 +1069,94 @@ mod tests {
             c.ends_with("powershell/PSReadLine/ConsoleHost_history.txt")
         }));
         assert!(candidates.iter().any(|c| c.ends_with("/.history")));
+        assert!(candidates.iter().any(|c| c.ends_with("nushell/history.txt")));
+        assert!(candidates.iter().any(|c| c.ends_with("xonsh/history_json")));
+        assert!(candidates.iter().any(|c| c.ends_with("atuin/history.db")));
+        assert!(
+            candidates.iter().any(|c| c.ends_with(".histdb/zsh-history.db"))
+        );
+    }
+
+    #[test]
+    fn test_environment_history_candidates() {
+        let vars = |var: &str| match var {
+            "ZDOTDIR" => Some("/tmp/home/.config/zsh".to_string()),
+            "XDG_DATA_HOME" => Some("/tmp/data".to_string()),
+            "fish_history" => Some("work".to_string()),
+            _ => None,
+        };
+        let candidates = environment_history_candidates("/tmp/home", vars);
+        assert_eq!(
+            candidates,
+            vec![
+                "/tmp/home/.config/zsh/.zsh_history",
+                "/tmp/data/fish/work_history",
+                "/tmp/data/powershell/PSReadLine/ConsoleHost_history.txt",
+                "/tmp/home/.local/share/fish/work_history",
+                "/tmp/home/.local/share/powershell/PSReadLine/ConsoleHost_history.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_environment_history_candidates_fish_session() {
+        let disabled = |var: &str| (var == "fish_history").then(String::new);
+        let candidates = environment_history_candidates("/tmp/home", disabled);
+        assert!(!candidates.iter().any(|c| c.contains("/fish/")));
+
+        let default =
+            |var: &str| (var == "fish_history").then(|| "default".to_string());
+        let candidates = environment_history_candidates("/tmp/home", default);
+        assert_eq!(candidates[0], "/tmp/home/.local/share/fish/fish_history");
+    }
+
+    #[test]
+    fn test_shell_history_candidates_xonsh() {
+        let candidates = shell_history_candidates("/tmp/home", "xonsh");
+        assert_eq!(
+            candidates.last(),
+            Some(&"/tmp/home/.local/share/xonsh/history_json".to_string())
+        );
+    }
+
+    #[test]
+    fn test_shell_from_cmdline() {
+        assert_eq!(shell_from_cmdline("/bin/zsh\0-l\0"), Some("zsh".into()));
+        assert_eq!(
+            shell_from_cmdline("/usr/bin/python3\0-u\0/usr/bin/xonsh\0"),
+            Some("xonsh".into())
+        );
+        assert_eq!(shell_from_cmdline("python3\0"), Some("python3".into()));
+        assert_eq!(shell_from_cmdline(""), None);
+    }
+
+    #[test]
+    fn test_report_command() {
+        let parse = |args: &[&str]| {
+            let args: Vec<String> =
+                args.iter().map(|arg| arg.to_string()).collect();
+            let mut i = 0;
+            report_command(&args, &mut i).map(|command| (command, i))
+        };
+        assert_eq!(parse(&["--heatmap"]), Ok((None, 0)));
+        assert_eq!(parse(&["--heatmap=git"]), Ok((Some("git".into()), 0)));
+        assert_eq!(parse(&["--heatmap", "git"]), Ok((Some("git".into()), 1)));
+        assert_eq!(parse(&["--trend=a=b"]), Ok((Some("a=b".into()), 0)));
+        assert!(parse(&["--trend="]).is_err());
+        for next in ["-c", "-", "./git", "~/.zsh_history", ".bash_history"] {
+            assert_eq!(parse(&["--trend", next]), Ok((None, 0)));
+        }
+        assert_eq!(parse(&["--trend", "/tmp/history"]), Ok((None, 0)));
+        assert_eq!(parse(&["--trend", "history*"]), Ok((None, 0)));
+    }
+
+    #[test]
+    fn test_set_report_rejects_second_report() {
+        let mut overrides = CliOverrides::default();
+        set_report(&mut overrides, Report::Heatmap { command: None }).unwrap();
+        let err = set_report(&mut overrides, Report::Heatmap { command: None })
+            .unwrap_err();
+        assert_eq!(err, REPORT_CONFLICT_ERROR);
     }
 
     #[test]
@@ -550,9 +1200,9 @@ fn print_help_message(
     bar_size: usize,
 ) {
     println!(
-        "Usage: histop [options] [FILE]\n\
+        "Usage: histop [options] [FILE...]\n\
         \u{A0}-h, --help       Print this help message\n\
-        \u{A0}-f <FILE>        Path to the history file (or pass FILE positionally)\n\
+        \u{A0}-f <FILE>        Path to a history file; repeat or use a glob to merge several\n\
         \u{A0}-c <COUNT>       Number of commands to print (default: {})\n\
         \u{A0}-a               Print all commands (overrides -c)\n\
         \u{A0}-m <MORE_THAN>   Only consider commands used more than <MORE_THAN> times\n\
@@ -562,8 +1212,28 @@ fn print_help_message(
         \u{A0}-nh              Disable history mode (requires FILE or piped/redirected stdin)\n\
         \u{A0}-np              Do not print the percentage in the bar\n\
         \u{A0}-nc              Do not print the inverse cumulative percentage in the bar\n\
+        \u{A0}--format <FMT>   Read input as FMT instead of detecting it (bash, zsh, fish, raw, ...)\n\
         \u{A0}-o <FMT>         Output format: text (default), json, csv\n\
         \u{A0}--color <WHEN>   Color output: auto (default), always, never\n\
+        \u{A0}--since <WHEN>   Only count entries at or after WHEN (YYYY-MM-DD, yesterday, 30d)\n\
+        \u{A0}                 Relative units: m (minutes), h, d, w, mo (months), y\n\
+        \u{A0}--until <WHEN>   Only count entries before the end of WHEN\n\
+        \u{A0}--untimed <POL>  Entries without timestamp: error (default), include, exclude\n\
+        \u{A0}--heatmap [CMD]  Show activity by weekday and hour (for CMD only if given)\n\
+        \u{A0}--trend [CMD]    Show usage per period for CMD or the top -c commands\n\
+        \u{A0}                 CMD may also be given as --heatmap=CMD; a next argument\n\
+        \u{A0}                 that is a flag or looks like a path is not taken as CMD\n\
+        \u{A0}--durations      Rank commands by total time spent (zsh, nushell SQLite, xonsh, atuin, zsh-histdb)\n\
+        \u{A0}--paths          Show the most referenced paths and directories (fish)\n\
+        \u{A0}--path-prefix <DIR> Only count entries that referenced or ran in a path under DIR\n\
+        \u{A0}--period <P>     Trend/sparkline period: day, week (default), month\n\
+        \u{A0}--sparkline <N>  Show each command's activity over the last N periods\n\
+        \u{A0}--by-source      Show each command's count per input file next to the total\n\
+        \u{A0}--discover       List every history file found, with its format, entries and dates\n\
+        \u{A0}--detect-only    Show how each file's format is detected, with scores and reasons\n\
+        \u{A0}--all-histories  Analyse every history file --discover finds as one history\n\
+        \u{A0}--rotated        Also read rotated copies of each file (.1, .2023.gz, ...), oldest first\n\
+        \u{A0}--utc            Use UTC instead of local time for time-based reports and --since/--until\n\
         \u{A0}--config <PATH>  Path to config file\n\
         \u{A0}██               Percentage\n\
         \u{A0}▓▓               Inverse cumulative percentage",
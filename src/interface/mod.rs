@@ -1,3 +1,4 @@
 //! Application interface adapters.
 
 pub mod cli;
+mod rc_files;
@@ -0,0 +1,585 @@
+//! Static scan of shell startup files for history file settings.
+//!
+//! Programs and scripts rarely inherit `HISTFILE`, so the assignments in
+//! `.bashrc`, `.zshrc`, `.cshrc` and fish's config are read as text to
+//! find where the shell really keeps its history. Nothing is executed:
+//! values are unquoted and `~`, `$VAR` and `${VAR:-default}` expanded, and
+//! assignments needing anything more, such as command substitution, are
+//! ignored.
+
+use std::fs;
+
+/// Variable lookup used while expanding assignment values
+type Lookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// History files named in the startup files of `shell`, most
+/// authoritative first.
+///
+/// `var` reads the environment; `HOME` and the XDG base directories fall
+/// back to `home` and the XDG defaults when it has no value for them.
+pub(crate) fn history_candidates(
+    home: &str,
+    shell: &str,
+    var: impl Fn(&str) -> Option<String>,
+) -> Vec<String> {
+    let env = |name: &str| {
+        var(name)
+            .filter(|value| !value.is_empty())
+            .or_else(|| default_var(home, name))
+    };
+
+    let mut candidates = Vec::new();
+    match shell {
+        "bash" => {
+            for rc in [".bashrc", ".bash_profile", ".bash_login", ".profile"] {
+                push_sh_histfile(
+                    &mut candidates,
+                    &format!("{}/{}", home, rc),
+                    &env,
+                );
+            }
+        }
+        "zsh" => {
+            let zdotdir = env("ZDOTDIR")
+                .or_else(|| {
+                    let zshenv = read(&format!("{}/.zshenv", home))?;
+                    sh_variable(&zshenv, "ZDOTDIR", &env)
+                })
+                .unwrap_or_else(|| home.to_string());
+            let zsh_env = |name: &str| match name {
+                "ZDOTDIR" => Some(zdotdir.clone()),
+                _ => env(name),
+            };
+            for rc in [".zshrc", ".zprofile", ".zshenv"] {
+                push_sh_histfile(
+                    &mut candidates,
+                    &format!("{}/{}", zdotdir, rc),
+                    &zsh_env,
+                );
+            }
+            if zdotdir != home {
+                push_sh_histfile(
+                    &mut candidates,
+                    &format!("{}/.zshenv", home),
+                    &zsh_env,
+                );
+            }
+        }
+        "ksh" | "ksh93" | "mksh" => {
+            for rc in [".kshrc", ".mkshrc", ".profile"] {
+                push_sh_histfile(
+                    &mut candidates,
+                    &format!("{}/{}", home, rc),
+                    &env,
+                );
+            }
+        }
+        "tcsh" | "csh" => {
+            for rc in [".tcshrc", ".cshrc"] {
+                if let Some(content) = read(&format!("{}/{}", home, rc))
+                    && let Some(path) = csh_histfile(&content, &env)
+                {
+                    push_unique(&mut candidates, path);
+                }
+            }
+        }
+        "fish" => {
+            if let (Some(session), Some(data_home)) =
+                (fish_session(&env), env("XDG_DATA_HOME"))
+            {
+                candidates
+                    .push(format!("{}/fish/{}_history", data_home, session));
+            }
+        }
+        _ => {}
+    }
+    candidates
+}
+
+/// `XDG_*` directory defaults from the base directory specification
+fn default_var(
+    home: &str,
+    name: &str,
+) -> Option<String> {
+    let dir = match name {
+        "HOME" => return Some(home.to_string()),
+        "XDG_CONFIG_HOME" => ".config",
+        "XDG_DATA_HOME" => ".local/share",
+        "XDG_STATE_HOME" => ".local/state",
+        "XDG_CACHE_HOME" => ".cache",
+        _ => return None,
+    };
+    Some(format!("{}/{}", home, dir))
+}
+
+fn read(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+fn push_unique(
+    values: &mut Vec<String>,
+    value: String,
+) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+fn push_sh_histfile(
+    candidates: &mut Vec<String>,
+    rc: &str,
+    env: Lookup<'_>,
+) {
+    if let Some(content) = read(rc)
+        && let Some(path) = sh_variable(&content, "HISTFILE", env)
+    {
+        push_unique(candidates, path);
+    }
+}
+
+/// The last value assigned to `name` in a POSIX-style startup file.
+///
+/// Earlier assignments in the file are visible to later ones, so
+/// `dir=~/.local/state/zsh` followed by `HISTFILE=$dir/history` resolves.
+fn sh_variable(
+    content: &str,
+    name: &str,
+    env: Lookup<'_>,
+) -> Option<String> {
+    let mut locals: Vec<(String, String)> = Vec::new();
+    let mut value = None;
+
+    for line in content.lines() {
+        let Some((assigned, raw)) = sh_assignment(line) else {
+            continue;
+        };
+        let lookup = |var: &str| {
+            locals
+                .iter()
+                .rev()
+                .find(|(name, _)| name == var)
+                .map(|(_, value)| value.clone())
+                .or_else(|| env(var))
+        };
+        let Some(expanded) = expand_word(raw, &lookup) else {
+            continue;
+        };
+        if assigned == name {
+            value = Some(expanded.clone());
+        }
+        locals.push((assigned.to_string(), expanded));
+    }
+    value.filter(|value| !value.is_empty())
+}
+
+/// Split `[export|typeset|declare ...] NAME=VALUE` into the name and the
+/// raw value word.
+fn sh_assignment(line: &str) -> Option<(&str, &str)> {
+    let mut rest = line.trim_start();
+    let (command, after) = split_token(rest);
+    if matches!(
+        command,
+        "export" | "typeset" | "declare" | "readonly" | "local"
+    ) {
+        rest = after;
+        // Options such as `declare -x`
+        while rest.starts_with('-') {
+            rest = split_token(rest).1;
+        }
+    }
+
+    let name = &rest[..name_len(rest)];
+    let value = rest[name.len()..].strip_prefix('=')?;
+    if name.is_empty() || name.as_bytes()[0].is_ascii_digit() {
+        return None;
+    }
+    Some((name, split_word(value).0))
+}
+
+/// The `histfile` set in a csh startup file: `set histfile = ~/.history`
+fn csh_histfile(
+    content: &str,
+    env: Lookup<'_>,
+) -> Option<String> {
+    let mut value = None;
+    for line in content.lines() {
+        let (command, rest) = split_token(line.trim_start());
+        if command != "set" {
+            continue;
+        }
+        let Some(raw) = rest
+            .strip_prefix("histfile")
+            .and_then(|rest| rest.trim_start().strip_prefix('='))
+        else {
+            continue;
+        };
+        if let Some(expanded) = expand_word(split_word(raw.trim_start()).0, env)
+        {
+            value = Some(expanded);
+        }
+    }
+    value.filter(|value| !value.is_empty())
+}
+
+/// The session fish writes history for, named by its `fish_history`
+/// variable, or `None` when history is disabled
+fn fish_session(env: Lookup<'_>) -> Option<String> {
+    let config = env("XDG_CONFIG_HOME")?;
+    let fish_dir = format!("{}/fish", config);
+
+    // conf.d snippets run before config.fish, and variables set in either
+    // shadow universal ones
+    let mut session = read(&format!("{}/config.fish", fish_dir))
+        .and_then(|content| fish_set_variable(&content, "fish_history"));
+    if session.is_none() {
+        let mut snippets: Vec<String> =
+            fs::read_dir(format!("{}/conf.d", fish_dir))
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter_map(|entry| entry.path().to_str().map(String::from))
+                .filter(|path| path.ends_with(".fish"))
+                .collect();
+        snippets.sort();
+        session =
+            snippets.iter().rev().filter_map(|path| read(path)).find_map(
+                |content| fish_set_variable(&content, "fish_history"),
+            );
+    }
+    if session.is_none() {
+        session =
+            read(&format!("{}/fish_variables", fish_dir)).and_then(|content| {
+                fish_universal_variable(&content, "fish_history")
+            });
+    }
+
+    match session.as_deref() {
+        None | Some("default") => Some("fish".to_string()),
+        Some("") => None,
+        Some(_) => session,
+    }
+}
+
+/// The last value given to `name` with `set` in a fish script
+fn fish_set_variable(
+    content: &str,
+    name: &str,
+) -> Option<String> {
+    let mut value = None;
+    for line in content.lines() {
+        let (command, mut rest) = split_token(line.trim_start());
+        if command != "set" {
+            continue;
+        }
+        let mut erase = false;
+        let variable = loop {
+            let (word, after) = split_token(rest);
+            rest = after;
+            if word.starts_with('-') {
+                erase |= matches!(word, "-e" | "--erase");
+                continue;
+            }
+            break word;
+        };
+        if variable != name || erase {
+            continue;
+        }
+        let no_env = |_: &str| None;
+        if let Some(expanded) = expand_word(split_word(rest).0, &no_env) {
+            value = Some(expanded);
+        }
+    }
+    value
+}
+
+/// A universal variable from fish's `fish_variables` file, stored as
+/// `SETUVAR [--export] NAME:VALUE`
+fn fish_universal_variable(
+    content: &str,
+    name: &str,
+) -> Option<String> {
+    content.lines().find_map(|line| {
+        let rest = line.strip_prefix("SETUVAR ")?;
+        let rest = rest.strip_prefix("--export ").unwrap_or(rest);
+        rest.strip_prefix(name)?.strip_prefix(':').map(String::from)
+    })
+}
+
+/// Split off the first whitespace-separated token.
+fn split_token(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], s[i..].trim_start()),
+        None => (s, ""),
+    }
+}
+
+/// Split off one shell word, which ends at unquoted whitespace or a
+/// command separator.
+fn split_word(s: &str) -> (&str, &str) {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c.is_whitespace() || matches!(c, ';' | '&' | '|') => {
+                return (&s[..i], &s[i..]);
+            }
+            (None, _) => {}
+        }
+    }
+    (s, "")
+}
+
+/// Undo quoting in a shell word and expand `~` and variables, or `None`
+/// when it needs command substitution or an unknown variable.
+fn expand_word(
+    word: &str,
+    lookup: Lookup<'_>,
+) -> Option<String> {
+    let mut out = String::with_capacity(word.len());
+    let mut chars = word.char_indices().peekable();
+    let mut in_single = false;
+    let mut in_double = false;
+
+    if word == "~" || word.starts_with("~/") {
+        out.push_str(&lookup("HOME")?);
+        chars.next();
+    }
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' if !in_double => in_single = !in_single,
+            _ if in_single => out.push(c),
+            '"' => in_double = !in_double,
+            '`' => return None,
+            '\\' => match chars.next() {
+                Some((_, next))
+                    if !in_double || matches!(next, '$' | '"' | '\\' | '`') =>
+                {
+                    out.push(next)
+                }
+                Some((_, next)) => {
+                    out.push('\\');
+                    out.push(next);
+                }
+                None => out.push('\\'),
+            },
+            '$' => {
+                let rest = &word[i + 1..];
+                if rest.starts_with('(') {
+                    return None;
+                }
+                let (value, len) = if let Some(body) = rest.strip_prefix('{') {
+                    let end = closing_brace(body)?;
+                    (expand_parameter(&body[..end], lookup)?, end + 2)
+                } else {
+                    let len = name_len(rest);
+                    if len == 0 {
+                        out.push('$');
+                        continue;
+                    }
+                    (lookup(&rest[..len])?, len)
+                };
+                out.push_str(&value);
+                let resume = i + 1 + len;
+                while chars.next_if(|&(j, _)| j < resume).is_some() {}
+            }
+            _ => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// Index of the `}` closing a `${` whose body starts `s`
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Expand the body of `${NAME}`, `${NAME:-default}` or `${NAME-default}`
+/// (`=` in place of `-` behaves the same here).
+fn expand_parameter(
+    body: &str,
+    lookup: Lookup<'_>,
+) -> Option<String> {
+    let (name, operator) = body.split_at(name_len(body));
+    // With a colon, an empty value also takes the default
+    let default = [(":-", true), (":=", true), ("-", false), ("=", false)]
+        .into_iter()
+        .find_map(|(prefix, colon)| {
+            operator.strip_prefix(prefix).map(|default| (default, colon))
+        });
+    if default.is_none() && !operator.is_empty() {
+        // Other expansions, like `${NAME%suffix}`, are not supported
+        return None;
+    }
+
+    match (lookup(name), default) {
+        (Some(value), Some((default, true))) if value.is_empty() => {
+            expand_word(default, lookup)
+        }
+        (Some(value), _) => Some(value),
+        (None, Some((default, _))) => expand_word(default, lookup),
+        (None, None) => None,
+    }
+}
+
+/// Length of the variable name at the start of `s`
+fn name_len(s: &str) -> usize {
+    s.bytes().take_while(|b| b.is_ascii_alphanumeric() || *b == b'_').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/u".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_word() {
+        assert_eq!(expand_word("~/.hist", &env).unwrap(), "/home/u/.hist");
+        assert_eq!(expand_word("'$HOME/x'", &env).unwrap(), "$HOME/x");
+        assert_eq!(
+            expand_word("\"$HOME\"/a\\ b", &env).unwrap(),
+            "/home/u/a b"
+        );
+        assert_eq!(expand_word("${HOME}/é", &env).unwrap(), "/home/u/é");
+        assert_eq!(expand_word("${EMPTY:-/x}", &env).unwrap(), "/x");
+        assert_eq!(expand_word("${EMPTY-/x}", &env).unwrap(), "");
+        assert_eq!(
+            expand_word("${XDG_STATE_HOME:-$HOME/.local/state}/h", &env)
+                .unwrap(),
+            "/home/u/.local/state/h"
+        );
+        assert_eq!(expand_word("$UNSET/h", &env), None);
+        assert_eq!(expand_word("$(mktemp)", &env), None);
+        assert_eq!(expand_word("`mktemp`", &env), None);
+        assert_eq!(expand_word("${HOME%/u}", &env), None);
+    }
+
+    #[test]
+    fn test_sh_variable() {
+        let content = "\
+# HISTFILE=~/commented
+export HISTFILE=~/.bash_history
+state=\"${XDG_STATE_HOME:-$HOME/.local/state}\"
+[ -d \"$state\" ] || mkdir -p \"$state\"
+declare -x HISTFILE=$state/bash/history # keep it out of $HOME
+HISTFILE=$(mktemp)
+";
+        assert_eq!(
+            sh_variable(content, "HISTFILE", &env).unwrap(),
+            "/home/u/.local/state/bash/history"
+        );
+        assert_eq!(sh_variable("HISTSIZE=100\n", "HISTFILE", &env), None);
+    }
+
+    #[test]
+    fn test_csh_histfile() {
+        let content = "set history = 1000\nset histfile = ~/.local/state/tcsh_history\nset savehist=(1000 merge)\n";
+        assert_eq!(
+            csh_histfile(content, &env).unwrap(),
+            "/home/u/.local/state/tcsh_history"
+        );
+        assert_eq!(
+            csh_histfile("set histfile=$HOME/.h\n", &env).unwrap(),
+            "/home/u/.h"
+        );
+    }
+
+    #[test]
+    fn test_fish_variables() {
+        let config = "set -gx EDITOR vim\nset -U fish_history work\n";
+        assert_eq!(fish_set_variable(config, "fish_history").unwrap(), "work");
+        assert_eq!(
+            fish_set_variable("set -e fish_history\n", "fish_history"),
+            None
+        );
+        assert_eq!(
+            fish_set_variable("set -g fish_history\n", "fish_history").unwrap(),
+            ""
+        );
+
+        let variables = "# This file contains fish universal variable definitions.\nSETUVAR __fish_initialized:3400\nSETUVAR fish_history:home\n";
+        assert_eq!(
+            fish_universal_variable(variables, "fish_history").unwrap(),
+            "home"
+        );
+    }
+
+    #[test]
+    fn test_history_candidates_from_home() {
+        let now_nanos =
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let home = std::env::temp_dir().join(format!(
+            "histop_rc_{}_{}",
+            std::process::id(),
+            now_nanos
+        ));
+        let home_str = home.to_str().unwrap();
+        fs::create_dir_all(home.join(".config/zsh")).unwrap();
+        fs::create_dir_all(home.join(".config/fish")).unwrap();
+        fs::write(
+            home.join(".zshenv"),
+            "export ZDOTDIR=\"$HOME/.config/zsh\"\n",
+        )
+        .unwrap();
+        fs::write(
+            home.join(".config/zsh/.zshrc"),
+            "HISTFILE=\"$ZDOTDIR/history\"\n",
+        )
+        .unwrap();
+        fs::write(
+            home.join(".config/fish/fish_variables"),
+            "SETUVAR fish_history:work\n",
+        )
+        .unwrap();
+        fs::write(
+            home.join(".cshrc"),
+            "set histfile = ~/.local/state/csh_history\n",
+        )
+        .unwrap();
+        let no_env = |_: &str| None;
+
+        assert_eq!(
+            history_candidates(home_str, "zsh", no_env),
+            vec![format!("{}/.config/zsh/history", home_str)]
+        );
+        assert_eq!(
+            history_candidates(home_str, "fish", no_env),
+            vec![format!("{}/.local/share/fish/work_history", home_str)]
+        );
+        assert_eq!(
+            history_candidates(home_str, "tcsh", no_env),
+            vec![format!("{}/.local/state/csh_history", home_str)]
+        );
+        assert!(history_candidates(home_str, "bash", no_env).is_empty());
+
+        fs::remove_dir_all(home).ok();
+    }
+}
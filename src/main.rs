@@ -30,7 +30,8 @@ fn main() {
 
 fn to_run_config(config: interface::cli::Config) -> RunConfig {
     RunConfig {
-        file: config.file,
+        files: config.files,
+        formats: config.formats,
         count: config.count,
         all: config.all,
         more_than: config.more_than,
@@ -42,5 +43,14 @@ fn to_run_config(config: interface::cli::Config) -> RunConfig {
         no_perc: config.no_perc,
         output_format: config.output_format,
         color_mode: config.color_mode,
+        since: config.since,
+        until: config.until,
+        untimed: config.untimed,
+        report: config.report,
+        zone: config.zone,
+        period: config.period,
+        sparkline: config.sparkline,
+        path_prefix: config.path_prefix,
+        by_source: config.by_source,
     }
 }
@@ -15,11 +15,19 @@ pub struct BarConfig {
     pub show_percentage: bool,
     /// Show semi-filled portion (inverse cumulative)
     pub show_cumulative: bool,
+    /// Size the filled portion relative to the largest value instead of
+    /// the total, which suits series that are not ranked
+    pub scale_to_max: bool,
 }
 
 impl Default for BarConfig {
     fn default() -> Self {
-        Self { size: 25, show_percentage: true, show_cumulative: true }
+        Self {
+            size: 25,
+            show_percentage: true,
+            show_cumulative: true,
+            scale_to_max: false,
+        }
     }
 }
 
@@ -45,6 +53,8 @@ pub struct RenderedBar {
     pub bar_str: String,
     pub percentage_str: String,
     pub label: String,
+    /// Optional extra column shown next to the count, such as a sparkline
+    pub detail: Option<String>,
 }
 
 /// Render a bar segment given percentage values
@@ -118,10 +128,9 @@ pub fn render_bars<'a>(
         return Vec::new();
     }
 
-    let total: usize = items.iter().map(|item| item.value).sum();
-    if total == 0 {
-        return Vec::new();
-    }
+    let total = items
+        .iter()
+        .fold(0_usize, |total, item| total.saturating_add(item.value));
 
     let mut results = Vec::with_capacity(items.len());
     let mut inv_cumu_perc = 100.0;
@@ -129,14 +138,26 @@ pub fn render_bars<'a>(
     // Calculate max widths for alignment
     let max_count_width =
         items.iter().map(|i| i.value.to_string().len()).max().unwrap_or(0);
+    let max_value = items.iter().map(|i| i.value).max().unwrap_or(0);
 
     for item in items {
-        let perc = item.value as f32 / total as f32 * 100.0;
+        // All-zero values, such as durations that all round to 0s, still
+        // get a row each
+        let perc = if total == 0 {
+            0.0
+        } else {
+            item.value as f32 / total as f32 * 100.0
+        };
         let percentage_str = format!("{:.2}%", perc);
 
         let bar_str = if config.size > 0 {
+            let bar_perc = if config.scale_to_max && max_value > 0 {
+                item.value as f32 / max_value as f32 * 100.0
+            } else {
+                perc
+            };
             let bar = render_bar_segment(
-                perc,
+                bar_perc,
                 inv_cumu_perc,
                 config.size,
                 config.show_cumulative,
@@ -156,6 +177,7 @@ pub fn render_bars<'a>(
             bar_str,
             percentage_str,
             label: item.label.to_string(),
+            detail: None,
         });
     }
 
@@ -184,6 +206,11 @@ pub fn write_bars<W: Write>(
         let count_display = colorizer.paint(Color::Cyan, &bar.count_str);
         write!(writer, "{}{}", count_display, padding)?;
 
+        if let Some(ref detail) = bar.detail {
+            let detail_display = colorizer.paint(Color::Magenta, detail);
+            write!(writer, "{}{}", detail_display, padding)?;
+        }
+
         if show_bar && !bar.bar_str.is_empty() {
             write!(writer, "{} ", bar.bar_str)?;
         }
@@ -226,6 +253,16 @@ mod tests {
         assert!(result.is_empty());
     }
 
+    #[test]
+    fn test_render_bars_all_zero() {
+        let items = vec![BarItem::new("ls", 0), BarItem::new("cd", 0)];
+        let config = BarConfig::default();
+        let result = render_bars(&items, &config);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].percentage_str, "0.00%");
+        assert_eq!(result[1].label, "cd");
+    }
+
     #[test]
     fn test_render_bars_single() {
         let items = vec![BarItem::new("ls", 10)];
@@ -249,6 +286,36 @@ mod tests {
         assert_eq!(result[0].percentage_str, "50.00%");
     }
 
+    #[test]
+    fn test_render_bars_scale_to_max() {
+        let items = vec![BarItem::new("a", 2), BarItem::new("b", 4)];
+        let config = BarConfig {
+            size: 4,
+            show_percentage: true,
+            show_cumulative: false,
+            scale_to_max: true,
+        };
+        let result = render_bars(&items, &config);
+        assert_eq!(result[0].bar_str, "│░░██│");
+        assert_eq!(result[1].bar_str, "│████│");
+        assert_eq!(result[1].percentage_str, "66.67%");
+    }
+
+    #[test]
+    fn test_write_bars_with_sparkline() {
+        let items = vec![BarItem::new("git", 3)];
+        let mut bars = render_bars(&items, &BarConfig::default());
+        bars[0].detail = Some("▁▄█".to_string());
+        let colorizer = Colorizer::new(ColorMode::Never);
+
+        let mut out = Vec::new();
+        write_bars(&mut out, &bars, false, &colorizer).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "3   ▁▄█   100.00%   git\n"
+        );
+    }
+
     #[test]
     fn test_print_bars_no_crash() {
         let items = vec![BarItem::new("test", 5)];
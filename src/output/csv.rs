@@ -1,6 +1,29 @@
 use std::fmt::Write;
 
-use super::CommandEntry;
+use super::heatmap::{Heatmap, WEEKDAYS};
+use super::{
+    CommandEntry, DetectionEntry, DurationEntry, HistoryFileEntry, PathEntry,
+    SourceEntry, TrendEntry,
+};
+
+/// Quote a CSV field if it contains a separator, quote or newline
+fn escape_csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        let mut escaped = String::with_capacity(field.len() + 2);
+        escaped.push('"');
+        for c in field.chars() {
+            if c == '"' {
+                escaped.push_str("\"\"");
+            } else {
+                escaped.push(c);
+            }
+        }
+        escaped.push('"');
+        escaped
+    } else {
+        field.to_string()
+    }
+}
 
 /// Format output as CSV
 pub fn format_csv(entries: &[CommandEntry]) -> String {
@@ -9,25 +32,7 @@ pub fn format_csv(entries: &[CommandEntry]) -> String {
     result.push_str("command,count,percentage\n");
 
     for entry in entries {
-        // Escape CSV fields
-        let escaped_cmd = if entry.command.contains(',')
-            || entry.command.contains('"')
-            || entry.command.contains('\n')
-        {
-            let mut escaped = String::with_capacity(entry.command.len() + 2);
-            escaped.push('"');
-            for c in entry.command.chars() {
-                if c == '"' {
-                    escaped.push_str("\"\"");
-                } else {
-                    escaped.push(c);
-                }
-            }
-            escaped.push('"');
-            escaped
-        } else {
-            entry.command.clone()
-        };
+        let escaped_cmd = escape_csv_field(&entry.command);
 
         let _ = writeln!(
             result,
@@ -38,3 +43,160 @@ pub fn format_csv(entries: &[CommandEntry]) -> String {
 
     result
 }
+
+/// Format a by-source ranking as CSV, with one count column per label
+pub fn format_sources_csv(
+    entries: &[SourceEntry],
+    labels: &[String],
+) -> String {
+    let mut result =
+        String::with_capacity((entries.len() + 1) * (30 + labels.len() * 8));
+    result.push_str("command,count,percentage");
+    for label in labels {
+        result.push(',');
+        result.push_str(&escape_csv_field(label));
+    }
+    result.push('\n');
+
+    for entry in entries {
+        let _ = write!(
+            result,
+            "{},{},{:.2}",
+            escape_csv_field(&entry.command),
+            entry.count,
+            entry.percentage
+        );
+        for count in &entry.sources {
+            let _ = write!(result, ",{}", count);
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Format trend output as CSV, one row per (period, command)
+pub fn format_trend_csv(entries: &[TrendEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 30 + 24);
+    result.push_str("period,command,count\n");
+
+    for entry in entries {
+        let _ = writeln!(
+            result,
+            "{},{},{}",
+            entry.period,
+            escape_csv_field(&entry.command),
+            entry.count
+        );
+    }
+
+    result
+}
+
+/// Format command durations as CSV
+pub fn format_durations_csv(entries: &[DurationEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 40 + 56);
+    result.push_str("command,count,total_seconds,median_seconds,percentage\n");
+
+    for entry in entries {
+        let _ = writeln!(
+            result,
+            "{},{},{},{},{:.2}",
+            escape_csv_field(&entry.command),
+            entry.count,
+            entry.total_seconds,
+            entry.median_seconds,
+            entry.percentage
+        );
+    }
+
+    result
+}
+
+/// Format referenced paths and directories as CSV, one row per item
+pub fn format_paths_csv(
+    paths: &[PathEntry],
+    directories: &[PathEntry],
+) -> String {
+    let mut result =
+        String::with_capacity((paths.len() + directories.len()) * 40 + 16);
+    result.push_str("kind,path,count\n");
+
+    let rows = paths
+        .iter()
+        .map(|entry| ("path", entry))
+        .chain(directories.iter().map(|entry| ("directory", entry)));
+    for (kind, entry) in rows {
+        let _ = writeln!(
+            result,
+            "{},{},{}",
+            kind,
+            escape_csv_field(&entry.path),
+            entry.count
+        );
+    }
+
+    result
+}
+
+/// Format discovered history files as CSV, leaving dates empty for files
+/// without timestamps
+pub fn format_history_files_csv(entries: &[HistoryFileEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 80 + 32);
+    result.push_str("path,format,entries,first,last\n");
+
+    for entry in entries {
+        let _ = writeln!(
+            result,
+            "{},{},{},{},{}",
+            escape_csv_field(&entry.path),
+            entry.format,
+            entry.entries,
+            entry.first.as_deref().unwrap_or_default(),
+            entry.last.as_deref().unwrap_or_default()
+        );
+    }
+
+    result
+}
+
+/// Format format detection reports as CSV, with the scores as
+/// `format=score` pairs and the reasons joined by `; `
+pub fn format_detections_csv(entries: &[DetectionEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 200 + 48);
+    result.push_str("path,format,lines_inspected,ambiguous,scores,reasons\n");
+
+    for entry in entries {
+        let scores: Vec<String> = entry
+            .scores
+            .iter()
+            .map(|(format, score)| format!("{}={}", format, score))
+            .collect();
+        let _ = writeln!(
+            result,
+            "{},{},{},{},{},{}",
+            escape_csv_field(&entry.path),
+            entry.format,
+            entry.lines_inspected,
+            entry.ambiguous,
+            scores.join(" "),
+            escape_csv_field(&entry.reasons.join("; "))
+        );
+    }
+
+    result
+}
+
+/// Format a weekday by hour heatmap as CSV, one row per cell
+pub fn format_heatmap_csv(heatmap: &Heatmap) -> String {
+    let mut result = String::with_capacity(7 * 24 * 12 + 16);
+    result.push_str("day,hour,count\n");
+
+    for (day, row) in WEEKDAYS.iter().zip(heatmap.cells.iter()) {
+        for (hour, count) in row.iter().enumerate() {
+            let _ = writeln!(result, "{},{},{}", day, hour, count);
+        }
+    }
+
+    result
+}
@@ -0,0 +1,138 @@
+//! Weekday by hour activity heatmap.
+//!
+//! Like the bar module, this works on plain counts and knows nothing
+//! about shell history.
+
+use std::io::{self, Write};
+
+use crate::output::color::{Color, Colorizer};
+
+/// Weekday labels, Monday first
+pub const WEEKDAYS: [&str; 7] =
+    ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Shading glyphs from empty to busiest cell
+const SHADES: [&str; 5] = ["··", "░░", "▒▒", "▓▓", "██"];
+const SHADE_COLORS: [Color; 5] = [
+    Color::BrightBlack,
+    Color::Blue,
+    Color::Cyan,
+    Color::Green,
+    Color::BrightGreen,
+];
+
+/// Activity counts indexed by weekday (Monday = 0) and hour (0..24)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Heatmap {
+    pub cells: [[usize; 24]; 7],
+}
+
+impl Heatmap {
+    /// Record one unit of activity
+    #[inline]
+    pub fn add(
+        &mut self,
+        weekday: usize,
+        hour: usize,
+    ) {
+        self.cells[weekday][hour] += 1;
+    }
+
+    /// Count in the busiest cell
+    pub fn max(&self) -> usize {
+        self.cells.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// Sum of all cells
+    pub fn total(&self) -> usize {
+        self.cells.iter().flatten().sum()
+    }
+}
+
+/// Shade level (0..=4) of a cell relative to the busiest cell
+#[inline]
+fn shade_level(
+    count: usize,
+    max: usize,
+) -> usize {
+    if count == 0 || max == 0 { 0 } else { (count * 4).div_ceil(max).min(4) }
+}
+
+/// Write a heatmap as a 7x24 grid with an hour header and a legend
+pub fn write_heatmap<W: Write>(
+    writer: &mut W,
+    heatmap: &Heatmap,
+    colorizer: &Colorizer,
+) -> io::Result<()> {
+    let max = heatmap.max();
+
+    write!(writer, "    ")?;
+    for hour in 0..24 {
+        write!(writer, " {:02}", hour)?;
+    }
+    writeln!(writer)?;
+
+    for (day, row) in WEEKDAYS.iter().zip(heatmap.cells.iter()) {
+        let label = colorizer.paint(Color::BrightWhite, day);
+        write!(writer, "{} ", label)?;
+        for &count in row {
+            let level = shade_level(count, max);
+            let cell = colorizer.paint(SHADE_COLORS[level], SHADES[level]);
+            write!(writer, " {}", cell)?;
+        }
+        let total: usize = row.iter().sum();
+        let total_str = total.to_string();
+        writeln!(writer, "   {}", colorizer.paint(Color::Cyan, &total_str))?;
+    }
+
+    let max_str = max.to_string();
+    writeln!(
+        writer,
+        "\n{} 0  {} ≤25%  {} ≤50%  {} ≤75%  {} ≤100% of {}",
+        colorizer.paint(SHADE_COLORS[0], SHADES[0]),
+        colorizer.paint(SHADE_COLORS[1], SHADES[1]),
+        colorizer.paint(SHADE_COLORS[2], SHADES[2]),
+        colorizer.paint(SHADE_COLORS[3], SHADES[3]),
+        colorizer.paint(SHADE_COLORS[4], SHADES[4]),
+        colorizer.paint(Color::Yellow, &max_str)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::color::ColorMode;
+
+    #[test]
+    fn test_shade_level() {
+        assert_eq!(shade_level(0, 10), 0);
+        assert_eq!(shade_level(1, 10), 1);
+        assert_eq!(shade_level(3, 10), 2);
+        assert_eq!(shade_level(6, 10), 3);
+        assert_eq!(shade_level(10, 10), 4);
+        assert_eq!(shade_level(5, 0), 0);
+    }
+
+    #[test]
+    fn test_write_heatmap_layout() {
+        let mut heatmap = Heatmap::default();
+        heatmap.add(0, 9);
+        heatmap.add(0, 9);
+        heatmap.add(4, 23);
+
+        let mut out = Vec::new();
+        let colorizer = Colorizer::new(ColorMode::Never);
+        write_heatmap(&mut out, &heatmap, &colorizer).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert!(lines[0].starts_with("     00 01"));
+        assert!(lines[0].ends_with("23"));
+        assert!(lines[1].starts_with("Mon "));
+        assert!(lines[1].contains("██"));
+        assert!(lines[1].ends_with("   2"));
+        assert!(lines[5].starts_with("Fri "));
+        assert!(lines[5].contains("▒▒"));
+        assert!(text.contains("≤100% of 2"));
+    }
+}
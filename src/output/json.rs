@@ -1,6 +1,10 @@
 use std::fmt::Write;
 
-use super::CommandEntry;
+use super::heatmap::{Heatmap, WEEKDAYS};
+use super::{
+    CommandEntry, DetectionEntry, DurationEntry, HistoryFileEntry, PathEntry,
+    SourceEntry, TrendEntry,
+};
 
 /// Escape a string for JSON output per RFC 8259.
 ///
@@ -50,6 +54,241 @@ pub fn format_json(entries: &[CommandEntry]) -> String {
     result
 }
 
+/// Format a by-source ranking as JSON, with a `sources` object mapping
+/// each label to the command's count in that source
+pub fn format_sources_json(
+    entries: &[SourceEntry],
+    labels: &[String],
+) -> String {
+    let mut result =
+        String::with_capacity(entries.len() * (100 + labels.len() * 24) + 4);
+    result.push_str("[\n");
+
+    for (i, entry) in entries.iter().enumerate() {
+        let _ = write!(
+            result,
+            "  {{\n    \"command\": \"{}\",\n    \"count\": {},\n    \"percentage\": {:.2},\n    \"sources\": {{",
+            escape_json_string(&entry.command),
+            entry.count,
+            entry.percentage
+        );
+        for (j, (label, count)) in labels.iter().zip(&entry.sources).enumerate()
+        {
+            if j > 0 {
+                result.push(',');
+            }
+            let _ = write!(
+                result,
+                "\n      \"{}\": {}",
+                escape_json_string(label),
+                count
+            );
+        }
+        result.push_str("\n    }\n  }");
+
+        if i < entries.len() - 1 {
+            result.push(',');
+        }
+        result.push('\n');
+    }
+
+    result.push(']');
+    result
+}
+
+/// Format trend output as JSON, one object per (period, command)
+pub fn format_trend_json(entries: &[TrendEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 80 + 4);
+    result.push_str("[\n");
+
+    for (i, entry) in entries.iter().enumerate() {
+        let _ = write!(
+            result,
+            "  {{\n    \"period\": \"{}\",\n    \"command\": \"{}\",\n    \"count\": {}\n  }}",
+            escape_json_string(&entry.period),
+            escape_json_string(&entry.command),
+            entry.count
+        );
+
+        if i < entries.len() - 1 {
+            result.push(',');
+        }
+        result.push('\n');
+    }
+
+    result.push(']');
+    result
+}
+
+/// Format command durations as JSON
+pub fn format_durations_json(entries: &[DurationEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 120 + 4);
+    result.push_str("[\n");
+
+    for (i, entry) in entries.iter().enumerate() {
+        let _ = write!(
+            result,
+            "  {{\n    \"command\": \"{}\",\n    \"count\": {},\n    \"total_seconds\": {},\n    \"median_seconds\": {},\n    \"percentage\": {:.2}\n  }}",
+            escape_json_string(&entry.command),
+            entry.count,
+            entry.total_seconds,
+            entry.median_seconds,
+            entry.percentage
+        );
+
+        if i < entries.len() - 1 {
+            result.push(',');
+        }
+        result.push('\n');
+    }
+
+    result.push(']');
+    result
+}
+
+/// Format referenced paths and directories as JSON
+pub fn format_paths_json(
+    paths: &[PathEntry],
+    directories: &[PathEntry],
+) -> String {
+    let mut result =
+        String::with_capacity((paths.len() + directories.len()) * 64 + 40);
+    result.push_str("{\n");
+
+    for (key, entries) in [("paths", paths), ("directories", directories)] {
+        let _ = write!(result, "  \"{}\": [", key);
+        for (i, entry) in entries.iter().enumerate() {
+            let _ = write!(
+                result,
+                "\n    {{\n      \"path\": \"{}\",\n      \"count\": {}\n    }}",
+                escape_json_string(&entry.path),
+                entry.count
+            );
+            if i < entries.len() - 1 {
+                result.push(',');
+            }
+        }
+        if !entries.is_empty() {
+            result.push_str("\n  ");
+        }
+        result.push(']');
+        if key == "paths" {
+            result.push(',');
+        }
+        result.push('\n');
+    }
+
+    result.push('}');
+    result
+}
+
+/// Format discovered history files as JSON
+pub fn format_history_files_json(entries: &[HistoryFileEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 160 + 4);
+    result.push_str("[\n");
+
+    for (i, entry) in entries.iter().enumerate() {
+        let _ = write!(
+            result,
+            "  {{\n    \"path\": \"{}\",\n    \"format\": \"{}\",\n    \"entries\": {},\n    \"first\": {},\n    \"last\": {}\n  }}",
+            escape_json_string(&entry.path),
+            escape_json_string(&entry.format),
+            entry.entries,
+            optional_json_string(entry.first.as_deref()),
+            optional_json_string(entry.last.as_deref())
+        );
+
+        if i < entries.len() - 1 {
+            result.push(',');
+        }
+        result.push('\n');
+    }
+
+    result.push(']');
+    result
+}
+
+/// Format format detection reports as JSON
+pub fn format_detections_json(entries: &[DetectionEntry]) -> String {
+    let mut result = String::with_capacity(entries.len() * 400 + 4);
+    result.push_str("[\n");
+
+    for (i, entry) in entries.iter().enumerate() {
+        let scores: Vec<String> = entry
+            .scores
+            .iter()
+            .map(|(format, score)| {
+                format!("\"{}\": {}", escape_json_string(format), score)
+            })
+            .collect();
+        let reasons: Vec<String> = entry
+            .reasons
+            .iter()
+            .map(|reason| format!("\"{}\"", escape_json_string(reason)))
+            .collect();
+        let _ = write!(
+            result,
+            "  {{\n    \"path\": \"{}\",\n    \"format\": \"{}\",\n    \"lines_inspected\": {},\n    \"ambiguous\": {},\n    \"scores\": {{{}}},\n    \"reasons\": [{}]\n  }}",
+            escape_json_string(&entry.path),
+            escape_json_string(&entry.format),
+            entry.lines_inspected,
+            entry.ambiguous,
+            scores.join(", "),
+            reasons.join(", ")
+        );
+
+        if i < entries.len() - 1 {
+            result.push(',');
+        }
+        result.push('\n');
+    }
+
+    result.push(']');
+    result
+}
+
+fn optional_json_string(value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("\"{}\"", escape_json_string(value)),
+        None => "null".to_string(),
+    }
+}
+
+/// Format a weekday by hour heatmap as JSON
+pub fn format_heatmap_json(
+    heatmap: &Heatmap,
+    command: Option<&str>,
+    zone: &str,
+) -> String {
+    let mut result = String::with_capacity(1200);
+    let command = optional_json_string(command);
+    let _ = write!(
+        result,
+        "{{\n  \"command\": {},\n  \"timezone\": \"{}\",\n  \"total\": {},\n  \"days\": [\n",
+        command,
+        zone,
+        heatmap.total()
+    );
+
+    for (i, (day, row)) in WEEKDAYS.iter().zip(heatmap.cells.iter()).enumerate()
+    {
+        let hours: Vec<String> = row.iter().map(usize::to_string).collect();
+        let _ = write!(
+            result,
+            "    {{\n      \"day\": \"{}\",\n      \"hours\": [{}]\n    }}",
+            day,
+            hours.join(", ")
+        );
+        if i < WEEKDAYS.len() - 1 {
+            result.push(',');
+        }
+        result.push('\n');
+    }
+
+    result.push_str("  ]\n}");
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
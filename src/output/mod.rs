@@ -3,10 +3,20 @@
 pub mod bar;
 pub mod color;
 mod csv;
+pub mod heatmap;
 mod json;
+pub mod sparkline;
 
-pub use csv::format_csv;
-pub use json::format_json;
+pub use csv::{
+    format_csv, format_detections_csv, format_durations_csv,
+    format_heatmap_csv, format_history_files_csv, format_paths_csv,
+    format_sources_csv, format_trend_csv,
+};
+pub use json::{
+    format_detections_json, format_durations_json, format_heatmap_json,
+    format_history_files_json, format_json, format_paths_json,
+    format_sources_json, format_trend_json,
+};
 
 use crate::output::bar::RenderedBar;
 
@@ -58,6 +68,66 @@ impl CommandEntry {
     }
 }
 
+/// Command count split by input file, for by-source ranking output
+#[derive(Debug)]
+pub struct SourceEntry {
+    pub command: String,
+    pub count: usize,
+    pub percentage: f64,
+    /// One count per source label
+    pub sources: Vec<usize>,
+}
+
+/// Count of one command in one time period, for trend output
+#[derive(Debug)]
+pub struct TrendEntry {
+    pub period: String,
+    pub command: String,
+    pub count: usize,
+}
+
+/// Wall time statistics of one command, for duration output
+#[derive(Debug)]
+pub struct DurationEntry {
+    pub command: String,
+    pub count: usize,
+    pub total_seconds: u64,
+    pub median_seconds: u64,
+    pub percentage: f64,
+}
+
+/// Number of entries that referenced a path, for path output
+#[derive(Debug)]
+pub struct PathEntry {
+    pub path: String,
+    pub count: usize,
+}
+
+/// Detected format, size and date range of a history file, for discovery
+/// output
+#[derive(Debug)]
+pub struct HistoryFileEntry {
+    pub path: String,
+    pub format: String,
+    pub entries: usize,
+    /// Date of the oldest and newest timestamped entries
+    pub first: Option<String>,
+    pub last: Option<String>,
+}
+
+/// How a history file's format was detected, for detection output
+#[derive(Debug)]
+pub struct DetectionEntry {
+    pub path: String,
+    pub format: String,
+    pub lines_inspected: u32,
+    /// Whether the two best scores are close
+    pub ambiguous: bool,
+    /// Score of each text format, by format name
+    pub scores: Vec<(String, u32)>,
+    pub reasons: Vec<String>,
+}
+
 /// Convert RenderedBars to CommandEntries for alternative output formats
 pub fn bars_to_entries(
     bars: &[RenderedBar],
@@ -120,4 +190,182 @@ mod tests {
         let csv = format_csv(&entries);
         assert!(csv.contains("\"echo,hello\""));
     }
+
+    #[test]
+    fn test_format_sources_json_and_csv() {
+        let entries = vec![SourceEntry {
+            command: "git".to_string(),
+            count: 5,
+            percentage: 62.5,
+            sources: vec![2, 3],
+        }];
+        let labels = vec!["bash".to_string(), "a,b/fish_history".to_string()];
+        let json = format_sources_json(&entries, &labels);
+        assert!(json.contains("\"percentage\": 62.50,\n"));
+        assert!(json.contains(
+            "\"sources\": {\n      \"bash\": 2,\n      \"a,b/fish_history\": 3\n    }"
+        ));
+
+        let csv = format_sources_csv(&entries, &labels);
+        assert_eq!(
+            csv,
+            "command,count,percentage,bash,\"a,b/fish_history\"\ngit,5,62.50,2,3\n"
+        );
+    }
+
+    #[test]
+    fn test_format_trend_json_and_csv() {
+        let entries = vec![
+            TrendEntry {
+                period: "2026-01".to_string(),
+                command: "git".to_string(),
+                count: 3,
+            },
+            TrendEntry {
+                period: "2026-02".to_string(),
+                command: "git".to_string(),
+                count: 0,
+            },
+        ];
+        let json = format_trend_json(&entries);
+        assert!(json.contains("\"period\": \"2026-01\""));
+        assert!(json.contains("\"command\": \"git\""));
+        assert!(json.contains("\"count\": 0"));
+
+        let csv = format_trend_csv(&entries);
+        assert_eq!(csv, "period,command,count\n2026-01,git,3\n2026-02,git,0\n");
+    }
+
+    #[test]
+    fn test_format_durations_json_and_csv() {
+        let entries = vec![DurationEntry {
+            command: "cargo".to_string(),
+            count: 3,
+            total_seconds: 670,
+            median_seconds: 40,
+            percentage: 75.0,
+        }];
+        let json = format_durations_json(&entries);
+        assert!(json.contains("\"command\": \"cargo\""));
+        assert!(json.contains("\"total_seconds\": 670"));
+        assert!(json.contains("\"median_seconds\": 40"));
+        assert!(json.contains("\"percentage\": 75.00"));
+
+        let csv = format_durations_csv(&entries);
+        assert_eq!(
+            csv,
+            "command,count,total_seconds,median_seconds,percentage\ncargo,3,670,40,75.00\n"
+        );
+    }
+
+    #[test]
+    fn test_format_paths_json_and_csv() {
+        let paths =
+            vec![PathEntry { path: "/srv/a,b.txt".to_string(), count: 2 }];
+        let directories =
+            vec![PathEntry { path: "/srv".to_string(), count: 3 }];
+        let json = format_paths_json(&paths, &directories);
+        assert!(
+            json.contains(
+                "\"paths\": [\n    {\n      \"path\": \"/srv/a,b.txt\""
+            )
+        );
+        assert!(
+            json.contains(
+                "\"directories\": [\n    {\n      \"path\": \"/srv\""
+            )
+        );
+        assert!(json.contains("\"count\": 3"));
+
+        let csv = format_paths_csv(&paths, &directories);
+        assert_eq!(
+            csv,
+            "kind,path,count\npath,\"/srv/a,b.txt\",2\ndirectory,/srv,3\n"
+        );
+    }
+
+    #[test]
+    fn test_format_history_files_json_and_csv() {
+        let entries = vec![
+            HistoryFileEntry {
+                path: "/home/u/.bash_history".to_string(),
+                format: "shell".to_string(),
+                entries: 21,
+                first: None,
+                last: None,
+            },
+            HistoryFileEntry {
+                path: "/home/u/fish_history".to_string(),
+                format: "fish".to_string(),
+                entries: 3,
+                first: Some("2023-04-06".to_string()),
+                last: Some("2023-04-07".to_string()),
+            },
+        ];
+        let json = format_history_files_json(&entries);
+        assert!(json.contains("\"format\": \"shell\""));
+        assert!(json.contains("\"first\": null"));
+        assert!(json.contains("\"last\": \"2023-04-07\""));
+
+        let csv = format_history_files_csv(&entries);
+        assert_eq!(
+            csv,
+            "path,format,entries,first,last\n/home/u/.bash_history,shell,21,,\n/home/u/fish_history,fish,3,2023-04-06,2023-04-07\n"
+        );
+    }
+
+    #[test]
+    fn test_format_detections_json_and_csv() {
+        let entries = vec![DetectionEntry {
+            path: "/home/u/history.txt".to_string(),
+            format: "fish".to_string(),
+            lines_inspected: 7,
+            ambiguous: true,
+            scores: vec![("fish".to_string(), 6), ("shell".to_string(), 5)],
+            reasons: vec![
+                "1 lines start with \"- cmd: \"".to_string(),
+                "5 lines are plain commands".to_string(),
+            ],
+        }];
+        let json = format_detections_json(&entries);
+        assert!(json.contains("\"ambiguous\": true"));
+        assert!(json.contains("\"scores\": {\"fish\": 6, \"shell\": 5}"));
+        assert!(
+            json.contains(
+                "\"reasons\": [\"1 lines start with \\\"- cmd: \\\"\""
+            )
+        );
+
+        let csv = format_detections_csv(&entries);
+        assert_eq!(
+            csv,
+            "path,format,lines_inspected,ambiguous,scores,reasons\n/home/u/history.txt,fish,7,true,fish=6 shell=5,\"1 lines start with \"\"- cmd: \"\"; 5 lines are plain commands\"\n"
+        );
+    }
+
+    #[test]
+    fn test_format_heatmap_json() {
+        let mut heatmap = heatmap::Heatmap::default();
+        heatmap.add(3, 22);
+        let json = format_heatmap_json(&heatmap, Some("git"), "utc");
+        assert!(json.contains("\"command\": \"git\""));
+        assert!(json.contains("\"timezone\": \"utc\""));
+        assert!(json.contains("\"total\": 1"));
+        assert!(json.contains("\"day\": \"Thu\""));
+        assert!(json.contains("0, 0, 1, 0]"));
+
+        let json = format_heatmap_json(&heatmap, None, "local");
+        assert!(json.contains("\"command\": null"));
+    }
+
+    #[test]
+    fn test_format_heatmap_csv() {
+        let mut heatmap = heatmap::Heatmap::default();
+        heatmap.add(0, 9);
+        let csv = format_heatmap_csv(&heatmap);
+        assert!(csv.starts_with("day,hour,count\n"));
+        assert!(csv.contains("Mon,9,1\n"));
+        assert!(csv.contains("Sun,23,0\n"));
+        assert_eq!(csv.lines().count(), 1 + 7 * 24);
+    }
 }
@@ -0,0 +1,39 @@
+//! Unicode block sparklines for small series of counts.
+
+/// Block glyphs from lowest to highest non-zero level
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Render one glyph per value, scaled to the largest value.
+///
+/// Zero values are rendered as a space so that idle periods stand out.
+pub fn sparkline(values: &[usize]) -> String {
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&value| {
+            if value == 0 || max == 0 {
+                ' '
+            } else {
+                let level = (value * BLOCKS.len()).div_ceil(max);
+                BLOCKS[level.clamp(1, BLOCKS.len()) - 1]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_scales_to_max() {
+        assert_eq!(sparkline(&[1, 2, 4, 8]), "▁▂▄█");
+        assert_eq!(sparkline(&[0, 3, 0, 3]), " █ █");
+    }
+
+    #[test]
+    fn test_sparkline_empty_and_idle() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[0, 0]), "  ");
+    }
+}
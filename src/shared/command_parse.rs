@@ -3,7 +3,35 @@
 use ahash::AHashSet;
 use bstr::ByteSlice;
 
-/// Extract the first meaningful word(s) from a command.
+/// Reserved words and quoting rules of a shell language
+pub struct ShellLanguage {
+    /// Words skipped to find the command, such as `then`
+    prefixes: &'static [&'static str],
+    /// Words that close a block or introduce a pattern, after which no
+    /// command is named, such as `done`
+    closers: &'static [&'static str],
+    /// A bare `(...)` substitutes a command, as in fish, instead of
+    /// running a subshell
+    paren_substitution: bool,
+    /// Backslash escapes apply inside single quotes, as in fish
+    single_quote_escapes: bool,
+}
+
+pub const POSIX_SHELL: ShellLanguage = ShellLanguage {
+    prefixes: &["!", "{", "do", "then", "else", "elif"],
+    closers: &["}", "done", "fi", "esac"],
+    paren_substitution: false,
+    single_quote_escapes: false,
+};
+
+pub const FISH: ShellLanguage = ShellLanguage {
+    prefixes: &["!", "and", "or", "not", "begin", "else"],
+    closers: &["end", "case", "switch"],
+    paren_substitution: true,
+    single_quote_escapes: true,
+};
+
+/// Extract the first meaningful word(s) from a POSIX shell command.
 ///
 /// # Arguments
 /// * `cmd` - The command string to parse
@@ -15,6 +43,16 @@ use bstr::ByteSlice;
 pub fn get_first_word<'a>(
     cmd: &'a str,
     filtered: &AHashSet<&str>,
+) -> Option<&'a str> {
+    get_first_word_for(cmd, filtered, &POSIX_SHELL)
+}
+
+/// Extract the first meaningful word(s) from a command written in `shell`.
+#[inline]
+pub fn get_first_word_for<'a>(
+    cmd: &'a str,
+    filtered: &AHashSet<&str>,
+    shell: &ShellLanguage,
 ) -> Option<&'a str> {
     let bytes = cmd.as_bytes();
     let mut i = 0;
@@ -50,9 +88,10 @@ pub fn get_first_word<'a>(
             continue;
         }
 
-        // 4. Handle escaped commands (\ls -> ls) and separators
-        // We do this BEFORE filtered check to handle \sudo
-        let clean_word = w.trim_matches('\\');
+        // 4. Handle escaped commands (\ls -> ls), separators and subshell
+        // parentheses ((cd foo -> cd). We do this BEFORE filtered check to
+        // handle \sudo
+        let clean_word = w.trim_matches(['\\', '(', ')']);
 
         // Handle cases like cd\numount -> cd
         let first_component =
@@ -82,8 +121,14 @@ pub fn get_first_word<'a>(
             continue;
         }
 
-        // 5. Skip filtered commands (sudo, doas, etc.) - after cleaning
-        if filtered.contains(command_name) {
+        // 5. Skip reserved words (do, then, ...) and filtered commands
+        // (sudo, doas, etc.) - after cleaning
+        if shell.closers.contains(&command_name) {
+            return None;
+        }
+        if shell.prefixes.contains(&command_name)
+            || filtered.contains(command_name)
+        {
             continue;
         }
 
@@ -100,19 +145,41 @@ pub fn get_first_word<'a>(
     None
 }
 
-/// Iterator that splits a command line by pipes `|`, respecting quotes.
+/// Iterator that splits a command line into the simple commands it runs.
 ///
-/// This avoids allocating a new String just to mask pipes inside quotes.
+/// Splits on `;`, `&&`, `||`, `&`, `|`, `|&` and newlines, but not inside
+/// single quotes, double quotes, `$'...'` strings, command substitutions or
+/// comments, nor on escaped characters. `&` in a redirection such as
+/// `2>&1` or `&>` does not split either.
 pub struct SplitCommands<'a> {
     remaining: &'a str,
+    shell: &'static ShellLanguage,
 }
 
 impl<'a> SplitCommands<'a> {
+    /// Split a POSIX shell command line
     pub fn new(line: &'a str) -> Self {
-        Self { remaining: line }
+        Self::for_shell(line, &POSIX_SHELL)
+    }
+
+    pub fn for_shell(
+        line: &'a str,
+        shell: &'static ShellLanguage,
+    ) -> Self {
+        Self { remaining: line, shell }
     }
 }
 
+/// Quoting in effect at a position of a command line
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Quote {
+    None,
+    Single,
+    Double,
+    /// `$'...'`, where backslash escapes apply
+    Ansi,
+}
+
 impl<'a> Iterator for SplitCommands<'a> {
     type Item = &'a str;
 
@@ -121,25 +188,84 @@ impl<'a> Iterator for SplitCommands<'a> {
             return None;
         }
 
-        let mut in_single_quotes = false;
-        let mut in_double_quotes = false;
-        let mut split_idx = None;
+        let bytes = self.remaining.as_bytes();
+        let mut quote = Quote::None;
+        // Depth of `$(...)` command substitutions
+        let mut substitution = 0_usize;
+        let mut in_backticks = false;
+        let mut in_comment = false;
+        let mut split = None;
+
+        let mut i = 0;
+        while i < bytes.len() {
+            let b = bytes[i];
+            let next = bytes.get(i + 1).copied();
+
+            if in_comment {
+                if b != b'\n' {
+                    i += 1;
+                    continue;
+                }
+                in_comment = false;
+            }
 
-        for (i, b) in self.remaining.bytes().enumerate() {
-            match b {
-                b'\'' => in_single_quotes = !in_single_quotes,
-                b'"' => in_double_quotes = !in_double_quotes,
-                b'|' if !in_single_quotes && !in_double_quotes => {
-                    split_idx = Some(i);
+            match (quote, b) {
+                (Quote::Single, b'\'')
+                | (Quote::Double, b'"')
+                | (Quote::Ansi, b'\'') => quote = Quote::None,
+                (Quote::Double | Quote::Ansi, b'\\') => i += 1,
+                (Quote::Single, b'\\') if self.shell.single_quote_escapes => {
+                    i += 1
+                }
+                (Quote::Single | Quote::Double | Quote::Ansi, _) => {}
+                (Quote::None, b'\\') => i += 1,
+                (Quote::None, b'\'') => quote = Quote::Single,
+                (Quote::None, b'"') => quote = Quote::Double,
+                (Quote::None, b'$') if next == Some(b'\'') => {
+                    quote = Quote::Ansi;
+                    i += 1;
+                }
+                (Quote::None, b'$') if next == Some(b'(') => {
+                    substitution += 1;
+                    i += 1;
+                }
+                (Quote::None, b'(')
+                    if substitution > 0 || self.shell.paren_substitution =>
+                {
+                    substitution += 1
+                }
+                (Quote::None, b')') if substitution > 0 => substitution -= 1,
+                (Quote::None, b'`') => in_backticks = !in_backticks,
+                _ if substitution > 0 || in_backticks => {}
+                (Quote::None, b'#') if starts_word(bytes, i) => {
+                    in_comment = true
+                }
+                (Quote::None, b'\n' | b';') => {
+                    split = Some((i, 1));
+                    break;
+                }
+                (Quote::None, b'|') => {
+                    let len =
+                        if matches!(next, Some(b'|' | b'&')) { 2 } else { 1 };
+                    split = Some((i, len));
+                    break;
+                }
+                (Quote::None, b'&') if next == Some(b'&') => {
+                    split = Some((i, 2));
+                    break;
+                }
+                (Quote::None, b'&') if !is_redirection(bytes, i) => {
+                    split = Some((i, 1));
                     break;
                 }
                 _ => {}
             }
+            i += 1;
         }
 
-        if let Some(idx) = split_idx {
+        if let Some((idx, len)) = split {
             let (chunk, rest) = self.remaining.split_at(idx);
-            self.remaining = &rest[1..]; // Skip the pipe
+            self.remaining = &rest[len..];
             Some(chunk)
         } else {
             let chunk = self.remaining;
@@ -149,6 +275,25 @@ impl<'a> Iterator for SplitCommands<'a> {
     }
 }
 
+/// Whether the byte at `i` starts a word, where a `#` begins a comment
+fn starts_word(
+    bytes: &[u8],
+    i: usize,
+) -> bool {
+    i == 0
+        || matches!(bytes[i - 1], b';' | b'&' | b'|' | b'(' | b')')
+        || bytes[i - 1].is_ascii_whitespace()
+}
+
+/// Whether the `&` at `i` belongs to a redirection (`2>&1`, `<&3`, `&>`)
+fn is_redirection(
+    bytes: &[u8],
+    i: usize,
+) -> bool {
+    (i > 0 && matches!(bytes[i - 1], b'>' | b'<'))
+        || bytes.get(i + 1) == Some(&b'>')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -322,4 +467,102 @@ mod tests {
             SplitCommands::new("echo 'foo | bar' | grep baz").collect();
         assert_eq!(parts, vec!["echo 'foo | bar' ", " grep baz"]);
     }
+
+    #[test]
+    fn test_split_commands_list_operators() {
+        let parts: Vec<&str> =
+            SplitCommands::new("make && ./run || echo failed; ls").collect();
+        assert_eq!(parts, vec!["make ", " ./run ", " echo failed", " ls"]);
+    }
+
+    #[test]
+    fn test_split_commands_background_and_pipe_stderr() {
+        let parts: Vec<&str> =
+            SplitCommands::new("sleep 5 & make |& tee log").collect();
+        assert_eq!(parts, vec!["sleep 5 ", " make ", " tee log"]);
+    }
+
+    #[test]
+    fn test_split_commands_keeps_redirections() {
+        let parts: Vec<&str> =
+            SplitCommands::new("make 2>&1 &>/dev/null <&3").collect();
+        assert_eq!(parts, vec!["make 2>&1 &>/dev/null <&3"]);
+    }
+
+    #[test]
+    fn test_split_commands_multiline_block() {
+        let parts: Vec<&str> =
+            SplitCommands::new("for f in *; do\n  echo $f\ndone").collect();
+        assert_eq!(parts, vec!["for f in *", " do", "  echo $f", "done"]);
+    }
+
+    #[test]
+    fn test_split_commands_respects_quotes_and_escapes() {
+        let parts: Vec<&str> = SplitCommands::new(
+            "git commit -m \"a; b\nc\" && echo 'x && y' \\; $'it\\'s; ok'",
+        )
+        .collect();
+        assert_eq!(
+            parts,
+            vec![
+                "git commit -m \"a; b\nc\" ",
+                " echo 'x && y' \\; $'it\\'s; ok'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_commands_keeps_continued_lines() {
+        let parts: Vec<&str> =
+            SplitCommands::new("make \\\n  -j8\necho 'a\nb'").collect();
+        assert_eq!(parts, vec!["make \\\n  -j8", "echo 'a\nb'"]);
+    }
+
+    #[test]
+    fn test_split_commands_ignores_substitutions_and_comments() {
+        let parts: Vec<&str> = SplitCommands::new(
+            "echo $(date; uptime) `id | cut -c1` # a; b\nls",
+        )
+        .collect();
+        assert_eq!(
+            parts,
+            vec!["echo $(date; uptime) `id | cut -c1` # a; b", "ls"]
+        );
+    }
+
+    #[test]
+    fn test_split_commands_fish_substitutions_and_quotes() {
+        let parts: Vec<&str> = SplitCommands::for_shell(
+            "echo (date; uptime) 'it\\'s; ok'; and ls",
+            &FISH,
+        )
+        .collect();
+        assert_eq!(parts, vec!["echo (date; uptime) 'it\\'s; ok'", " and ls"]);
+    }
+
+    #[test]
+    fn test_get_first_word_for_fish_skips_reserved_words() {
+        let filters = AHashSet::new();
+        let first = |cmd| get_first_word_for(cmd, &filters, &FISH);
+        assert_eq!(first(" and ./run"), Some("run"));
+        assert_eq!(first(" or echo fail"), Some("echo"));
+        assert_eq!(first("not test -f x"), Some("test"));
+        assert_eq!(first("begin"), None);
+        assert_eq!(first(" end"), None);
+        assert_eq!(first("case '*.rs'"), None);
+        assert_eq!(first("switch $argv"), None);
+        assert_eq!(first("else if true"), Some("if"));
+    }
+
+    #[test]
+    fn test_get_first_word_skips_reserved_words() {
+        let filters = AHashSet::new();
+        assert_eq!(get_first_word(" do echo $f", &filters), Some("echo"));
+        assert_eq!(get_first_word("done", &filters), None);
+        assert_eq!(get_first_word("done < list", &filters), None);
+        assert_eq!(get_first_word(" then ls", &filters), Some("ls"));
+        assert_eq!(get_first_word("{ ls", &filters), Some("ls"));
+        assert_eq!(get_first_word("(cd foo ", &filters), Some("cd"));
+        assert_eq!(get_first_word(" make)", &filters), Some("make"));
+    }
 }
@@ -0,0 +1,154 @@
+//! Minimal glob expansion for input file patterns.
+//!
+//! Supports `*`, `?` and bracket classes (`[abc]`, `[a-z]`, `[!a]`) within
+//! a path component, which covers patterns like `~/histories/*_history`
+//! that reach histop quoted or from a shell that does not expand them.
+
+use std::fs;
+use std::path::Path;
+
+/// Whether `s` contains glob metacharacters
+pub fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// Paths matching `pattern`, sorted.
+///
+/// As in the shell, wildcards do not match a leading `.` unless the
+/// pattern component starts with one.
+pub fn expand(pattern: &str) -> Vec<String> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_string()], rest),
+        None => (vec![String::new()], pattern),
+    };
+
+    for component in rest.split('/').filter(|c| !c.is_empty()) {
+        let mut next = Vec::new();
+        for base in &paths {
+            if !is_pattern(component) {
+                next.push(join(base, component));
+                continue;
+            }
+            let dir = if base.is_empty() { "." } else { base.as_str() };
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let Some(name) = name.to_str() else {
+                    continue;
+                };
+                if name.starts_with('.') && !component.starts_with('.') {
+                    continue;
+                }
+                if matches(component.as_bytes(), name.as_bytes()) {
+                    next.push(join(base, name));
+                }
+            }
+        }
+        paths = next;
+    }
+
+    paths.retain(|path| !path.is_empty() && Path::new(path).exists());
+    paths.sort();
+    paths
+}
+
+fn join(
+    base: &str,
+    name: &str,
+) -> String {
+    match base {
+        "" => name.to_string(),
+        "/" => format!("/{}", name),
+        _ => format!("{}/{}", base, name),
+    }
+}
+
+/// Match one path component against a pattern component.
+fn matches(
+    pattern: &[u8],
+    name: &[u8],
+) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some(b'*') => {
+            (0..=name.len()).any(|i| matches(&pattern[1..], &name[i..]))
+        }
+        Some(b'?') => !name.is_empty() && matches(&pattern[1..], &name[1..]),
+        Some(b'[') => match (name.first(), class_end(pattern)) {
+            (Some(&c), Some(end)) => {
+                in_class(&pattern[1..end], c)
+                    && matches(&pattern[end + 1..], &name[1..])
+            }
+            (Some(&c), None) => c == b'[' && matches(&pattern[1..], &name[1..]),
+            (None, _) => false,
+        },
+        Some(&p) => {
+            name.first() == Some(&p) && matches(&pattern[1..], &name[1..])
+        }
+    }
+}
+
+/// Index of the `]` closing the class that starts `pattern`
+fn class_end(pattern: &[u8]) -> Option<usize> {
+    // A `]` right after `[` or `[!` is a member, not the end
+    let first = if pattern.get(1) == Some(&b'!') { 3 } else { 2 };
+    pattern.get(first..)?.iter().position(|&b| b == b']').map(|i| i + first)
+}
+
+fn in_class(
+    class: &[u8],
+    c: u8,
+) -> bool {
+    let (negated, class) = match class.strip_prefix(b"!") {
+        Some(rest) => (true, rest),
+        None => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if class.get(i + 1) == Some(&b'-') && i + 2 < class.len() {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(b"*_history", b"bash_history"));
+        assert!(matches(b"*", b""));
+        assert!(!matches(b"*_history", b"history"));
+        assert!(matches(b"h?st", b"hist"));
+        assert!(matches(b"[bz]sh", b"zsh"));
+        assert!(!matches(b"[!bz]sh", b"zsh"));
+        assert!(matches(b"log.[0-9]", b"log.3"));
+        assert!(matches(b"[]]", b"]"));
+        assert!(matches(b"a[b", b"a[b"));
+    }
+
+    #[test]
+    fn test_expand_fixture_directory() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+        let paths = expand(&format!("{}/[!bf]*sh_history", dir));
+        let names: Vec<&str> =
+            paths.iter().map(|p| p.rsplit('/').next().unwrap()).collect();
+        assert_eq!(
+            names,
+            vec!["ksh_history", "mksh_history", "tcsh_history", "zsh_history"]
+        );
+
+        assert!(expand(&format!("{}/repl/*", dir)).is_empty());
+        assert_eq!(expand(&format!("{}/repl/.p*", dir)).len(), 2);
+        assert!(expand(&format!("{}/missing/*", dir)).is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+//! Minimal JSON reader for history formats stored as JSON.
+//!
+//! Only what the history parsers need: a full parse into [`JsonValue`]
+//! and a few accessors. Invalid input yields `None` rather than an error,
+//! since the callers skip unreadable files.
+
+/// Nesting deeper than this is treated as invalid input
+const MAX_DEPTH: usize = 128;
+
+/// A parsed JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Members in document order
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Member `key` of an object
+    pub fn get(
+        &self,
+        key: &str,
+    ) -> Option<&JsonValue> {
+        match self {
+            Self::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The value as an integer, if it is a number without a fraction
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_f64()
+            .filter(|n| n.fract() == 0.0 && n.abs() < 9.0e15)
+            .map(|n| n as i64)
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a complete JSON document.
+pub fn parse(input: &str) -> Option<JsonValue> {
+    let mut parser = Parser { bytes: input.as_bytes(), pos: 0 };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    (parser.pos == parser.bytes.len()).then_some(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(
+        &mut self,
+        literal: &[u8],
+    ) -> Option<()> {
+        if self.bytes[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn value(
+        &mut self,
+        depth: usize,
+    ) -> Option<JsonValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match self.peek()? {
+            b'{' => self.object(depth),
+            b'[' => self.array(depth),
+            b'"' => self.string().map(JsonValue::String),
+            b't' => self.expect(b"true").map(|_| JsonValue::Bool(true)),
+            b'f' => self.expect(b"false").map(|_| JsonValue::Bool(false)),
+            b'n' => self.expect(b"null").map(|_| JsonValue::Null),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => None,
+        }
+    }
+
+    fn object(
+        &mut self,
+        depth: usize,
+    ) -> Option<JsonValue> {
+        self.pos += 1; // '{'
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Some(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return None;
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(b":")?;
+            let value = self.value(depth + 1)?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b'}' => {
+                    self.pos += 1;
+                    return Some(JsonValue::Object(members));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn array(
+        &mut self,
+        depth: usize,
+    ) -> Option<JsonValue> {
+        self.pos += 1; // '['
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Some(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b']' => {
+                    self.pos += 1;
+                    return Some(JsonValue::Array(items));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<JsonValue> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+            self.peek()
+        {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).ok()?;
+        text.parse().ok().map(JsonValue::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.pos += 1; // opening quote
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // The input is a &str and we only stop on ASCII bytes, so the
+            // run is valid UTF-8
+            out.push_str(
+                std::str::from_utf8(&self.bytes[start..self.pos]).ok()?,
+            );
+
+            match self.peek()? {
+                b'"' => {
+                    self.pos += 1;
+                    return Some(out);
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    let escape = self.peek()?;
+                    self.pos += 1;
+                    match escape {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => out.push(self.unicode_escape()?),
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Decode the digits after `\u`, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            // Lone low surrogates are replaced rather than rejected
+            return Some(char::from_u32(high).unwrap_or('\u{FFFD}'));
+        }
+        let low_start = self.pos;
+        if self.expect(b"\\u").is_none() {
+            return Some('\u{FFFD}');
+        }
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            // Leave the next escape to be decoded as a character of its own
+            self.pos = low_start;
+            return Some('\u{FFFD}');
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.bytes.get(self.pos..self.pos + 4)?;
+        let value =
+            u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+        self.pos += 4;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_document() {
+        let value = parse(
+            r#" {"cmds": [{"inp": "ls -la\n", "rtn": 0, "ts": [1.5, 2]}], "locked": false, "env": null} "#,
+        )
+        .unwrap();
+        let cmd = &value.get("cmds").unwrap().as_array().unwrap()[0];
+        assert_eq!(cmd.get("inp").unwrap().as_str(), Some("ls -la\n"));
+        assert_eq!(cmd.get("rtn").unwrap().as_i64(), Some(0));
+        assert_eq!(
+            cmd.get("ts").unwrap().as_array().unwrap()[0].as_f64(),
+            Some(1.5)
+        );
+        assert_eq!(value.get("locked"), Some(&JsonValue::Bool(false)));
+        assert_eq!(value.get("env"), Some(&JsonValue::Null));
+        assert_eq!(value.get("missing"), None);
+    }
+
+    #[test]
+    fn test_parse_string_escapes() {
+        assert_eq!(
+            parse(r#""a\"b\\cé😀\/""#),
+            Some(JsonValue::String("a\"b\\cé😀/".to_string()))
+        );
+        assert_eq!(
+            parse(r#""日本""#),
+            Some(JsonValue::String("日本".to_string()))
+        );
+        assert_eq!(
+            parse(r#""\ud83d\ude00\ud800\u0041\ud800x""#),
+            Some(JsonValue::String("😀\u{FFFD}A\u{FFFD}x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_documents() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("{\"a\": 1"), None);
+        assert_eq!(parse("[1, 2,]"), None);
+        assert_eq!(parse("{} {}"), None);
+        assert_eq!(parse("\"unterminated"), None);
+        assert_eq!(parse(&"[".repeat(500)), None);
+    }
+
+    #[test]
+    fn test_as_i64_requires_integer() {
+        assert_eq!(JsonValue::Number(3.0).as_i64(), Some(3));
+        assert_eq!(JsonValue::Number(3.5).as_i64(), None);
+        assert_eq!(JsonValue::Null.as_i64(), None);
+    }
+}
@@ -1,3 +1,8 @@
 //! Shared helpers reused across modules.
 
 pub mod command_parse;
+pub mod glob;
+pub mod json;
+pub mod rotation;
+pub mod statement_parse;
+pub mod time;
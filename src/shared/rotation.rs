@@ -0,0 +1,97 @@
+//! Rotated history series.
+//!
+//! Log rotation leaves older copies of a history next to it, such as
+//! `.bash_history.1`, `.zsh_history.2023.gz` or `fish_history-20230101.zst`:
+//! the file name, a separator, then a suffix made of digits and
+//! separators, optionally followed by a compression extension. A
+//! compressed copy without a suffix, like `.bash_history.gz`, is one too.
+
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Extensions a rotated copy may carry after its suffix
+const COMPRESSED_EXTENSIONS: [&str; 3] = [".gz", ".zst", ".zstd"];
+
+/// `path` and its rotated copies, oldest first.
+///
+/// Rotation schemes disagree on whether higher numbers are older, so the
+/// copies are ordered by modification time, which rotation preserves.
+/// `path` itself comes last, as the history being written to.
+pub fn series(path: &str) -> Vec<String> {
+    let path_ref = Path::new(path);
+    if !path_ref.is_file() {
+        return vec![path.to_string()];
+    }
+    let Some(name) = path_ref.file_name().and_then(|name| name.to_str()) else {
+        return vec![path.to_string()];
+    };
+    let dir = match path_ref.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![path.to_string()];
+    };
+
+    let mut rotated: Vec<(SystemTime, String)> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            if !is_rotation_of(&file_name, name) {
+                return None;
+            }
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            let path = path_ref.with_file_name(file_name);
+            Some((modified, path.to_str()?.to_string()))
+        })
+        .collect();
+    rotated.sort();
+
+    let mut series: Vec<String> =
+        rotated.into_iter().map(|(_, path)| path).collect();
+    series.push(path.to_string());
+    series
+}
+
+/// Whether `candidate` names a rotated copy of `name`
+fn is_rotation_of(
+    candidate: &str,
+    name: &str,
+) -> bool {
+    let Some(suffix) = candidate.strip_prefix(name) else {
+        return false;
+    };
+    let suffix = match COMPRESSED_EXTENSIONS
+        .iter()
+        .find_map(|ext| suffix.strip_suffix(ext))
+    {
+        Some("") => return true,
+        Some(stripped) => stripped,
+        None => suffix,
+    };
+    let mut chars = suffix.chars();
+    matches!(chars.next(), Some('.' | '-' | '_'))
+        && suffix.chars().any(|c| c.is_ascii_digit())
+        && chars.all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_rotation_of() {
+        let name = ".zsh_history";
+        assert!(is_rotation_of(".zsh_history.1", name));
+        assert!(is_rotation_of(".zsh_history.2023.gz", name));
+        assert!(is_rotation_of(".zsh_history-20230101.zst", name));
+        assert!(is_rotation_of(".zsh_history_2023-01-01", name));
+        assert!(!is_rotation_of(".zsh_history", name));
+        assert!(is_rotation_of(".zsh_history.gz", name));
+        assert!(!is_rotation_of(".zsh_history.LOCK", name));
+        assert!(!is_rotation_of(".zsh_history.new", name));
+        assert!(!is_rotation_of(".zsh_history2", name));
+    }
+}
@@ -0,0 +1,494 @@
+//! Statement heads for REPL histories (SQL, Python, JavaScript, Ruby).
+//!
+//! Where `command_parse` finds the program a shell line runs, these
+//! helpers name what a REPL line does: the statement keyword of an SQL
+//! query, a psql or sqlite meta-command, or the keyword or function called
+//! by a line of script.
+
+use bstr::ByteSlice;
+
+/// SQL statement keywords, spelled as they are reported
+const SQL_KEYWORDS: &[&str] = &[
+    "ABORT",
+    "ALTER",
+    "ANALYZE",
+    "ATTACH",
+    "BEGIN",
+    "CALL",
+    "CHECKPOINT",
+    "CLOSE",
+    "CLUSTER",
+    "COMMENT",
+    "COMMIT",
+    "COPY",
+    "CREATE",
+    "DEALLOCATE",
+    "DECLARE",
+    "DELETE",
+    "DETACH",
+    "DISCARD",
+    "DO",
+    "DROP",
+    "END",
+    "EXECUTE",
+    "EXPLAIN",
+    "FETCH",
+    "GRANT",
+    "IMPORT",
+    "INSERT",
+    "LISTEN",
+    "LOAD",
+    "LOCK",
+    "MERGE",
+    "MOVE",
+    "NOTIFY",
+    "PRAGMA",
+    "PREPARE",
+    "REFRESH",
+    "REINDEX",
+    "RELEASE",
+    "REPLACE",
+    "RESET",
+    "REVOKE",
+    "ROLLBACK",
+    "SAVEPOINT",
+    "SELECT",
+    "SET",
+    "SHOW",
+    "START",
+    "TABLE",
+    "TRUNCATE",
+    "UNLISTEN",
+    "UPDATE",
+    "UPSERT",
+    "VACUUM",
+    "VALUES",
+    "WITH",
+];
+
+/// Keywords and REPL conventions of a scripting language
+pub struct ScriptLanguage {
+    /// Statement keywords, counted as they are
+    keywords: &'static [&'static str],
+    /// Words skipped to find the statement head, such as `await`
+    prefixes: &'static [&'static str],
+    /// Words that only close a block and are not counted
+    closers: &'static [&'static str],
+    line_comment: &'static str,
+    /// REPL commands start with a dot, like node's `.editor`
+    dot_commands: bool,
+    /// Method names may end in `?` or `!`, as in Ruby
+    method_suffixes: bool,
+}
+
+pub const PYTHON: ScriptLanguage = ScriptLanguage {
+    keywords: &[
+        "assert", "break", "case", "class", "continue", "def", "del", "elif",
+        "else", "except", "finally", "for", "from", "global", "if", "import",
+        "lambda", "match", "nonlocal", "pass", "raise", "return", "try",
+        "while", "with", "yield",
+    ],
+    prefixes: &["async", "await"],
+    closers: &[],
+    line_comment: "#",
+    dot_commands: false,
+    method_suffixes: false,
+};
+
+pub const JAVASCRIPT: ScriptLanguage = ScriptLanguage {
+    keywords: &[
+        "break", "catch", "class", "continue", "delete", "do", "else",
+        "export", "finally", "for", "function", "if", "import", "return",
+        "switch", "throw", "try", "typeof", "while",
+    ],
+    prefixes: &["async", "await", "const", "let", "new", "var", "void"],
+    closers: &[],
+    line_comment: "//",
+    dot_commands: true,
+    method_suffixes: false,
+};
+
+pub const RUBY: ScriptLanguage = ScriptLanguage {
+    keywords: &[
+        "begin", "break", "case", "class", "def", "else", "elsif", "ensure",
+        "for", "if", "module", "next", "redo", "rescue", "retry", "return",
+        "unless", "until", "when", "while", "yield",
+    ],
+    prefixes: &[],
+    closers: &["end"],
+    line_comment: "#",
+    dot_commands: false,
+    method_suffixes: true,
+};
+
+/// Call `f` with the name of every statement in an SQL history entry.
+///
+/// Statements are split on `;` outside quotes and comments. Statements
+/// are named by their keyword in upper case (`select` counts as
+/// `SELECT`), and meta-commands (`\d+`, `.tables`) by their first word.
+pub fn for_each_sql_statement_name<'a, F>(
+    text: &'a str,
+    mut f: F,
+) where
+    F: FnMut(&'a str),
+{
+    for_each_sql_statement(text, |statement| {
+        if let Some(name) = sql_statement_name(statement) {
+            f(name);
+        }
+    });
+}
+
+/// Split SQL text into statements and meta-commands.
+///
+/// A meta-command runs to the end of its line, as in psql and sqlite.
+fn for_each_sql_statement<'a, F>(
+    text: &'a str,
+    mut f: F,
+) where
+    F: FnMut(&'a str),
+{
+    let bytes = text.as_bytes();
+    let len = bytes.len();
+    let mut start = 0;
+    let mut at_statement_start = true;
+    let mut i = 0;
+
+    while i < len {
+        let b = bytes[i];
+        match b {
+            b'\\' | b'.' if at_statement_start => {
+                let end = bytes[i..].find_byte(b'\n').map_or(len, |n| i + n);
+                f(&text[i..end]);
+                start = end;
+                i = end;
+                continue;
+            }
+            b'\'' | b'"' => {
+                i = bytes[i + 1..].find_byte(b).map_or(len, |n| i + 1 + n);
+                at_statement_start = false;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = bytes[i..].find_byte(b'\n').map_or(len, |n| i + n);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = bytes[i + 2..].find(b"*/").map_or(len, |n| i + 2 + n + 1);
+            }
+            b';' => {
+                f(&text[start..i]);
+                start = i + 1;
+                at_statement_start = true;
+            }
+            _ if !b.is_ascii_whitespace() => at_statement_start = false,
+            _ => {}
+        }
+        i += 1;
+    }
+    if start < len {
+        f(&text[start..]);
+    }
+}
+
+fn sql_statement_name(statement: &str) -> Option<&str> {
+    let statement = skip_sql_comments(statement).trim_start_matches('(');
+    if statement.starts_with(['\\', '.']) {
+        return statement.split_whitespace().next().filter(|w| w.len() > 1);
+    }
+
+    let end = statement
+        .bytes()
+        .position(|b| !b.is_ascii_alphabetic() && b != b'_')
+        .unwrap_or(statement.len());
+    let word = &statement[..end];
+    if word.is_empty() {
+        return None;
+    }
+    Some(
+        SQL_KEYWORDS
+            .iter()
+            .find(|keyword| keyword.eq_ignore_ascii_case(word))
+            .copied()
+            .unwrap_or(word),
+    )
+}
+
+/// Skip whitespace and comments before a statement.
+fn skip_sql_comments(mut statement: &str) -> &str {
+    loop {
+        statement = statement.trim_start();
+        if statement.starts_with("--") {
+            statement = statement.find('\n').map_or("", |i| &statement[i..]);
+        } else if let Some(rest) = statement.strip_prefix("/*") {
+            statement = rest.find("*/").map_or("", |i| &rest[i + 2..]);
+        } else {
+            return statement;
+        }
+    }
+}
+
+/// Call `f` with the head of every line of a script history entry.
+pub fn for_each_script_statement_name<'a, F>(
+    text: &'a str,
+    language: &ScriptLanguage,
+    mut f: F,
+) where
+    F: FnMut(&'a str),
+{
+    for line in text.lines() {
+        if let Some(name) = script_statement_name(line, language) {
+            f(name);
+        }
+    }
+}
+
+/// Head of one line of script: its keyword, the function or method it
+/// calls (`os.path.join`), or for an assignment the head of the value.
+fn script_statement_name<'a>(
+    line: &'a str,
+    language: &ScriptLanguage,
+) -> Option<&'a str> {
+    let mut rest = line.trim();
+    if rest.is_empty() || rest.starts_with(language.line_comment) {
+        return None;
+    }
+    if language.dot_commands && rest.starts_with('.') {
+        return rest.split_whitespace().next().filter(|w| w.len() > 1);
+    }
+
+    loop {
+        let (name, after) = split_identifier(rest, language.method_suffixes)?;
+        let after = after.trim_start();
+        if language.prefixes.contains(&name) {
+            rest = after;
+        } else if language.keywords.contains(&name) {
+            return Some(name);
+        } else if language.closers.contains(&name) {
+            return None;
+        } else if let Some(value) = assignment_value(after) {
+            rest = value.trim_start();
+        } else {
+            return Some(name);
+        }
+    }
+}
+
+/// Whether a line of xonsh input is Python rather than a subprocess
+/// command: a Python keyword, a call, an indexed expression, an
+/// assignment, a literal or a decorator.
+///
+/// `FOO=bar make` assigns an environment variable for a command, so an
+/// upper-case name directly followed by `=` stays a command.
+pub fn is_python_statement(line: &str) -> bool {
+    let line = line.trim();
+    let Some(&first) = line.as_bytes().first() else {
+        return false;
+    };
+    if b"([{'\"".contains(&first) || first.is_ascii_digit() {
+        return true;
+    }
+    if first == b'@' {
+        return line[1..].starts_with(|c: char| c.is_ascii_alphabetic());
+    }
+    let Some((name, after)) = split_identifier(line, false) else {
+        return false;
+    };
+    if PYTHON.keywords.contains(&name) || PYTHON.prefixes.contains(&name) {
+        return after.is_empty() || after.starts_with([' ', '\t', ':', '(']);
+    }
+    if after.starts_with(['(', '[']) {
+        return true;
+    }
+    let is_env_name = name
+        .bytes()
+        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_');
+    if is_env_name && after.starts_with('=') {
+        return false;
+    }
+    assignment_value(after.trim_start()).is_some()
+}
+
+/// Split a dotted identifier (`console.log`, `File::read`) off `s`.
+fn split_identifier(
+    s: &str,
+    method_suffixes: bool,
+) -> Option<(&str, &str)> {
+    let bytes = s.as_bytes();
+    let is_start = |b: &u8| b.is_ascii_alphabetic() || b"_$@".contains(b);
+    let is_part = |b: &u8| b.is_ascii_alphanumeric() || b"_$".contains(b);
+    if !bytes.first().is_some_and(is_start) {
+        return None;
+    }
+
+    let mut i = 1;
+    loop {
+        while bytes.get(i).is_some_and(is_part) {
+            i += 1;
+        }
+        match bytes.get(i) {
+            Some(b'.') if bytes.get(i + 1).is_some_and(is_start) => i += 2,
+            Some(b':')
+                if bytes.get(i + 1) == Some(&b':')
+                    && bytes.get(i + 2).is_some_and(is_start) =>
+            {
+                i += 3
+            }
+            Some(b'?' | b'!')
+                if method_suffixes
+                    && !bytes
+                        .get(i + 1)
+                        .is_some_and(|b| is_part(b) || *b == b'=') =>
+            {
+                i += 1;
+                break;
+            }
+            _ => break,
+        }
+    }
+    Some((&s[..i], &s[i..]))
+}
+
+/// Operators that combine with `=` into an assignment
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "%", "&", "|", "^", "**", "//", "<<", ">>", "||", "&&",
+    "??", ":",
+];
+
+/// The value of an assignment (`x = ...`, `total += ...`), if `rest`
+/// follows the target of one.
+fn assignment_value(rest: &str) -> Option<&str> {
+    if let Some(value) = rest.strip_prefix('=') {
+        return (!value.starts_with(['=', '>', '~'])).then_some(value);
+    }
+    let (op, value) = rest.split_at(rest.find('=')?);
+    let value = value.strip_prefix('=')?;
+    (ASSIGNMENT_OPERATORS.contains(&op) && !value.starts_with('='))
+        .then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_python_statement() {
+        assert!(is_python_statement("print('hello world')"));
+        assert!(is_python_statement("x = 1"));
+        assert!(is_python_statement("total += len(xs)"));
+        assert!(is_python_statement("import os"));
+        assert!(is_python_statement("for f in $(ls).split():"));
+        assert!(is_python_statement("$PATH.append('/opt/bin')"));
+        assert!(is_python_statement("$EDITOR = 'vim'"));
+        assert!(is_python_statement("os.path.join(a, b)"));
+        assert!(is_python_statement("[x for x in range(3)]"));
+        assert!(is_python_statement("@contextmanager"));
+        assert!(!is_python_statement("ls -la"));
+        assert!(!is_python_statement("git commit -m 'x = 1'"));
+        assert!(!is_python_statement("$EDITOR notes.txt"));
+        assert!(!is_python_statement("FOO=bar make"));
+        assert!(!is_python_statement("make"));
+        assert!(!is_python_statement(""));
+    }
+
+    fn sql_names(text: &str) -> Vec<&str> {
+        let mut names = Vec::new();
+        for_each_sql_statement_name(text, |name| names.push(name));
+        names
+    }
+
+    fn script_names<'a>(
+        text: &'a str,
+        language: &ScriptLanguage,
+    ) -> Vec<&'a str> {
+        let mut names = Vec::new();
+        for_each_script_statement_name(text, language, |name| names.push(name));
+        names
+    }
+
+    #[test]
+    fn test_sql_statement_keywords() {
+        assert_eq!(sql_names("select * from users;"), vec!["SELECT"]);
+        assert_eq!(
+            sql_names("begin; update t set a = 'x;y'; commit;"),
+            vec!["BEGIN", "UPDATE", "COMMIT"]
+        );
+        assert_eq!(
+            sql_names("-- count ; them\nSELECT count(*) FROM t"),
+            vec!["SELECT"]
+        );
+        assert_eq!(sql_names("/* a; b */ (select 1)"), vec!["SELECT"]);
+        assert_eq!(sql_names("frobnicate now"), vec!["frobnicate"]);
+        assert!(sql_names(";;  ").is_empty());
+    }
+
+    #[test]
+    fn test_sql_meta_commands() {
+        assert_eq!(sql_names("\\d+ users"), vec!["\\d+"]);
+        assert_eq!(sql_names(".tables"), vec![".tables"]);
+        assert_eq!(
+            sql_names("\\timing on\nselect 1;"),
+            vec!["\\timing", "SELECT"]
+        );
+        assert_eq!(sql_names("select 1.5;"), vec!["SELECT"]);
+    }
+
+    #[test]
+    fn test_python_statement_heads() {
+        assert_eq!(script_names("import os", &PYTHON), vec!["import"]);
+        assert_eq!(
+            script_names("df = pd.read_csv('a.csv')", &PYTHON),
+            vec!["pd.read_csv"]
+        );
+        assert_eq!(script_names("print(x)", &PYTHON), vec!["print"]);
+        assert_eq!(script_names("total += len(xs)", &PYTHON), vec!["len"]);
+        assert_eq!(script_names("x == 1", &PYTHON), vec!["x"]);
+        assert_eq!(script_names("x <= 1", &PYTHON), vec!["x"]);
+        assert_eq!(script_names("n := next(it)", &PYTHON), vec!["next"]);
+        assert_eq!(script_names("for x in xs:", &PYTHON), vec!["for"]);
+        assert_eq!(script_names("else:", &PYTHON), vec!["else"]);
+        assert_eq!(
+            script_names("async def f():\n    await g()", &PYTHON),
+            vec!["def", "g"]
+        );
+        assert!(script_names("x = 5", &PYTHON).is_empty());
+        assert!(script_names("# note", &PYTHON).is_empty());
+        assert!(script_names(")", &PYTHON).is_empty());
+    }
+
+    #[test]
+    fn test_javascript_statement_heads() {
+        assert_eq!(
+            script_names("const fs = require('fs')", &JAVASCRIPT),
+            vec!["require"]
+        );
+        assert_eq!(
+            script_names("console.log(x)", &JAVASCRIPT),
+            vec!["console.log"]
+        );
+        assert_eq!(
+            script_names("await fetch(url)", &JAVASCRIPT),
+            vec!["fetch"]
+        );
+        assert_eq!(script_names("new Date()", &JAVASCRIPT), vec!["Date"]);
+        assert_eq!(script_names(".editor", &JAVASCRIPT), vec![".editor"]);
+        assert_eq!(
+            script_names("function f() {}", &JAVASCRIPT),
+            vec!["function"]
+        );
+        assert_eq!(script_names("a ??= b()", &JAVASCRIPT), vec!["b"]);
+        assert_eq!(script_names("x => x", &JAVASCRIPT), vec!["x"]);
+    }
+
+    #[test]
+    fn test_ruby_statement_heads() {
+        assert_eq!(script_names("puts 'hi'", &RUBY), vec!["puts"]);
+        assert_eq!(
+            script_names("@user = User.find(1)", &RUBY),
+            vec!["User.find"]
+        );
+        assert_eq!(script_names("xs.empty?", &RUBY), vec!["xs.empty?"]);
+        assert_eq!(script_names("a != b", &RUBY), vec!["a"]);
+        assert_eq!(script_names("File::read('x')", &RUBY), vec!["File::read"]);
+        assert_eq!(script_names("def f\n  g\nend", &RUBY), vec!["def", "g"]);
+    }
+}
@@ -0,0 +1,457 @@
+//! Time window parsing for restricting history entries by timestamp.
+
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime,
+    TimeDelta, TimeZone, Utc,
+};
+
+/// Which end of a time window a bound describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Inclusive start of the window (`--since`)
+    Since,
+    /// Exclusive end of the window (`--until`)
+    Until,
+}
+
+/// How entries without a timestamp are treated when a time window is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UntimedPolicy {
+    /// Keep entries without a timestamp
+    Include,
+    /// Drop entries without a timestamp
+    Exclude,
+    /// Fail if any entry has no timestamp
+    #[default]
+    Error,
+}
+
+impl UntimedPolicy {
+    /// Parse from string (for CLI argument)
+    #[inline]
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("include") {
+            Some(Self::Include)
+        } else if s.eq_ignore_ascii_case("exclude") {
+            Some(Self::Exclude)
+        } else if s.eq_ignore_ascii_case("error") {
+            Some(Self::Error)
+        } else {
+            None
+        }
+    }
+}
+
+/// Time zone used to place timestamps on the calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Zone {
+    /// The system's local time zone
+    #[default]
+    Local,
+    /// Coordinated Universal Time, for reproducible output
+    Utc,
+}
+
+impl Zone {
+    /// Wall-clock date and time of a Unix timestamp in this zone
+    pub fn datetime(
+        &self,
+        timestamp: i64,
+    ) -> Option<NaiveDateTime> {
+        let utc = DateTime::from_timestamp(timestamp, 0)?;
+        Some(match self {
+            Self::Local => utc.with_timezone(&Local).naive_local(),
+            Self::Utc => utc.naive_utc(),
+        })
+    }
+
+    /// Parse a `--since`/`--until` value against the current time in this
+    /// zone (see [`parse_time_bound`])
+    pub fn parse_time_bound(
+        &self,
+        value: &str,
+        bound: Bound,
+    ) -> Result<i64, String> {
+        match self {
+            Self::Local => parse_time_bound(value, bound, &Local::now()),
+            Self::Utc => parse_time_bound(value, bound, &Utc::now()),
+        }
+    }
+
+    /// Short name used in structured output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Utc => "utc",
+        }
+    }
+}
+
+/// Calendar period used to bucket entries over time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Period {
+    Day,
+    /// ISO week, starting on Monday
+    #[default]
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Parse from string (for CLI argument)
+    #[inline]
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.eq_ignore_ascii_case("day") {
+            Some(Self::Day)
+        } else if s.eq_ignore_ascii_case("week") {
+            Some(Self::Week)
+        } else if s.eq_ignore_ascii_case("month") {
+            Some(Self::Month)
+        } else {
+            None
+        }
+    }
+
+    /// First day of the period containing `date`
+    pub fn start(
+        &self,
+        date: NaiveDate,
+    ) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date
+                .checked_sub_days(Days::new(u64::from(
+                    date.weekday().num_days_from_monday(),
+                )))
+                .unwrap_or(date),
+            Self::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// First day of the period after the one starting at `start`
+    pub fn next(
+        &self,
+        start: NaiveDate,
+    ) -> Option<NaiveDate> {
+        match self {
+            Self::Day => start.checked_add_days(Days::new(1)),
+            Self::Week => start.checked_add_days(Days::new(7)),
+            Self::Month => start.checked_add_months(Months::new(1)),
+        }
+    }
+
+    /// First day of the period before the one starting at `start`
+    pub fn previous(
+        &self,
+        start: NaiveDate,
+    ) -> Option<NaiveDate> {
+        match self {
+            Self::Day => start.checked_sub_days(Days::new(1)),
+            Self::Week => start.checked_sub_days(Days::new(7)),
+            Self::Month => start.checked_sub_months(Months::new(1)),
+        }
+    }
+
+    /// Label of the period starting at `start`
+    pub fn label(
+        &self,
+        start: NaiveDate,
+    ) -> String {
+        match self {
+            Self::Day | Self::Week => start.format("%Y-%m-%d").to_string(),
+            Self::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// Half-open time window `[since, until)` in seconds since the Unix epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeRange {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl TimeRange {
+    /// Check whether the window restricts anything at all
+    #[inline]
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Check whether a timestamp falls inside the window
+    #[inline]
+    pub fn contains(
+        &self,
+        timestamp: i64,
+    ) -> bool {
+        self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp < until)
+    }
+}
+
+/// Parse a `--since`/`--until` value into a Unix timestamp.
+///
+/// Accepted forms:
+/// - `YYYY-MM-DD`: start of that day (`Since`) or end of it (`Until`)
+/// - `today`, `yesterday`: same as the corresponding date
+/// - `now`
+/// - `<N>m`, `<N>h`, `<N>d`, `<N>w`, `<N>mo`, `<N>y`: minutes, hours,
+///   days, weeks, months or years before `now`
+pub fn parse_time_bound<Tz: TimeZone>(
+    value: &str,
+    bound: Bound,
+    now: &DateTime<Tz>,
+) -> Result<i64, String> {
+    let value = value.trim();
+    let invalid = || {
+        format!(
+            "Invalid time '{}'. Use YYYY-MM-DD, today, yesterday, now, or a relative value like 30d",
+            value
+        )
+    };
+
+    let today = now.date_naive();
+    let date = match value.to_ascii_lowercase().as_str() {
+        "now" => return Ok(now.timestamp()),
+        "today" => Some(today),
+        "yesterday" => today.checked_sub_days(Days::new(1)),
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+    };
+    if let Some(date) = date {
+        let date = match bound {
+            Bound::Since => date,
+            Bound::Until => {
+                date.checked_add_days(Days::new(1)).ok_or_else(invalid)?
+            }
+        };
+        return start_of_day(date, &now.timezone()).ok_or_else(invalid);
+    }
+
+    let split =
+        value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u32 = amount.parse().map_err(|_| invalid())?;
+    let start = match unit.to_ascii_lowercase().as_str() {
+        "m" => TimeDelta::try_minutes(i64::from(amount))
+            .and_then(|minutes| now.clone().checked_sub_signed(minutes)),
+        "h" => TimeDelta::try_hours(i64::from(amount))
+            .and_then(|hours| now.clone().checked_sub_signed(hours)),
+        "d" => now.clone().checked_sub_days(Days::new(u64::from(amount))),
+        "w" => now.clone().checked_sub_days(Days::new(u64::from(amount) * 7)),
+        "mo" => now.clone().checked_sub_months(Months::new(amount)),
+        "y" => amount.checked_mul(12).and_then(|months| {
+            now.clone().checked_sub_months(Months::new(months))
+        }),
+        _ => None,
+    };
+    start.map(|time| time.timestamp()).ok_or_else(invalid)
+}
+
+/// First instant of `date` in `tz`, skipping over a DST gap at midnight.
+fn start_of_day<Tz: TimeZone>(
+    date: NaiveDate,
+    tz: &Tz,
+) -> Option<i64> {
+    (0..3).find_map(|hour| {
+        let local = date.and_hms_opt(hour, 0, 0)?;
+        tz.from_local_datetime(&local).earliest().map(|time| time.timestamp())
+    })
+}
+
+/// Format a number of seconds using its two most significant units,
+/// e.g. `38s`, `4m 12s`, `14h 3m` or `2d 5h`.
+pub fn format_duration(seconds: u64) -> String {
+    const UNITS: [(u64, &str); 4] =
+        [(86_400, "d"), (3_600, "h"), (60, "m"), (1, "s")];
+
+    let Some(first) = UNITS.iter().position(|(size, _)| seconds >= *size)
+    else {
+        return "0s".to_string();
+    };
+    let (size, unit) = UNITS[first];
+    let mut formatted = format!("{}{}", seconds / size, unit);
+    if let Some(&(next_size, next_unit)) = UNITS.get(first + 1) {
+        let rest = seconds % size / next_size;
+        if rest > 0 {
+            formatted.push_str(&format!(" {}{}", rest, next_unit));
+        }
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Timelike, Utc};
+
+    fn now() -> DateTime<Utc> {
+        // 2026-03-15 12:30:00 UTC
+        Utc.with_ymd_and_hms(2026, 3, 15, 12, 30, 0).unwrap()
+    }
+
+    fn ymd(
+        year: i32,
+        month: u32,
+        day: u32,
+    ) -> i64 {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap().timestamp()
+    }
+
+    #[test]
+    fn test_parse_date_bounds() {
+        assert_eq!(
+            parse_time_bound("2026-01-01", Bound::Since, &now()),
+            Ok(ymd(2026, 1, 1))
+        );
+        assert_eq!(
+            parse_time_bound("2026-01-31", Bound::Until, &now()),
+            Ok(ymd(2026, 2, 1))
+        );
+    }
+
+    #[test]
+    fn test_parse_named_days() {
+        assert_eq!(
+            parse_time_bound("today", Bound::Since, &now()),
+            Ok(ymd(2026, 3, 15))
+        );
+        assert_eq!(
+            parse_time_bound("yesterday", Bound::Until, &now()),
+            Ok(ymd(2026, 3, 15))
+        );
+        assert_eq!(
+            parse_time_bound("Yesterday", Bound::Since, &now()),
+            Ok(ymd(2026, 3, 14))
+        );
+        assert_eq!(
+            parse_time_bound("now", Bound::Until, &now()),
+            Ok(now().timestamp())
+        );
+    }
+
+    #[test]
+    fn test_parse_relative_bounds() {
+        let now_ts = now().timestamp();
+        assert_eq!(
+            parse_time_bound("30d", Bound::Since, &now()),
+            Ok(now_ts - 30 * 86_400)
+        );
+        assert_eq!(
+            parse_time_bound("2w", Bound::Since, &now()),
+            Ok(now_ts - 14 * 86_400)
+        );
+        assert_eq!(
+            parse_time_bound("6h", Bound::Since, &now()),
+            Ok(now_ts - 6 * 3_600)
+        );
+        assert_eq!(
+            parse_time_bound("90m", Bound::Since, &now()),
+            Ok(now_ts - 90 * 60)
+        );
+        assert_eq!(
+            parse_time_bound("1mo", Bound::Since, &now()),
+            Ok(now_ts - 28 * 86_400)
+        );
+        assert_eq!(
+            parse_time_bound("1y", Bound::Since, &now()),
+            Ok(now_ts - 365 * 86_400)
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_bounds() {
+        assert!(parse_time_bound("", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("30", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("d", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("30x", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("3mon", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("2026-13-01", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("-3d", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("4294967295h", Bound::Since, &now()).is_err());
+        assert!(parse_time_bound("4294967295d", Bound::Since, &now()).is_err());
+    }
+
+    #[test]
+    fn test_time_range_contains() {
+        let range = TimeRange { since: Some(10), until: Some(20) };
+        assert!(!range.contains(9));
+        assert!(range.contains(10));
+        assert!(range.contains(19));
+        assert!(!range.contains(20));
+        assert!(TimeRange::default().contains(i64::MIN));
+        assert!(TimeRange::default().is_unbounded());
+        assert!(!range.is_unbounded());
+    }
+
+    #[test]
+    fn test_untimed_policy_parse() {
+        assert_eq!(
+            UntimedPolicy::parse("include"),
+            Some(UntimedPolicy::Include)
+        );
+        assert_eq!(
+            UntimedPolicy::parse("EXCLUDE"),
+            Some(UntimedPolicy::Exclude)
+        );
+        assert_eq!(UntimedPolicy::parse("error"), Some(UntimedPolicy::Error));
+        assert_eq!(UntimedPolicy::parse("maybe"), None);
+    }
+
+    #[test]
+    fn test_zone_utc_datetime() {
+        // 2023-04-06 22:33:11 UTC, a Thursday
+        let datetime = Zone::Utc.datetime(1680820391).unwrap();
+        assert_eq!(datetime.weekday(), chrono::Weekday::Thu);
+        assert_eq!(datetime.hour(), 22);
+        assert_eq!(Zone::Utc.name(), "utc");
+        assert_eq!(Zone::Local.name(), "local");
+    }
+
+    #[test]
+    fn test_period_start_and_next() {
+        let date = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2026, 1, 12).unwrap();
+        let first = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+
+        assert_eq!(Period::Day.start(date), date);
+        assert_eq!(Period::Week.start(date), monday);
+        assert_eq!(Period::Week.start(monday), monday);
+        assert_eq!(Period::Month.start(date), first);
+
+        assert_eq!(
+            Period::Week.next(monday),
+            NaiveDate::from_ymd_opt(2026, 1, 19)
+        );
+        assert_eq!(
+            Period::Month.next(first),
+            NaiveDate::from_ymd_opt(2026, 2, 1)
+        );
+        assert_eq!(
+            Period::Month.previous(first),
+            NaiveDate::from_ymd_opt(2025, 12, 1)
+        );
+        assert_eq!(
+            Period::Week.previous(monday),
+            NaiveDate::from_ymd_opt(2026, 1, 5)
+        );
+    }
+
+    #[test]
+    fn test_period_label_and_parse() {
+        let date = NaiveDate::from_ymd_opt(2026, 1, 12).unwrap();
+        assert_eq!(Period::Week.label(date), "2026-01-12");
+        assert_eq!(Period::Month.label(date), "2026-01");
+        assert_eq!(Period::parse("Month"), Some(Period::Month));
+        assert_eq!(Period::parse("fortnight"), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(38), "38s");
+        assert_eq!(format_duration(252), "4m 12s");
+        assert_eq!(format_duration(3_600), "1h");
+        assert_eq!(format_duration(50_580), "14h 3m");
+        assert_eq!(format_duration(190_800), "2d 5h");
+    }
+}
@@ -65,6 +65,17 @@ mod help_flag {
         assert!(stdout.contains("-o <FMT>"));
         assert!(stdout.contains("--color"));
         assert!(stdout.contains("--config"));
+        assert!(stdout.contains("--since <WHEN>"));
+        assert!(stdout.contains("--until <WHEN>"));
+        assert!(stdout.contains("--untimed <POL>"));
+        assert!(stdout.contains("--heatmap [CMD]"));
+        assert!(stdout.contains("--trend [CMD]"));
+        assert!(stdout.contains("--period <P>"));
+        assert!(stdout.contains("--utc"));
+        assert!(stdout.contains("--sparkline"));
+        assert!(stdout.contains("--durations"));
+        assert!(stdout.contains("--paths"));
+        assert!(stdout.contains("--path-prefix <DIR>"));
     }
 
     #[test]
@@ -130,6 +141,124 @@ mod file_flag {
     }
 }
 
+mod multiple_inputs {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        fixtures_path().join(name).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_repeated_file_flag_merges_counts() {
+        let bash = fixture("bash_history");
+        let fish = fixture("fish_history");
+        let output =
+            run_histop(&["-f", &bash, "-f", &fish, "-o", "csv", "-c", "1"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(stdout, "command,count,percentage\ngit,12,100.00\n");
+    }
+
+    #[test]
+    fn test_several_positional_files() {
+        let bash = fixture("bash_history");
+        let fish = fixture("fish_history");
+        let output = run_histop(&[&bash, &fish, "-o", "csv", "-c", "1"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("git,12,"));
+    }
+
+    #[test]
+    fn test_glob_pattern_expands_to_files() {
+        let pattern = fixture("[bf]*sh_history");
+        let output = run_histop(&["-f", &pattern, "--by-source", "-o", "csv"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.starts_with("command,count,percentage,shell,fish\n"));
+    }
+
+    #[test]
+    fn test_glob_pattern_without_matches() {
+        let pattern = fixture("missing_*_history");
+        let output = run_histop(&["-f", &pattern]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("No history files match"));
+    }
+
+    #[test]
+    fn test_by_source_csv_columns() {
+        let bash = fixture("bash_history");
+        let fish = fixture("fish_history");
+        let output = run_histop(&[
+            "-f",
+            &bash,
+            "-f",
+            &fish,
+            "--by-source",
+            "-o",
+            "csv",
+            "-c",
+            "2",
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            "command,count,percentage,shell,fish\ngit,12,54.55,6,6\nls,10,45.45,5,5\n"
+        );
+    }
+
+    #[test]
+    fn test_by_source_json_breakdown() {
+        let bash = fixture("bash_history");
+        let fish = fixture("fish_history");
+        let output =
+            run_histop(&[&bash, &fish, "--by-source", "-o", "json", "-c", "1"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains(
+            "\"sources\": {\n      \"shell\": 6,\n      \"fish\": 6\n    }"
+        ));
+    }
+
+    #[test]
+    fn test_by_source_labels_same_format_by_path() {
+        let bash = fixture("bash_history");
+        let zsh = fixture("zsh_history");
+        let output = run_histop(&[
+            &bash,
+            &zsh,
+            "--by-source",
+            "--color",
+            "never",
+            "-c",
+            "1",
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains(&format!("{} 6  {} 6", bash, zsh)));
+    }
+
+    #[test]
+    fn test_by_source_rejects_other_reports() {
+        let bash = fixture("bash_history");
+        let output = run_histop(&[&bash, "--by-source", "--durations"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("--by-source only applies"));
+    }
+}
+
 mod count_flag {
     use super::*;
 
@@ -706,6 +835,1514 @@ mod config_flag {
     }
 }
 
+mod time_range_flags {
+    use super::*;
+
+    #[test]
+    fn test_bash_timestamped_history_time_range() {
+        let path = fixtures_path().join("bash_timestamped_history");
+        let output = run_histop(&[
+            "--since",
+            "2026-01-06",
+            "--utc",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            "command,count,percentage\ncargo,1,50.00\ngit,1,50.00\n"
+        );
+    }
+
+    #[test]
+    fn test_since_includes_timestamped_entries() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "-f",
+            path.to_str().unwrap(),
+            "--since",
+            "2023-01-01",
+            "-o",
+            "csv",
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("git,6,"));
+    }
+
+    #[test]
+    fn test_until_excludes_later_entries() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "-f",
+            path.to_str().unwrap(),
+            "--until",
+            "2023-01-01",
+            "-o",
+            "csv",
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(stdout, "command,count,percentage\n");
+    }
+
+    #[test]
+    fn test_untimed_entries_error_by_default() {
+        let path = fixtures_path().join("bash_history");
+        let output =
+            run_histop(&["-f", path.to_str().unwrap(), "--since", "30d"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("have no timestamp"));
+    }
+
+    #[test]
+    fn test_untimed_include_and_exclude() {
+        let path = fixtures_path().join("bash_history");
+        let include = run_histop(&[
+            "-f",
+            path.to_str().unwrap(),
+            "--since",
+            "30d",
+            "--untimed",
+            "include",
+            "-o",
+            "csv",
+        ]);
+        let exclude = run_histop(&[
+            "-f",
+            path.to_str().unwrap(),
+            "--since",
+            "30d",
+            "--untimed",
+            "exclude",
+            "-o",
+            "csv",
+        ]);
+
+        assert!(include.status.success());
+        assert!(String::from_utf8_lossy(&include.stdout).contains("git,6,"));
+        assert!(exclude.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&exclude.stdout),
+            "command,count,percentage\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_time_value() {
+        let path = fixtures_path().join("zsh_history");
+        let output =
+            run_histop(&["-f", path.to_str().unwrap(), "--since", "soon"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("Invalid time"));
+    }
+
+    #[test]
+    fn test_utc_moves_the_since_cutoff() {
+        // 23:30 UTC on 2026-01-05 is already 01:30 on 2026-01-06 at UTC+2
+        let path = unique_temp_path("histop_since_utc", "_zsh_history");
+        std::fs::write(&path, ": 1767655800:0;git status\n").unwrap();
+        let run = |utc: bool| {
+            let mut args =
+                vec!["-f", path.to_str().unwrap(), "--since", "2026-01-06"];
+            if utc {
+                args.push("--utc");
+            }
+            args.extend(["-o", "csv"]);
+            Command::new(histop_bin())
+                .args(&args)
+                .env("TZ", "UTC-2")
+                .output()
+                .expect("Failed to execute histop")
+        };
+        let local = run(false);
+        let utc = run(true);
+        std::fs::remove_file(&path).ok();
+
+        assert!(local.status.success());
+        assert!(String::from_utf8_lossy(&local.stdout).contains("git,1,"));
+        assert!(utc.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&utc.stdout),
+            "command,count,percentage\n"
+        );
+    }
+
+    #[test]
+    fn test_since_after_until_rejected() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "-f",
+            path.to_str().unwrap(),
+            "--since",
+            "2024-01-01",
+            "--until",
+            "2023-01-01",
+        ]);
+
+        assert!(!output.status.success());
+    }
+}
+
+mod heatmap_flag {
+    use super::*;
+
+    #[test]
+    fn test_heatmap_text_output() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "-f",
+            path.to_str().unwrap(),
+            "--heatmap",
+            "--utc",
+            "--color",
+            "never",
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert!(lines[0].contains("00 01 02"));
+        assert!(lines[4].starts_with("Thu"));
+        assert!(lines[4].contains("██"));
+    }
+
+    #[test]
+    fn test_heatmap_for_single_command_csv() {
+        let path = fixtures_path().join("fish_history");
+        for command in [&["--heatmap=git"][..], &["--heatmap", "git"]] {
+            let mut args = command.to_vec();
+            args.extend(["--utc", "-o", "csv", "-f", path.to_str().unwrap()]);
+            let output = run_histop(&args);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+
+            assert!(output.status.success());
+            assert!(stdout.starts_with("day,hour,count\n"));
+            assert!(stdout.contains("Thu,22,6\n"));
+            assert!(stdout.contains("Thu,21,0\n"));
+        }
+    }
+
+    #[test]
+    fn test_heatmap_positional_file() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "--heatmap",
+            path.to_str().unwrap(),
+            "--utc",
+            "-o",
+            "json",
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\"command\": null"));
+        assert!(stdout.contains("\"timezone\": \"utc\""));
+    }
+
+    #[test]
+    fn test_heatmap_empty_command_rejected() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&["--heatmap=", "-f", path.to_str().unwrap()]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("Missing value for --heatmap=CMD"));
+    }
+
+    #[test]
+    fn test_heatmap_requires_timestamps() {
+        let path = fixtures_path().join("bash_history");
+        let output = run_histop(&["-f", path.to_str().unwrap(), "--heatmap"]);
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("have no timestamp"));
+    }
+}
+
+mod trend_flag {
+    use super::*;
+    use std::io::Write;
+
+    fn write_spread_history() -> PathBuf {
+        let path = unique_temp_path("histop_trend", ".zsh_history");
+        let mut file = std::fs::File::create(&path).unwrap();
+        // 2026-01-05, 2026-01-06, 2026-03-02 (UTC noon)
+        writeln!(file, ": 1767614400:0;apt update").unwrap();
+        writeln!(file, ": 1767700800:0;apt upgrade").unwrap();
+        writeln!(file, ": 1767700800:0;nix build").unwrap();
+        writeln!(file, ": 1772452800:0;nix develop").unwrap();
+        writeln!(file, ": 1772452800:0;nix flake update").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_trend_csv_fills_empty_periods() {
+        let path = write_spread_history();
+        for command in [&["--trend=nix"][..], &["--trend", "nix"]] {
+            let mut args = command.to_vec();
+            args.extend(["--period", "month", "--utc", "-o", "csv", "-f"]);
+            args.push(path.to_str().unwrap());
+            let output = run_histop(&args);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+
+            assert!(output.status.success());
+            assert_eq!(
+                stdout,
+                "period,command,count\n2026-01,nix,1\n2026-02,nix,0\n2026-03,nix,2\n"
+            );
+        }
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_trend_top_commands_json() {
+        let path = write_spread_history();
+        let output = run_histop(&[
+            "--trend",
+            "--utc",
+            "-o",
+            "json",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\"period\": \"2026-01-05\""));
+        assert!(stdout.contains("\"command\": \"apt\""));
+        assert!(stdout.contains("\"command\": \"nix\""));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_trend_text_draws_one_bar_per_period() {
+        let path = write_spread_history();
+        let output = run_histop(&[
+            "--trend",
+            "-c",
+            "1",
+            "--period",
+            "month",
+            "--utc",
+            "--color",
+            "never",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "nix");
+        assert!(lines[1].ends_with("2026-01"));
+        assert!(lines[3].contains("│█████████████████████████│"));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_sparkline_column_in_ranking() {
+        let path = write_spread_history();
+        let output = run_histop(&[
+            "--sparkline",
+            "3",
+            "--period",
+            "month",
+            "--utc",
+            "--color",
+            "never",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert!(lines[0].starts_with("3   ▄ █   │"));
+        assert!(lines[0].ends_with("nix"));
+        assert!(lines[1].starts_with("2   █     │"));
+        assert!(lines[1].ends_with("apt"));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_sparkline_without_timestamps_omits_column() {
+        let path = fixtures_path().join("bash_history");
+        let path = path.to_str().unwrap();
+        let with_sparkline =
+            run_histop(&["--sparkline", "4", "--color", "never", "-f", path]);
+        let without = run_histop(&["--color", "never", "-f", path]);
+
+        assert!(with_sparkline.status.success());
+        assert_eq!(with_sparkline.stdout, without.stdout);
+    }
+
+    #[test]
+    fn test_sparkline_rejects_huge_period_counts() {
+        let path = fixtures_path().join("zsh_history");
+        let path = path.to_str().unwrap();
+        let output =
+            run_histop(&["--sparkline", "18446744073709551615", "-f", path]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("must be at most 365"));
+        assert!(
+            run_histop(&["--sparkline", "365", "-f", path]).status.success()
+        );
+    }
+
+    #[test]
+    fn test_trend_conflicts_with_heatmap() {
+        let path = fixtures_path().join("zsh_history");
+        let output =
+            run_histop(&["--trend", "--heatmap", "-f", path.to_str().unwrap()]);
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Conflicting report modes"));
+    }
+
+    #[test]
+    fn test_invalid_period() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "--trend",
+            "--period",
+            "year",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+
+        assert!(!output.status.success());
+    }
+}
+
+mod durations_flag {
+    use super::*;
+    use std::io::Write;
+
+    fn write_timed_history() -> PathBuf {
+        let path = unique_temp_path("histop_durations", ".zsh_history");
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, ": 1767614400:40;cargo build").unwrap();
+        writeln!(file, ": 1767614500:30;cargo test | tee log").unwrap();
+        writeln!(file, ": 1767614600:600;cargo build --release").unwrap();
+        writeln!(file, ": 1767614700:0;ls").unwrap();
+        writeln!(file, ": 1767614800:1;ls").unwrap();
+        writeln!(file, ": 1767614900:90;make").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_durations_text_ranks_by_total_time() {
+        let path = write_timed_history();
+        let output = run_histop(&[
+            "--durations",
+            "--color",
+            "never",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("11m 10s   median 40s      │"));
+        assert!(lines[0].ends_with("cargo"));
+        assert!(lines[1].starts_with(" 1m 30s   median 1m 30s   │"));
+        assert!(lines[1].ends_with("make"));
+        assert!(lines[2].starts_with("     1s   median 0s       │"));
+        assert!(lines[2].ends_with("ls"));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_durations_all_zero_still_lists_rows() {
+        let path = unique_temp_path("histop_durations_zero", ".zsh_history");
+        std::fs::write(&path, ": 1767614400:0;ls\n: 1767614500:0;cd\n")
+            .unwrap();
+        let output = run_histop(&[
+            "--durations",
+            "--color",
+            "never",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(stdout.lines().count(), 2);
+        assert!(stdout.lines().all(|line| line.contains("0.00%")));
+
+        std::fs::remove_file(path).ok();
+    }
+
+    /// Durations whose sum overflows, as corrupt elapsed fields give
+    fn run_with_corrupt_elapsed_fields(format: &str) -> std::process::Output {
+        let path = unique_temp_path("histop_durations_huge", ".zsh_history");
+        std::fs::write(
+            &path,
+            ": 1767614400:18446744073709551615;make\n\
+             : 1767614500:18446744073709551615;make\n\
+             : 1767614600:5;ls\n",
+        )
+        .unwrap();
+        let output = run_histop(&[
+            "--durations",
+            "-o",
+            format,
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        std::fs::remove_file(path).ok();
+        output
+    }
+
+    #[test]
+    fn test_durations_survive_corrupt_elapsed_fields() {
+        let output = run_with_corrupt_elapsed_fields("text");
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("make"));
+    }
+
+    #[test]
+    fn test_durations_json_survives_corrupt_elapsed_fields() {
+        let output = run_with_corrupt_elapsed_fields("json");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\"total_seconds\": 18446744073709551615"));
+    }
+
+    #[test]
+    fn test_durations_csv_survives_corrupt_elapsed_fields() {
+        let output = run_with_corrupt_elapsed_fields("csv");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("make,2,18446744073709551615,"));
+    }
+
+    #[test]
+    fn test_durations_csv() {
+        let path = write_timed_history();
+        let output = run_histop(&[
+            "--durations",
+            "-c",
+            "1",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            "command,count,total_seconds,median_seconds,percentage\ncargo,3,670,40,100.00\n"
+        );
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_durations_without_duration_data() {
+        let path = fixtures_path().join("bash_history");
+        let output = run_histop(&["--durations", "-f", path.to_str().unwrap()]);
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("No command durations recorded"));
+    }
+}
+
+mod paths_flag {
+    use super::*;
+    use std::io::Write;
+
+    fn write_fish_history() -> PathBuf {
+        let path = unique_temp_path("histop_paths", ".fish_history");
+        let mut file = std::fs::File::create(&path).unwrap();
+        write!(
+            file,
+            "- cmd: vim /nonexistent/work/foo/src/main.rs\n  when: 1767614400\n  paths:\n    - /nonexistent/work/foo/src/main.rs\n\
+             - cmd: cargo build\n  when: 1767614500\n  paths:\n    - /nonexistent/work/foo/\n\
+             - cmd: vim /nonexistent/work/foo/src/lib.rs\n  when: 1767614600\n  paths:\n    - /nonexistent/work/foo/src/lib.rs\n\
+             - cmd: cat /nonexistent/work/bar/notes\n  when: 1767614700\n  paths:\n    - /nonexistent/work/bar/notes\n\
+             - cmd: git status\n  when: 1767614800\n"
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_paths_csv_ranks_paths_and_directories() {
+        let path = write_fish_history();
+        let output =
+            run_histop(&["--paths", "-o", "csv", "-f", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines[0], "kind,path,count");
+        assert!(lines.contains(&"path,/nonexistent/work/foo/src/main.rs,1"));
+        assert!(lines.contains(&"directory,/nonexistent/work/foo/src,2"));
+        assert!(lines.contains(&"directory,/nonexistent/work/foo,1"));
+        assert_eq!(
+            lines.iter().position(|line| line.starts_with("directory,")),
+            Some(5)
+        );
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_path_prefix_restricts_ranking() {
+        let path = write_fish_history();
+        let output = run_histop(&[
+            "--path-prefix",
+            "/nonexistent/work/foo/",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            "command,count,percentage\nvim,2,66.67\ncargo,1,33.33\n"
+        );
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_paths_without_path_data() {
+        let path = fixtures_path().join("bash_history");
+        let output = run_histop(&["--paths", "-f", path.to_str().unwrap()]);
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("No paths recorded"));
+    }
+}
+
+mod discover_flag {
+    use super::*;
+    use std::fs;
+
+    /// A home directory holding bash history and a fish history in the
+    /// session named by `fish_history`
+    fn make_home() -> PathBuf {
+        let home = unique_temp_path("histop_discover", "");
+        let fish_dir = home.join(".local/share/fish");
+        fs::create_dir_all(&fish_dir).unwrap();
+        fs::copy(
+            fixtures_path().join("bash_history"),
+            home.join(".bash_history"),
+        )
+        .unwrap();
+        fs::copy(
+            fixtures_path().join("fish_history"),
+            fish_dir.join("work_history"),
+        )
+        .unwrap();
+        home
+    }
+
+    fn run_in_home(
+        home: &PathBuf,
+        args: &[&str],
+    ) -> std::process::Output {
+        Command::new(histop_bin())
+            .args(args)
+            .env_clear()
+            .env("HOME", home)
+            .env("fish_history", "work")
+            .output()
+            .expect("Failed to execute histop")
+    }
+
+    #[test]
+    fn test_discover_lists_formats_entries_and_dates() {
+        let home = make_home();
+        let output = run_in_home(&home, &["--discover", "-o", "csv", "--utc"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let home_str = home.to_str().unwrap();
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            format!(
+                "path,format,entries,first,last\n\
+                 {home}/.local/share/fish/work_history,fish,21,2023-04-06,2023-04-06\n\
+                 {home}/.bash_history,shell,21,,\n",
+                home = home_str
+            )
+        );
+    }
+
+    #[test]
+    fn test_discover_text_table() {
+        let home = make_home();
+        let output = run_in_home(&home, &["--discover", "--color", "never"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert!(lines[0].starts_with("FORMAT  ENTRIES  FIRST"));
+        assert!(lines[2].starts_with("shell        21  -"));
+    }
+
+    #[test]
+    fn test_discover_with_files_summarises_them() {
+        let path = fixtures_path().join("zsh_histdb.db");
+        let output =
+            run_histop(&["--discover", "-o", "json", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\"format\": \"zsh-histdb\""));
+        assert!(stdout.contains("\"entries\": 6"));
+    }
+
+    #[test]
+    fn test_all_histories_merges_discovered_files() {
+        let home = make_home();
+        let output = run_in_home(
+            &home,
+            &["--all-histories", "--by-source", "-o", "csv", "-c", "1"],
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            "command,count,percentage,fish,shell\ngit,12,100.00,6,6\n"
+        );
+    }
+
+    #[test]
+    fn test_all_histories_conflicts_with_file() {
+        let path = fixtures_path().join("bash_history");
+        let output = run_histop(&["--all-histories", path.to_str().unwrap()]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("--all-histories"));
+    }
+
+    #[test]
+    fn test_discover_skips_unreadable_history() {
+        let home = make_home();
+        // Compressed by its magic bytes, but not valid gzip
+        fs::write(home.join(".zsh_history"), [0x1f, 0x8b, 0, 0]).unwrap();
+        let output = run_in_home(&home, &["--discover", "-o", "csv"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        assert!(stderr.contains("Warning: skipping"));
+        assert!(stderr.contains(".zsh_history"));
+        assert_eq!(stdout.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_all_histories_skips_unreadable_history() {
+        let home = make_home();
+        fs::write(home.join(".zsh_history"), [0x1f, 0x8b, 0, 0]).unwrap();
+        let output = run_in_home(&home, &["--all-histories", "-o", "csv"]);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("git,12,"));
+    }
+
+    #[test]
+    fn test_discover_histfile_from_bashrc() {
+        let home = unique_temp_path("histop_discover_bashrc", "");
+        let state = home.join(".local/state/bash");
+        fs::create_dir_all(&state).unwrap();
+        fs::write(
+            home.join(".bashrc"),
+            "export HISTFILE=\"$HOME/.local/state/bash/history\"\n",
+        )
+        .unwrap();
+        fs::write(state.join("history"), "make\nmake\nls\n").unwrap();
+        let output = run_in_home(&home, &["--discover", "-o", "csv"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        assert!(stdout.contains(".local/state/bash/history,shell,3,"));
+    }
+
+    #[test]
+    fn test_discover_without_histories() {
+        let home = unique_temp_path("histop_discover_empty", "");
+        fs::create_dir_all(&home).unwrap();
+        let output = run_in_home(&home, &["--discover"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("No history files found"));
+    }
+}
+
+mod histfile_detection {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_histfile_from_bashrc() {
+        let home = unique_temp_path("histop_bashrc", "");
+        let state = home.join(".local/state/bash");
+        fs::create_dir_all(&state).unwrap();
+        fs::write(
+            home.join(".bashrc"),
+            "export HISTFILE=\"${XDG_STATE_HOME:-$HOME/.local/state}/bash/history\"\n",
+        )
+        .unwrap();
+        fs::write(state.join("history"), "make\nmake\nls\n").unwrap();
+
+        let output = Command::new(histop_bin())
+            .args(["-o", "csv"])
+            .env_clear()
+            .env("HOME", &home)
+            .env("SHELL", "/bin/bash")
+            .output()
+            .expect("Failed to execute histop");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        assert!(stdout.contains("make,2,"));
+    }
+
+    #[test]
+    fn test_unrelated_history_directory_is_skipped() {
+        let home = unique_temp_path("histop_history_dir", "");
+        fs::create_dir_all(home.join(".history/notes")).unwrap();
+        fs::write(home.join(".history/notes/todo.txt"), "buy milk\n").unwrap();
+        fs::write(home.join(".sh_history"), "make\nmake\nls\n").unwrap();
+
+        let output = Command::new(histop_bin())
+            .args(["-o", "csv"])
+            .env_clear()
+            .env("HOME", &home)
+            .output()
+            .expect("Failed to execute histop");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_dir_all(&home).ok();
+
+        assert!(output.status.success());
+        assert!(stdout.contains("make,2,"));
+    }
+}
+
+mod format_flag {
+    use super::*;
+    use std::fs;
+
+    const FISH_HISTORY: &str = "- cmd: git status\n  when: 1700000000\n- cmd: git log\n  when: 1700000001\n";
+
+    #[test]
+    fn test_format_overrides_detection() {
+        let path = unique_temp_path("histop_format_fish", ".txt");
+        fs::write(&path, FISH_HISTORY).unwrap();
+
+        let output = run_histop(&[
+            "--format",
+            "fish",
+            "-o",
+            "csv",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_file(&path).ok();
+
+        assert!(output.status.success());
+        assert_eq!(stdout, "command,count,percentage\ngit,2,100.00\n");
+    }
+
+    #[test]
+    fn test_format_raw_reads_lines_as_commands() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "--format",
+            "raw",
+            "-o",
+            "csv",
+            "-a",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\n:,"), "got: {}", stdout);
+    }
+
+    #[test]
+    fn test_format_conflicts_with_no_hist() {
+        let path = fixtures_path().join("zsh_history");
+        let output =
+            run_histop(&["--format", "zsh", "-nh", path.to_str().unwrap()]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("do not pass --format"));
+    }
+
+    #[test]
+    fn test_detect_only_reports_format_override() {
+        let path = fixtures_path().join("zsh_history");
+        let output = run_histop(&[
+            "--detect-only",
+            "--format",
+            "raw",
+            "-o",
+            "csv",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains(",raw,0,false,,format set by --format/config"));
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        let output = run_histop(&["--format", "elvish"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("Invalid history format: elvish"));
+        assert!(stderr.contains("nushell-sqlite"));
+    }
+
+    #[test]
+    fn test_format_from_config_file() {
+        let path = unique_temp_path("histop_format_config", ".txt");
+        let config = unique_temp_path("histop_format_config", ".toml");
+        fs::write(&path, FISH_HISTORY).unwrap();
+        fs::write(
+            &config,
+            format!("[formats]\n\"{}\" = \"fish\"\n", path.display()),
+        )
+        .unwrap();
+
+        let output = run_histop(&[
+            "--config",
+            config.to_str().unwrap(),
+            "-o",
+            "csv",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_file(&path).ok();
+        fs::remove_file(&config).ok();
+
+        assert!(output.status.success());
+        assert_eq!(stdout, "command,count,percentage\ngit,2,100.00\n");
+    }
+}
+
+mod detect_only_flag {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_detect_only_text() {
+        let path = fixtures_path().join("fish_history");
+        let output = run_histop(&["--detect-only", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("fish_history: fish\n"));
+        assert!(stdout.contains("lines inspected; scores: fish "));
+        assert!(!stdout.contains("ambiguous"));
+    }
+
+    #[test]
+    fn test_detect_only_flags_ambiguous_file() {
+        let path = unique_temp_path("histop_detect_ambiguous", "");
+        fs::write(&path, "ls\ngit\n- cmd: make\n  when: 1\npwd\ncd\nvim\n")
+            .unwrap();
+
+        let output = run_histop(&[
+            "--detect-only",
+            "-o",
+            "json",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_file(&path).ok();
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\"format\": \"fish\""));
+        assert!(stdout.contains("\"ambiguous\": true"));
+        assert!(stdout.contains("\"scores\": {\"fish\": 6, \"shell\": 5"));
+    }
+
+    #[test]
+    fn test_detect_only_decided_by_magic_bytes() {
+        let path = fixtures_path().join("ksh_history");
+        let output =
+            run_histop(&["--detect-only", "-o", "csv", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(
+            stdout.contains(
+                ",ksh,0,false,,file starts with the ksh magic bytes\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_detect_only_conflicts_with_other_reports() {
+        let output = run_histop(&["--detect-only", "--durations"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("Conflicting report modes"));
+    }
+}
+
+mod stream_input {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use std::process::Stdio;
+
+    /// Run histop with `input` piped to its stdin
+    fn run_histop_with_stdin(
+        args: &[&str],
+        input: &[u8],
+    ) -> std::process::Output {
+        let mut child = Command::new(histop_bin())
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to execute histop");
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        child.wait_with_output().expect("Failed waiting process")
+    }
+
+    #[test]
+    fn test_fish_history_from_stdin() {
+        let history = fs::read(fixtures_path().join("fish_history")).unwrap();
+        let output =
+            run_histop_with_stdin(&["-", "-o", "csv", "-c", "1"], &history);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(stdout, "command,count,percentage\ngit,6,100.00\n");
+    }
+
+    #[test]
+    fn test_zsh_timestamps_from_stdin() {
+        let history = fs::read(fixtures_path().join("zsh_history")).unwrap();
+        let output = run_histop_with_stdin(
+            &["-f", "-", "--since", "2023-04-06", "--utc", "-o", "csv"],
+            &history,
+        );
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success(), "{:?}", output);
+        assert!(stdout.contains("git,"));
+    }
+
+    #[test]
+    fn test_sqlite_history_from_stdin() {
+        let history =
+            fs::read(fixtures_path().join("nushell/history.sqlite3")).unwrap();
+        let output = run_histop_with_stdin(&["-", "-o", "csv"], &history);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("cargo,2,"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fifo_input_is_read_once_and_replayed() {
+        let fifo = unique_temp_path("histop_fifo", "");
+        let status = Command::new("mkfifo").arg(&fifo).status().unwrap();
+        assert!(status.success());
+
+        let writer = {
+            let fifo = fifo.clone();
+            std::thread::spawn(move || {
+                let history =
+                    fs::read(fixtures_path().join("fish_history")).unwrap();
+                fs::write(fifo, history).unwrap();
+            })
+        };
+        let output =
+            run_histop(&[fifo.to_str().unwrap(), "-o", "csv", "-c", "1"]);
+        writer.join().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_file(&fifo).ok();
+
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(stdout, "command,count,percentage\ngit,6,100.00\n");
+    }
+
+    #[test]
+    fn test_detect_only_from_stdin() {
+        let history = fs::read(fixtures_path().join("ksh_history")).unwrap();
+        let output = run_histop_with_stdin(&["--detect-only", "-"], &history);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.starts_with("-: ksh\n"));
+    }
+}
+
+mod concurrent_writers {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_reads_copy_while_zsh_lock_is_held() {
+        let dir = unique_temp_path("histop_zsh_lock", "");
+        fs::create_dir_all(&dir).unwrap();
+        let history = dir.join(".zsh_history");
+        fs::copy(fixtures_path().join("zsh_history"), &history).unwrap();
+        fs::write(dir.join(".zsh_history.LOCK"), "").unwrap();
+
+        let output =
+            run_histop(&["-o", "csv", "-c", "1", history.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(output.status.success());
+        assert!(stdout.starts_with("command,count,percentage\ngit,"));
+    }
+
+    #[test]
+    fn test_reads_copy_while_file_is_locked() {
+        let path = unique_temp_path("histop_fish_lock", "");
+        fs::copy(fixtures_path().join("fish_history"), &path).unwrap();
+        let writer = fs::File::options().append(true).open(&path).unwrap();
+        writer.lock().unwrap();
+
+        let output =
+            run_histop(&["-o", "csv", "-c", "1", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        writer.unlock().unwrap();
+        fs::remove_file(&path).ok();
+
+        assert!(output.status.success());
+        assert_eq!(stdout, "command,count,percentage\ngit,6,100.00\n");
+    }
+}
+
+mod compressed_history {
+    use super::*;
+    use std::fs;
+    use std::time::Duration;
+
+    fn fixture(name: &str) -> String {
+        fixtures_path()
+            .join("compressed")
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_gzip_history() {
+        let output =
+            run_histop(&["-o", "csv", "-c", "1", &fixture("zsh_history.gz")]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(stdout, "command,count,percentage\ngit,6,100.00\n");
+    }
+
+    #[test]
+    fn test_zstd_history_detected_by_content() {
+        let output = run_histop(&[
+            "--detect-only",
+            "-o",
+            "csv",
+            &fixture("fish_history.zst"),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("fish_history.zst,fish,"));
+    }
+
+    #[test]
+    fn test_gzip_sqlite_history() {
+        let output = run_histop(&["-o", "csv", &fixture("history.sqlite3.gz")]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("cargo,2,"));
+    }
+
+    #[test]
+    fn test_rotated_series_read_oldest_first() {
+        let dir = unique_temp_path("histop_rotated", "");
+        fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        let write = |name: &str, contents: &[u8], age_days: u64| {
+            let path = dir.join(name);
+            fs::write(&path, contents).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age_days * 86400))
+                .unwrap();
+            path.to_str().unwrap().to_string()
+        };
+        let archive = fs::read(fixture("zsh_history.gz")).unwrap();
+        let oldest = write("history.2.gz", &archive, 60);
+        let older = write("history.1", b"make\nmake\n", 30);
+        let current = write("history", b"make\n", 0);
+        write("history.new", b"ls\n", 0);
+
+        let output = run_histop(&[
+            "--rotated",
+            "--by-source",
+            "-o",
+            "csv",
+            "-c",
+            "1",
+            &current,
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            format!(
+                "command,count,percentage,{},{},{}\ngit,6,100.00,6,0,0\n",
+                oldest, older, current
+            )
+        );
+    }
+}
+
+mod nushell_history {
+    use super::*;
+
+    #[test]
+    fn test_nushell_sqlite_ranking() {
+        let path = fixtures_path().join("nushell/history.sqlite3");
+        let output = run_histop(&["-o", "csv", "-f", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("cargo,2,"));
+        assert!(stdout.contains("where,1,"));
+    }
+
+    #[test]
+    fn test_nushell_sqlite_path_prefix_uses_cwd() {
+        let path = fixtures_path().join("nushell/history.sqlite3");
+        let output = run_histop(&[
+            "--path-prefix",
+            "/home/user/work/foo",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            "command,count,percentage\ncargo,2,66.67\ngit,1,33.33\n"
+        );
+    }
+
+    #[test]
+    fn test_nushell_sqlite_durations() {
+        let path = fixtures_path().join("nushell/history.sqlite3");
+        let output = run_histop(&[
+            "--durations",
+            "-c",
+            "1",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\ncargo,2,99,49,"));
+    }
+
+    #[test]
+    fn test_nushell_plaintext_multiline_entry() {
+        let path = fixtures_path().join("nushell/history.txt");
+        let output = run_histop(&["-o", "csv", "-f", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("def,1,"));
+        assert!(stdout.contains("print,1,"));
+    }
+}
+
+mod repl_history {
+    use super::*;
+
+    fn ranking(name: &str) -> String {
+        let path = fixtures_path().join("repl").join(name);
+        let output =
+            run_histop(&["-a", "-o", "csv", "-f", path.to_str().unwrap()]);
+        assert!(output.status.success(), "{}", name);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    #[test]
+    fn test_psql_statement_types_and_meta_commands() {
+        let stdout = ranking(".psql_history");
+        assert!(stdout.starts_with("command,count,percentage\nSELECT,3,"));
+        assert!(stdout.contains("\nUPDATE,1,"));
+        assert!(stdout.contains("\nCOMMIT,1,"));
+        assert!(stdout.contains("\n\\d+,1,"));
+        assert!(!stdout.contains("select"));
+    }
+
+    #[test]
+    fn test_sqlite_dot_commands() {
+        let stdout = ranking(".sqlite_history");
+        assert!(stdout.contains("\nSELECT,2,"));
+        assert!(stdout.contains("\n.tables,1,"));
+        assert!(stdout.contains("\nPRAGMA,1,"));
+    }
+
+    #[test]
+    fn test_script_repl_statement_heads() {
+        let python = ranking(".python_history");
+        assert!(python.contains("\nimport,2,"));
+        assert!(python.contains("\nprint,2,"));
+        assert!(python.contains("\npd.read_csv,1,"));
+        assert!(!python.contains("\nx,"));
+
+        let node = ranking(".node_repl_history");
+        assert!(node.contains("\nrequire,1,"));
+        assert!(node.contains("\nconsole.log,1,"));
+        assert!(node.contains("\n.exit,1,"));
+
+        let irb = ranking(".irb_history");
+        assert!(irb.contains("\nputs,2,"));
+        assert!(irb.contains("\nUser.find,1,"));
+        assert!(!irb.contains("\nend,"));
+    }
+
+    #[test]
+    fn test_ignore_applies_to_statements() {
+        let path = fixtures_path().join("repl/.psql_history");
+        let output = run_histop(&[
+            "-i",
+            "SELECT",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(!stdout.contains("SELECT"));
+    }
+}
+
+mod ksh_history {
+    use super::*;
+
+    #[test]
+    fn test_binary_ksh_histories() {
+        for name in ["ksh_history", "mksh_history"] {
+            let path = fixtures_path().join(name);
+            let output =
+                run_histop(&["-o", "csv", "-f", path.to_str().unwrap()]);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+
+            assert!(output.status.success(), "{}", name);
+            assert!(stdout.contains("\ngit,2,"), "{}", name);
+            assert!(stdout.contains("\nls,2,"), "{}", name);
+            assert!(stdout.contains("\ngzip,1,"), "{}", name);
+        }
+    }
+}
+
+mod database_history {
+    use super::*;
+
+    #[test]
+    fn test_atuin_ranking() {
+        let path = fixtures_path().join("atuin_history.db");
+        let output = run_histop(&["-o", "csv", "-f", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\ncargo,2,"));
+        assert!(stdout.contains("\nhead,1,"));
+        assert!(!stdout.contains("\nrm,"));
+    }
+
+    #[test]
+    fn test_atuin_path_prefix_uses_cwd() {
+        let path = fixtures_path().join("atuin_history.db");
+        let output = run_histop(&[
+            "--path-prefix",
+            "/home/user/work/foo",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert_eq!(
+            stdout,
+            "command,count,percentage\ncargo,2,66.67\ngit,1,33.33\n"
+        );
+    }
+
+    #[test]
+    fn test_zsh_histdb_durations() {
+        let path = fixtures_path().join("zsh_histdb.db");
+        let output = run_histop(&[
+            "--durations",
+            "-c",
+            "1",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\nmake,3,147,40,"));
+    }
+
+    #[test]
+    fn test_zsh_histdb_time_range() {
+        let path = fixtures_path().join("zsh_histdb.db");
+        let output = run_histop(&[
+            "--since",
+            "2026-01-06",
+            "--utc",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\nmake,3,"));
+    }
+}
+
+mod xonsh_history {
+    use super::*;
+
+    #[test]
+    fn test_xonsh_directory_ranking() {
+        let path = fixtures_path().join("xonsh");
+        let output = run_histop(&["-o", "csv", "-f", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.starts_with("command,count,percentage\ngit,3,30.00\n"));
+        assert!(stdout.contains("\ncargo,2,"));
+        assert!(stdout.contains("\nmake,1,"));
+        assert!(stdout.contains("\nhead,1,"));
+    }
+
+    #[test]
+    fn test_xonsh_durations() {
+        let path = fixtures_path().join("xonsh");
+        let output = run_histop(&[
+            "--durations",
+            "-c",
+            "1",
+            "-o",
+            "csv",
+            "-f",
+            path.to_str().unwrap(),
+        ]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("\ncargo,2,53,26,"));
+    }
+
+    #[test]
+    fn test_directory_without_xonsh_history() {
+        let path = fixtures_path().join("nushell");
+        let output = run_histop(&["-f", path.to_str().unwrap()]);
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("no xonsh JSON history"));
+    }
+}
+
 mod invalid_options {
     use super::*;
 
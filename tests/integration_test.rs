@@ -68,6 +68,254 @@ mod zsh_history {
     }
 }
 
+mod bash_timestamped_history {
+    use super::*;
+    use histop::history::{HistoryFormat, for_each_entry};
+
+    #[test]
+    fn test_multiline_entries_keep_their_timestamp() {
+        let path = fixtures_path().join("bash_timestamped_history");
+        let mut entries = Vec::new();
+        for_each_entry(
+            HistoryFormat::BashTimestamped,
+            path.to_str().unwrap(),
+            false,
+            |entry| entries.push((entry.command.to_string(), entry.timestamp)),
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 6);
+        assert_eq!(
+            entries[1],
+            (
+                "for f in *.log; do\n  gzip \"$f\"\ndone".to_string(),
+                Some(1767618000)
+            )
+        );
+        assert_eq!(
+            entries[4],
+            (
+                "git commit -m \"multi\nline message\"".to_string(),
+                Some(1767708000)
+            )
+        );
+        assert!(entries.iter().all(|(_, timestamp)| timestamp.is_some()));
+    }
+}
+
+mod nushell_history {
+    use super::*;
+    use histop::history::nushell;
+
+    #[test]
+    fn test_parse_nushell_plaintext() {
+        let path = fixtures_path().join("nushell/history.txt");
+        let mut commands = Vec::new();
+        nushell::for_each_entry(path.to_str().unwrap(), |entry| {
+            commands.push(entry.command.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(commands.len(), 7);
+        assert_eq!(
+            commands[4],
+            "def greet [name] {\n  print $\"hello ($name)\"\n}"
+        );
+    }
+
+    #[test]
+    fn test_parse_nushell_sqlite_metadata() {
+        let path = fixtures_path().join("nushell/history.sqlite3");
+        let mut entries = Vec::new();
+        nushell::for_each_sqlite_entry(path.to_str().unwrap(), |entry| {
+            entries.push((
+                entry.command.to_string(),
+                entry.timestamp,
+                entry.duration,
+                entry.cwd.map(str::to_string),
+                entry.exit_status,
+            ))
+        })
+        .unwrap();
+
+        assert_eq!(entries.len(), 6);
+        assert_eq!(
+            entries[1],
+            (
+                "cargo build".to_string(),
+                Some(1767614460),
+                Some(38),
+                Some("/home/user/work/foo".to_string()),
+                Some(0)
+            )
+        );
+        assert_eq!(entries[3].4, Some(101));
+    }
+}
+
+mod repl_history {
+    use super::*;
+    use histop::history::{Syntax, repl};
+
+    #[test]
+    fn test_parse_psql_multiline_query() {
+        let path = fixtures_path().join("repl/.psql_history");
+        let mut entries = Vec::new();
+        repl::for_each_entry(path.to_str().unwrap(), Syntax::Sql, |entry| {
+            entries.push((entry.command.to_string(), entry.syntax))
+        })
+        .unwrap();
+
+        assert_eq!(entries.len(), 9);
+        assert_eq!(entries[0], ("\\dt".to_string(), Syntax::Sql));
+        assert_eq!(
+            entries[4].0,
+            "select o.id,\n       u.email\nfrom orders o join users u on u.id = o.user_id;"
+        );
+    }
+}
+
+mod ksh_history {
+    use super::*;
+    use histop::history::ksh;
+
+    #[test]
+    fn test_parse_ksh_and_mksh_fixtures_alike() {
+        let mut ksh_entries = Vec::new();
+        let path = fixtures_path().join("ksh_history");
+        ksh::for_each_entry(path.to_str().unwrap(), |entry| {
+            ksh_entries.push(entry.command.to_string())
+        })
+        .unwrap();
+
+        let mut mksh_entries = Vec::new();
+        let path = fixtures_path().join("mksh_history");
+        ksh::for_each_mksh_entry(path.to_str().unwrap(), |entry| {
+            mksh_entries.push(entry.command.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(ksh_entries.len(), 7);
+        assert_eq!(ksh_entries[0], "ls -la");
+        assert_eq!(ksh_entries[5], "for f in *.log\ndo\n  gzip $f\ndone");
+        assert_eq!(ksh_entries, mksh_entries);
+    }
+}
+
+mod database_history {
+    use super::*;
+    use histop::history::{atuin, histdb};
+
+    #[test]
+    fn test_parse_atuin_metadata() {
+        let path = fixtures_path().join("atuin_history.db");
+        let mut entries = Vec::new();
+        atuin::for_each_entry(path.to_str().unwrap(), |entry| {
+            entries.push((
+                entry.command.to_string(),
+                entry.timestamp,
+                entry.duration,
+                entry.cwd.map(str::to_string),
+                entry.exit_status,
+                entry.hostname.map(str::to_string),
+            ))
+        })
+        .unwrap();
+
+        // The deleted `rm -rf build` entry is skipped
+        assert_eq!(entries.len(), 6);
+        assert!(entries.iter().all(|entry| entry.0 != "rm -rf build"));
+        assert_eq!(
+            entries[1],
+            (
+                "cargo build".to_string(),
+                Some(1767614460),
+                Some(38),
+                Some("/home/user/work/foo".to_string()),
+                Some(0),
+                Some("laptop:user".to_string())
+            )
+        );
+        assert_eq!(entries[2].4, Some(101));
+        // Still running when recorded
+        assert_eq!(entries[5].0, "vim notes.md");
+        assert_eq!(entries[5].2, None);
+    }
+
+    #[test]
+    fn test_parse_zsh_histdb_metadata() {
+        let path = fixtures_path().join("zsh_histdb.db");
+        let mut entries = Vec::new();
+        histdb::for_each_entry(path.to_str().unwrap(), |entry| {
+            entries.push((
+                entry.command.to_string(),
+                entry.timestamp,
+                entry.duration,
+                entry.cwd.map(str::to_string),
+                entry.exit_status,
+                entry.session.map(str::to_string),
+            ))
+        })
+        .unwrap();
+
+        assert_eq!(entries.len(), 6);
+        assert_eq!(
+            entries[1],
+            (
+                "make -j8".to_string(),
+                Some(1767700010),
+                Some(95),
+                Some("/home/user/src/proj".to_string()),
+                Some(0),
+                Some("1".to_string())
+            )
+        );
+        assert_eq!(entries[2].4, Some(2));
+        assert_eq!(entries[4].3.as_deref(), Some("/home/user"));
+        assert_eq!(entries[5].2, None);
+    }
+}
+
+mod xonsh_history {
+    use super::*;
+    use histop::history::xonsh;
+
+    #[test]
+    fn test_parse_xonsh_directory_in_session_order() {
+        let path = fixtures_path().join("xonsh");
+        let mut entries = Vec::new();
+        xonsh::for_each_entry(path.to_str().unwrap(), |entry| {
+            entries.push((
+                entry.command.to_string(),
+                entry.timestamp,
+                entry.duration,
+                entry.exit_status,
+            ))
+        })
+        .unwrap();
+
+        assert_eq!(entries.len(), 8);
+        // The session that started first is read first, whatever its name
+        assert_eq!(
+            entries[0],
+            ("ls -la".to_string(), Some(1704110400), Some(0), Some(0))
+        );
+        assert_eq!(entries[1].0, "for f in $(ls).split():\n    echo @(f)");
+        assert_eq!(entries[3].0, "make -j8");
+        assert_eq!(
+            entries[5],
+            (
+                "cargo build --release".to_string(),
+                Some(1704196810),
+                Some(42),
+                Some(0)
+            )
+        );
+        assert_eq!(entries[6].0, "git log --oneline | head");
+        assert_eq!(entries[7].3, Some(101));
+    }
+}
+
 mod fish_history {
     use super::*;
 
@@ -194,4 +442,11 @@ mod utils {
             SplitCommands::new("cat file | grep pattern | wc -l").collect();
         assert_eq!(parts, vec!["cat file ", " grep pattern ", " wc -l"]);
     }
+
+    #[test]
+    fn test_split_commands_splits_on_list_operators() {
+        let parts: Vec<&str> =
+            SplitCommands::new("cd foo; make && ./run || true").collect();
+        assert_eq!(parts, vec!["cd foo", " make ", " ./run ", " true"]);
+    }
 }